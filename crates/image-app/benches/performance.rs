@@ -1,5 +1,5 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
-use image::{ImageBuffer, Rgb, DynamicImage, ImageFormat};
+use image::{ImageBuffer, Rgb, Rgba, DynamicImage, ImageFormat, Rgba32FImage, RgbaImage};
 use image_app::*;
 use std::io::Cursor;
 
@@ -44,7 +44,7 @@ fn bench_basic_adjustments(c: &mut Criterion) {
             BenchmarkId::new("saturation", size),
             &image_data,
             |b, data| {
-                b.iter(|| adjust_saturation(black_box(data), black_box(25.0)))
+                b.iter(|| adjust_saturation(black_box(data), black_box(25.0), None))
             },
         );
         
@@ -180,7 +180,7 @@ fn bench_filters(c: &mut Criterion) {
             BenchmarkId::new("sharpen", size),
             &image_data,
             |b, data| {
-                b.iter(|| sharpen(black_box(data), black_box(1.5)))
+                b.iter(|| sharpen(black_box(data), black_box(1.5), None))
             },
         );
         
@@ -204,7 +204,7 @@ fn bench_filters(c: &mut Criterion) {
             BenchmarkId::new("emboss", size),
             &image_data,
             |b, data| {
-                b.iter(|| apply_emboss(black_box(data)))
+                b.iter(|| apply_emboss(black_box(data), None))
             },
         );
         
@@ -220,7 +220,15 @@ fn bench_filters(c: &mut Criterion) {
             BenchmarkId::new("noise_reduction", size),
             &image_data,
             |b, data| {
-                b.iter(|| reduce_noise(black_box(data), black_box(0.3)))
+                b.iter(|| reduce_noise(black_box(data), black_box(0.3), None))
+            },
+        );
+        
+        group.bench_with_input(
+            BenchmarkId::new("denoise", size),
+            &image_data,
+            |b, data| {
+                b.iter(|| denoise(black_box(data), black_box(30.0), black_box(50.0), black_box(2)))
             },
         );
     }
@@ -246,7 +254,7 @@ fn bench_transforms(c: &mut Criterion) {
             BenchmarkId::new("rotate_arbitrary", size),
             &image_data,
             |b, data| {
-                b.iter(|| rotate_arbitrary(black_box(data), black_box(45.0)))
+                b.iter(|| rotate_arbitrary(black_box(data), black_box(45.0), None, None, None))
             },
         );
         
@@ -320,6 +328,150 @@ fn bench_memory_intensive(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_chained_editing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("chained_editing");
+    
+    for size in [1024, 2048].iter() {
+        let image_data = create_test_image(*size, *size);
+        
+        group.bench_with_input(
+            BenchmarkId::new("byte_exports", size),
+            &image_data,
+            |b, data| {
+                b.iter(|| {
+                    let step = adjust_brightness(black_box(data), black_box(20)).unwrap();
+                    let step = adjust_contrast(&step, black_box(1.2)).unwrap();
+                    let step = adjust_exposure(&step, black_box(0.5)).unwrap();
+                    adjust_vibrance(&step, black_box(30.0)).unwrap()
+                })
+            },
+        );
+        
+        group.bench_with_input(
+            BenchmarkId::new("editor_image", size),
+            &image_data,
+            |b, data| {
+                b.iter(|| {
+                    let mut editor = EditorImage::decode(black_box(data)).unwrap();
+                    editor.apply_operation(&Operation::Brightness { value: black_box(20) }).unwrap();
+                    editor.apply_operation(&Operation::Contrast { value: black_box(1.2) }).unwrap();
+                    editor.apply_operation(&Operation::Exposure { stops: black_box(0.5) }).unwrap();
+                    editor.apply_operation(&Operation::Vibrance { amount: black_box(30.0) }).unwrap();
+                    editor.encode().unwrap()
+                })
+            },
+        );
+    }
+    
+    group.finish();
+}
+
+fn bench_per_pixel(c: &mut Criterion) {
+    use image_app::adjustments as adj;
+    use image_app::filters;
+    use image_app::pixel::{EditorPixel, Image};
+
+    // Lanes are fixed at compile time: run once with and once without `--features simd`
+    // to compare scalar and SIMD (only sepia and white balance have SIMD lanes);
+    // each run compares one thread with all of them.
+    let lanes = if cfg!(feature = "simd") { "simd" } else { "scalar" };
+    let single_thread = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+
+    type Edit<P> = fn(&mut Image<P>);
+
+    fn operations<P: EditorPixel>() -> Vec<(&'static str, Edit<P>)> {
+        vec![
+            ("brightness", |img| adj::adjust_brightness(img, 20).unwrap()),
+            ("exposure", |img| adj::adjust_exposure(img, 0.5).unwrap()),
+            ("hue", |img| adj::adjust_hue(img, 30.0).unwrap()),
+            ("vibrance", |img| adj::adjust_vibrance(img, 30.0).unwrap()),
+            ("sepia", |img| filters::apply_sepia(img).unwrap()),
+            ("levels", |img| adj::adjust_levels(img, 10, 240, 1.2).unwrap()),
+            ("curves", |img| adj::adjust_curves(img, 1.1, 1.0, 0.9).unwrap()),
+            ("white_balance", |img| adj::adjust_white_balance(img, 30.0).unwrap()),
+        ]
+    }
+
+    fn run<P: EditorPixel>(
+        group: &mut criterion::BenchmarkGroup<criterion::measurement::WallTime>,
+        pool: Option<&rayon::ThreadPool>,
+        label: &str,
+        img: &Image<P>,
+    ) {
+        for (name, operation) in operations::<P>() {
+            group.bench_with_input(BenchmarkId::new(format!("{}/{}", name, label), img.width()), img, |b, img| {
+                b.iter_batched_ref(
+                    || img.clone(),
+                    |img| match pool {
+                        Some(pool) => pool.install(|| operation(black_box(img))),
+                        None => operation(black_box(img)),
+                    },
+                    criterion::BatchSize::LargeInput,
+                )
+            });
+        }
+    }
+
+    let mut group = c.benchmark_group("per_pixel");
+    group.sample_size(20);
+
+    for size in [1024u32, 2048].iter() {
+        let float: Rgba32FImage = ImageBuffer::from_fn(*size, *size, |x, y| {
+            Rgba([x as f32 / *size as f32, y as f32 / *size as f32, ((x + y) % 256) as f32 / 255.0, 1.0])
+        });
+        let eight_bit: RgbaImage = ImageBuffer::from_fn(*size, *size, |x, y| {
+            Rgba([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8, 255])
+        });
+
+        for (pool, threads) in [(Some(&single_thread), "1-thread"), (None, "parallel")] {
+            run(&mut group, pool, &format!("f32-{}-{}", lanes, threads), &float);
+            // 8-bit buffers go through lookup tables and never use SIMD lanes
+            run(&mut group, pool, &format!("u8-{}", threads), &eight_bit);
+        }
+    }
+
+    group.finish();
+}
+
+fn bench_point_stack(c: &mut Criterion) {
+    let mut group = c.benchmark_group("point_stack");
+    group.sample_size(20);
+
+    // Twelve sliders: separate passes against the fused runs, with vibrance and
+    // the OKLCh saturation boost on their own in between
+    let sliders = image_app::pipeline::Pipeline::from_json(
+        r#"{ "version": 2, "operations": [
+            { "op": "exposure", "stops": 0.3 }, { "op": "contrast", "value": 10 },
+            { "op": "highlights", "amount": -20 }, { "op": "shadows", "amount": 25 },
+            { "op": "white_balance", "value": 10 }, { "op": "vibrance", "amount": 20 },
+            { "op": "saturation", "value": 10 }, { "op": "levels", "black_point": 4, "white_point": 250, "gamma": 1.05 },
+            { "op": "brightness", "value": 5 }, { "op": "curves", "red_gamma": 1.05, "green_gamma": 1.0, "blue_gamma": 0.95 },
+            { "op": "hue", "shift": 4 }, { "op": "sepia" }
+        ] }"#,
+    )
+    .unwrap();
+    let compiled = sliders.compile().unwrap();
+    assert_eq!(compiled.passes(), 4);
+
+    for size in [1024u32, 2048].iter() {
+        let img: Rgba32FImage = ImageBuffer::from_fn(*size, *size, |x, y| {
+            Rgba([x as f32 / *size as f32, y as f32 / *size as f32, ((x + y) % 256) as f32 / 255.0, 1.0])
+        });
+
+        group.bench_with_input(BenchmarkId::new("separate", size), &img, |b, img| {
+            b.iter_batched_ref(|| img.clone(), |img| sliders.execute(black_box(img)).unwrap(), criterion::BatchSize::LargeInput)
+        });
+        group.bench_with_input(BenchmarkId::new("fused", size), &img, |b, img| {
+            b.iter_batched_ref(|| img.clone(), |img| compiled.execute(black_box(img)).unwrap(), criterion::BatchSize::LargeInput)
+        });
+        group.bench_with_input(BenchmarkId::new("compile_and_fused", size), &img, |b, img| {
+            b.iter_batched_ref(|| img.clone(), |img| sliders.compile().unwrap().execute(black_box(img)).unwrap(), criterion::BatchSize::LargeInput)
+        });
+    }
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_basic_adjustments,
@@ -327,6 +479,9 @@ criterion_group!(
     bench_professional_tools,
     bench_filters,
     bench_transforms,
-    bench_memory_intensive
+    bench_memory_intensive,
+    bench_chained_editing,
+    bench_per_pixel,
+    bench_point_stack
 );
 criterion_main!(benches);
\ No newline at end of file
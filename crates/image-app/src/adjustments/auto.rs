@@ -0,0 +1,292 @@
+//! Auto tone and white balance analysis
+//!
+//! [`analyze_auto_adjustments`] measures an image and recommends values for the
+//! existing sliders instead of changing pixels, so an "Auto" button can set the
+//! sliders and the user can fine-tune from there:
+//!
+//! - white balance: the mean of a gray-world estimate (the scene averages to
+//!   gray) and a white-patch estimate (the brightest unclipped pixels are white)
+//! - exposure: brings the median luminance to middle gray (18% linear)
+//! - highlights and shadows: from the share of pixels near white and near black
+//!   once the exposure is applied
+//! - contrast: scales the spread of the tones towards a typical spread
+//! - vibrance: lifts dull colors, never lowers saturation or colors black and white images
+//!
+//! Large images are measured on an evenly spaced subset of about 250 000
+//! pixels; fully transparent pixels are skipped.
+
+use serde::{Deserialize, Serialize};
+
+use super::white_balance::{WhiteBalance, NEUTRAL_TEMPERATURE};
+use crate::color::{linear_to_srgb, linear_to_xy, luminance, matrix_apply, temperature_tint, to_linear, TEMPERATURE_RANGE};
+use crate::pipeline::Operation;
+use crate::pixel::{EditorPixel, Image};
+use crate::statistics::sample_step;
+use crate::utils::rgb_to_hsv;
+
+/// Pixels measured at most, before subsampling kicks in
+const SAMPLE_PIXELS: u64 = 1 << 18;
+/// Linear luminance of middle gray
+const MIDDLE_GRAY: f32 = 0.18;
+/// Standard deviation of the encoded lightness of a well-spread image
+const TYPICAL_SPREAD: f32 = 0.22;
+/// Mean HSV saturation below which colors count as dull
+const TYPICAL_SATURATION: f32 = 0.3;
+
+/// Recommended slider values, all zero (and a neutral white balance) when
+/// nothing needs changing
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AutoAdjustments {
+    /// Exposure in stops (-2.0 to +2.0), as for [`super::adjust_exposure`]
+    pub exposure: f32,
+    /// Contrast (-30 to +50), as for [`super::adjust_contrast`]
+    pub contrast: f32,
+    /// Highlights (-60 to +30), as for [`super::adjust_highlights`]
+    pub highlights: f32,
+    /// Shadows (0 to +60), as for [`super::adjust_shadows`]
+    pub shadows: f32,
+    /// Light to neutralize, as for [`super::apply_white_balance`]
+    pub white_balance: WhiteBalance,
+    /// Vibrance (0 to +50), as for [`super::adjust_vibrance`]
+    pub vibrance: f32,
+}
+
+impl Default for AutoAdjustments {
+    fn default() -> AutoAdjustments {
+        AutoAdjustments {
+            exposure: 0.0,
+            contrast: 0.0,
+            highlights: 0.0,
+            shadows: 0.0,
+            white_balance: WhiteBalance::default(),
+            vibrance: 0.0,
+        }
+    }
+}
+
+impl AutoAdjustments {
+    /// The recommendation as pipeline steps, leaving out sliders that stay at zero
+    ///
+    /// White balance comes first and the tonal steps follow in the order they
+    /// were measured for.
+    pub fn operations(&self) -> Vec<Operation> {
+        let mut operations = Vec::new();
+        if !self.white_balance.is_identity() {
+            operations.push(Operation::ColorTemperature(self.white_balance));
+        }
+        if self.exposure != 0.0 {
+            operations.push(Operation::Exposure { stops: self.exposure });
+        }
+        if self.highlights != 0.0 {
+            operations.push(Operation::Highlights { amount: self.highlights });
+        }
+        if self.shadows != 0.0 {
+            operations.push(Operation::Shadows { amount: self.shadows });
+        }
+        if self.contrast != 0.0 {
+            operations.push(Operation::Contrast { value: self.contrast });
+        }
+        if self.vibrance != 0.0 {
+            operations.push(Operation::Vibrance { amount: self.vibrance });
+        }
+        operations
+    }
+}
+
+/// Recommend slider values for an image without changing it
+///
+/// # Arguments
+/// * `img` - Image to measure
+///
+/// # Returns
+/// Recommended settings (see the [module documentation](self) for how each is chosen)
+pub fn analyze_auto_adjustments<P: EditorPixel>(img: &Image<P>) -> AutoAdjustments {
+    let samples = sample_linear(img);
+    if samples.is_empty() {
+        return AutoAdjustments::default();
+    }
+
+    let white_balance = estimate_white_balance(&samples);
+
+    // Tones are judged after the white balance, which keeps white at or below 1.0
+    let matrix = white_balance.matrix();
+    let mut luminances: Vec<f32> = samples
+        .iter()
+        .map(|&rgb| {
+            let [r, g, b] = matrix_apply(&matrix, rgb);
+            luminance(r, g, b).max(0.0)
+        })
+        .collect();
+    luminances.sort_by(f32::total_cmp);
+    let median = percentile(&luminances, 0.5);
+
+    let exposure = if median > 0.0 {
+        round_to((MIDDLE_GRAY / median).log2().clamp(-2.0, 2.0), 0.05)
+    } else {
+        2.0
+    };
+
+    // Encoded lightness of every sample once the exposure is applied
+    let gain = exposure.exp2();
+    let lightness: Vec<f32> = luminances.iter().map(|&value| linear_to_srgb((value * gain).min(1.0))).collect();
+    let share = |predicate: fn(f32) -> bool| lightness.iter().filter(|&&value| predicate(value)).count() as f32 / lightness.len() as f32;
+
+    let bright = percentile(&lightness, 0.99);
+    let highlights = if bright > 0.9 {
+        -((bright - 0.9) / 0.1 * 40.0 + share(|value| value >= 0.99) * 200.0).min(60.0)
+    } else if bright < 0.75 {
+        ((0.75 - bright) / 0.75 * 60.0).min(30.0)
+    } else {
+        0.0
+    };
+    let shadows = ((share(|value| value < 0.15) - 0.1) * 150.0).clamp(0.0, 60.0);
+
+    let mean = lightness.iter().sum::<f32>() / lightness.len() as f32;
+    let spread = (lightness.iter().map(|value| (value - mean).powi(2)).sum::<f32>() / lightness.len() as f32).sqrt();
+    let contrast = if spread > 1e-3 {
+        // `adjust_contrast` scales deviations from mid gray by ((100 + value) / 100)²
+        (100.0 * ((TYPICAL_SPREAD / spread).sqrt() - 1.0)).clamp(-30.0, 50.0)
+    } else {
+        0.0
+    };
+
+    let saturation = samples
+        .iter()
+        .map(|&[r, g, b]| rgb_to_hsv(linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b)).1)
+        .sum::<f32>()
+        / samples.len() as f32;
+    // Black and white images have no colors to lift
+    let vibrance = if saturation > 0.02 {
+        ((TYPICAL_SATURATION - saturation) / TYPICAL_SATURATION * 50.0).clamp(0.0, 50.0)
+    } else {
+        0.0
+    };
+
+    AutoAdjustments {
+        exposure,
+        contrast: contrast.round(),
+        highlights: highlights.round(),
+        shadows: shadows.round(),
+        white_balance,
+        vibrance: vibrance.round(),
+    }
+}
+
+/// Linear RGB of an evenly spaced subset of the visible pixels
+fn sample_linear<P: EditorPixel>(img: &Image<P>) -> Vec<[f32; 3]> {
+    let step = sample_step(img.width() as u64 * img.height() as u64, SAMPLE_PIXELS);
+
+    let mut samples = Vec::new();
+    for y in (0..img.height()).step_by(step as usize) {
+        for x in (0..img.width()).step_by(step as usize) {
+            let [r, g, b, a] = to_linear(img.get_pixel(x, y).to_unit());
+            if a > 0.0 {
+                samples.push([r, g, b]);
+            }
+        }
+    }
+    samples
+}
+
+/// Mean of the gray-world and white-patch estimates of the light
+fn estimate_white_balance(samples: &[[f32; 3]]) -> WhiteBalance {
+    let normalized = |[r, g, b]: [f32; 3]| {
+        let level = luminance(r, g, b);
+        (level > 1e-4).then(|| [r / level, g / level, b / level])
+    };
+
+    let count = samples.len() as f32;
+    let gray_world = samples.iter().fold([0.0f32; 3], |sum, rgb| std::array::from_fn(|c| sum[c] + rgb[c] / count));
+
+    // Brightest 1% of the pixels that have no clipped channel
+    let mut unclipped: Vec<[f32; 3]> = samples.iter().copied().filter(|rgb| rgb.iter().all(|&c| c < 0.99)).collect();
+    unclipped.sort_by(|a, b| luminance(b[0], b[1], b[2]).total_cmp(&luminance(a[0], a[1], a[2])));
+    let brightest = &unclipped[..unclipped.len().div_ceil(100)];
+    let white_patch = brightest.iter().fold([0.0f32; 3], |sum, rgb| std::array::from_fn(|c| sum[c] + rgb[c]));
+
+    let estimates: Vec<[f32; 3]> = [normalized(gray_world), normalized(white_patch)].into_iter().flatten().collect();
+    if estimates.is_empty() {
+        return WhiteBalance::default();
+    }
+    let light: [f32; 3] = std::array::from_fn(|c| estimates.iter().map(|rgb| rgb[c]).sum::<f32>() / estimates.len() as f32);
+
+    match linear_to_xy(light) {
+        Some(xy) => {
+            let (temperature, tint) = temperature_tint(xy);
+            let temperature = temperature.clamp(*TEMPERATURE_RANGE.start(), *TEMPERATURE_RANGE.end());
+            // Close enough to neutral that a change would only add noise
+            if (1e6 / temperature - 1e6 / NEUTRAL_TEMPERATURE).abs() < 5.0 && tint.abs() < 2.0 {
+                return WhiteBalance::default();
+            }
+            WhiteBalance { temperature: temperature.round(), tint: tint.clamp(-100.0, 100.0).round() }
+        }
+        None => WhiteBalance::default(),
+    }
+}
+
+/// Value at `fraction` (0.0-1.0) of sorted values
+fn percentile(sorted: &[f32], fraction: f32) -> f32 {
+    sorted[((sorted.len() - 1) as f32 * fraction).round() as usize]
+}
+
+fn round_to(value: f32, step: f32) -> f32 {
+    (value / step).round() * step
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{chromatic_adaptation, from_linear, white_point, D65};
+    use image::{Rgba, Rgba32FImage};
+
+    /// A dark scene under warm light: a gradient of gray surfaces and a few colored ones
+    fn dark_warm_scene() -> Rgba32FImage {
+        let cast = chromatic_adaptation(D65, white_point(3400.0, 0.0));
+        Rgba32FImage::from_fn(64, 64, |x, y| {
+            let gray = 0.01 + x as f32 / 63.0 * 0.2;
+            let surface = match y % 4 {
+                0 => [gray * 1.4, gray * 0.8, gray * 0.8],
+                1 => [gray * 0.8, gray * 0.8, gray * 1.4],
+                _ => [gray; 3],
+            };
+            let [r, g, b] = matrix_apply(&cast, surface);
+            Rgba(from_linear([r, g, b, 1.0]))
+        })
+    }
+
+    #[test]
+    fn test_recommends_brighter_neutral_settings() {
+        let img = dark_warm_scene();
+        let auto = analyze_auto_adjustments(&img);
+        assert!(auto.exposure > 0.5, "{:?}", auto);
+        assert!((auto.white_balance.temperature - 3400.0).abs() < 300.0, "{:?}", auto);
+
+        // Applying the recommendation moves the image towards neutral mid tones
+        let mut fixed = img.clone();
+        for operation in auto.operations() {
+            operation.apply(&mut fixed).unwrap();
+        }
+        let again = analyze_auto_adjustments(&fixed);
+        assert!(again.exposure.abs() < auto.exposure.abs(), "{:?}", again);
+        let [r, _, b, _] = fixed.get_pixel(40, 2).0;
+        assert!((r - b).abs() < 0.03, "{:?}", fixed.get_pixel(40, 2));
+
+        // The image itself is untouched by the analysis
+        assert_eq!(analyze_auto_adjustments(&img), auto);
+    }
+
+    #[test]
+    fn test_balanced_image_needs_little() {
+        let img = Rgba32FImage::from_fn(64, 64, |x, y| {
+            let level = (x + y) as f32 / 126.0;
+            Rgba([level, level, level, 1.0])
+        });
+        let auto = analyze_auto_adjustments(&img);
+        assert!(auto.white_balance.is_identity(), "{:?}", auto);
+        assert!(auto.exposure.abs() <= 0.3, "{:?}", auto);
+
+        let transparent = Rgba32FImage::new(4, 4);
+        assert_eq!(analyze_auto_adjustments(&transparent), AutoAdjustments::default());
+    }
+}
@@ -0,0 +1,202 @@
+//! Contrast-limited adaptive histogram equalization (CLAHE)
+//!
+//! The image is split into a grid of tiles and each tile gets its own
+//! equalization curve, so contrast is raised where it is locally low instead of
+//! by one global curve. Each tile histogram is clipped at `clip_limit` times the
+//! average bin count and the excess spread over all bins, which caps how steep a
+//! curve can get and keeps flat areas such as sky from turning into noise.
+//! Every pixel blends the curves of its four nearest tile centers bilinearly,
+//! so no tile edges show.
+//!
+//! Only OKLab lightness is equalized; the a and b axes are kept, so colors do
+//! not shift the way equalizing R, G and B separately does:
+//!
+//! ```json
+//! { "tiles_x": 8, "tiles_y": 8, "clip_limit": 2.0 }
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::{from_linear, linear_to_oklab, oklab_to_linear_in_gamut, to_linear};
+use crate::error::{ensure_finite, EditorError};
+use crate::pixel::{map_pixels_at, EditorPixel, Image};
+
+/// Histogram bins per tile
+const BINS: usize = 256;
+
+/// Tile grid and clip limit of [`super::clahe`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClaheOptions {
+    /// Tile columns (1 to 64)
+    pub tiles_x: u32,
+    /// Tile rows (1 to 64)
+    pub tiles_y: u32,
+    /// Highest bin count relative to the average (1.0 to 100.0, 1.0 = no change)
+    pub clip_limit: f32,
+}
+
+impl Default for ClaheOptions {
+    fn default() -> ClaheOptions {
+        ClaheOptions { tiles_x: 8, tiles_y: 8, clip_limit: 2.0 }
+    }
+}
+
+impl ClaheOptions {
+    /// Parse options from JSON
+    pub fn from_json(json: &str) -> Result<ClaheOptions, EditorError> {
+        let options: ClaheOptions =
+            serde_json::from_str(json).map_err(|e| EditorError::invalid_parameter("clahe", e.to_string()))?;
+        options.validate()?;
+        Ok(options)
+    }
+
+    /// Check the grid size and clip limit
+    pub fn validate(&self) -> Result<(), EditorError> {
+        for (name, tiles) in [("tiles_x", self.tiles_x), ("tiles_y", self.tiles_y)] {
+            if !(1..=64).contains(&tiles) {
+                return Err(EditorError::invalid_parameter(name, "must be between 1 and 64"));
+            }
+        }
+        ensure_finite("clip_limit", self.clip_limit)?;
+        if !(1.0..=100.0).contains(&self.clip_limit) {
+            return Err(EditorError::invalid_parameter("clip_limit", "must be between 1 and 100"));
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn apply<P: EditorPixel>(img: &mut Image<P>, options: &ClaheOptions) {
+    let (width, height) = img.dimensions();
+    // At 1.0 every curve is clipped flat to the identity, up to rounding
+    if width == 0 || height == 0 || options.clip_limit <= 1.0 {
+        return;
+    }
+    // A tile needs at least one pixel
+    let tiles_x = options.tiles_x.min(width) as usize;
+    let tiles_y = options.tiles_y.min(height) as usize;
+    let tile_width = width as f32 / tiles_x as f32;
+    let tile_height = height as f32 / tiles_y as f32;
+
+    let lab: Vec<[f32; 4]> = img
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b, a] = to_linear(pixel.to_unit());
+            let [l, lab_a, lab_b] = linear_to_oklab([r.max(0.0), g.max(0.0), b.max(0.0)]);
+            [l, lab_a, lab_b, a]
+        })
+        .collect();
+    let position_in_bins = |lightness: f32| lightness.clamp(0.0, 1.0) * (BINS - 1) as f32;
+    let bin = |lightness: f32| (position_in_bins(lightness).round() as usize).min(BINS - 1);
+
+    // Equalization curve of every tile
+    let mut histograms = vec![[0u32; BINS]; tiles_x * tiles_y];
+    for (index, &[l, ..]) in lab.iter().enumerate() {
+        let x = index % width as usize;
+        let y = index / width as usize;
+        let tile_column = ((x as f32 / tile_width) as usize).min(tiles_x - 1);
+        let tile_row = ((y as f32 / tile_height) as usize).min(tiles_y - 1);
+        histograms[tile_row * tiles_x + tile_column][bin(l)] += 1;
+    }
+    let curves: Vec<[f32; BINS]> = histograms.iter().map(|histogram| clipped_curve(histogram, options.clip_limit)).collect();
+
+    // Fractional tile coordinate of a pixel center: lower tile and weight of the next one
+    let position = |coordinate: u32, tile_size: f32, tiles: usize| {
+        let center = ((coordinate as f32 + 0.5) / tile_size - 0.5).clamp(0.0, (tiles - 1) as f32);
+        let lower = (center as usize).min(tiles - 1);
+        (lower, (lower + 1).min(tiles - 1), center - lower as f32)
+    };
+
+    map_pixels_at(img, |x, y, _| {
+        let [l, lab_a, lab_b, alpha] = lab[(y * width + x) as usize];
+
+        let (left, right, tx) = position(x, tile_width, tiles_x);
+        let (top, bottom, ty) = position(y, tile_height, tiles_y);
+        // Lightness between two bins interpolates, so float images don't band
+        let level = position_in_bins(l);
+        let lower_bin = (level as usize).min(BINS - 2);
+        let t = level - lower_bin as f32;
+        let curve = |row: usize, column: usize| {
+            let curve = &curves[row * tiles_x + column];
+            curve[lower_bin] + (curve[lower_bin + 1] - curve[lower_bin]) * t
+        };
+        let upper = curve(top, left) + (curve(top, right) - curve(top, left)) * tx;
+        let lower = curve(bottom, left) + (curve(bottom, right) - curve(bottom, left)) * tx;
+        let lightness = upper + (lower - upper) * ty;
+
+        let [r, g, b] = oklab_to_linear_in_gamut([lightness, lab_a, lab_b]);
+        from_linear([r, g, b, alpha])
+    });
+}
+
+/// Cumulative curve of a histogram clipped at `clip_limit` times the mean bin
+///
+/// Each bin maps to the middle of its own step of the curve, so a tile of one
+/// flat level keeps its lightness as far as the clip limit allows.
+fn clipped_curve(histogram: &[u32; BINS], clip_limit: f32) -> [f32; BINS] {
+    let total: u32 = histogram.iter().sum();
+    if total == 0 {
+        return std::array::from_fn(|level| level as f32 / (BINS - 1) as f32);
+    }
+
+    let limit = clip_limit * total as f32 / BINS as f32;
+    let excess: f32 = histogram.iter().map(|&count| (count as f32 - limit).max(0.0)).sum();
+    let share = excess / BINS as f32;
+
+    let mut curve = [0.0; BINS];
+    let mut below = 0.0;
+    for (entry, &count) in curve.iter_mut().zip(histogram.iter()) {
+        let clipped = (count as f32).min(limit) + share;
+        *entry = (below + clipped / 2.0) / total as f32;
+        below += clipped;
+    }
+    curve
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, Rgba32FImage};
+
+    fn spread(img: &Rgba32FImage, x_range: std::ops::Range<u32>) -> f32 {
+        let values: Vec<f32> = x_range.map(|x| img.get_pixel(x, 8)[1]).collect();
+        values.iter().cloned().fold(f32::MIN, f32::max) - values.iter().cloned().fold(f32::MAX, f32::min)
+    }
+
+    #[test]
+    fn test_raises_local_contrast_without_color_shift() {
+        // A dark half and a bright half, each with faint detail
+        let mut img = Rgba32FImage::from_fn(64, 16, |x, _| {
+            let base = if x < 32 { 0.15 } else { 0.75 };
+            let detail = (x % 4) as f32 * 0.01;
+            Rgba([base + detail + 0.05, base + detail, base + detail, 1.0])
+        });
+        let before = (spread(&img, 4..28), spread(&img, 36..60));
+        apply(&mut img, &ClaheOptions { tiles_x: 4, tiles_y: 1, clip_limit: 4.0 });
+        assert!(spread(&img, 4..28) > before.0 * 2.0, "{} {}", before.0, spread(&img, 4..28));
+        assert!(spread(&img, 36..60) > before.1 * 2.0, "{} {}", before.1, spread(&img, 36..60));
+
+        // The reddish tint stays: red remains the strongest channel everywhere
+        assert!(img.pixels().all(|pixel| pixel[0] >= pixel[1] && (pixel[1] - pixel[2]).abs() < 1e-3));
+        assert!(img.pixels().all(|pixel| pixel[3] == 1.0));
+    }
+
+    #[test]
+    fn test_clip_limit_one_keeps_flat_tiles() {
+        let gray = Rgba32FImage::from_pixel(16, 16, Rgba([0.5, 0.5, 0.5, 1.0]));
+        let mut img = gray.clone();
+        apply(&mut img, &ClaheOptions { clip_limit: 1.0, ..ClaheOptions::default() });
+        assert!(img.pixels().zip(gray.pixels()).all(|(a, b)| (a[0] - b[0]).abs() < 0.01));
+
+        // 1.0 leaves any image exactly as it was
+        let ramp = Rgba32FImage::from_fn(32, 8, |x, y| Rgba([x as f32 / 31.0, y as f32 / 7.0, 0.3, 1.0]));
+        let mut img = ramp.clone();
+        apply(&mut img, &ClaheOptions { clip_limit: 1.0, ..ClaheOptions::default() });
+        assert_eq!(img, ramp);
+
+        let error = ClaheOptions::from_json(r#"{ "tiles_x": 0 }"#).unwrap_err();
+        assert_eq!(error.parameter(), Some("tiles_x"));
+        let error = ClaheOptions::from_json(r#"{ "clip_limit": 0.5 }"#).unwrap_err();
+        assert_eq!(error.parameter(), Some("clip_limit"));
+    }
+}
@@ -0,0 +1,226 @@
+//! Spline tone curves
+//!
+//! A curve is a list of control points `[input, output]` on the 0-255 scale,
+//! like the curve editor draws them. Points are joined with a monotonic cubic
+//! spline (Fritsch-Carlson), so the curve passes through every point without
+//! overshooting between them; beyond the first and last point it stays flat.
+//!
+//! [`ToneCurves`] holds a master curve plus one curve per channel. Each channel
+//! goes through its own curve and then the master curve:
+//!
+//! ```json
+//! { "master": [[0, 0], [64, 48], [192, 208], [255, 255]], "blue": [[0, 20], [255, 255]] }
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::EditorError;
+
+/// Control point of a tone curve: input and output level (0.0-255.0)
+pub type CurvePoint = [f32; 2];
+
+/// Master and per-channel tone curves, an empty list leaves that curve flat
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToneCurves {
+    /// Applied to R, G and B after their own curves
+    pub master: Vec<CurvePoint>,
+    pub red: Vec<CurvePoint>,
+    pub green: Vec<CurvePoint>,
+    pub blue: Vec<CurvePoint>,
+}
+
+/// Curves evaluated at evenly spaced inputs from 0 to 255, for drawing
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SampledCurves {
+    pub master: Vec<f32>,
+    pub red: Vec<f32>,
+    pub green: Vec<f32>,
+    pub blue: Vec<f32>,
+}
+
+impl ToneCurves {
+    /// Parse curves from JSON
+    pub fn from_json(json: &str) -> Result<ToneCurves, EditorError> {
+        let curves: ToneCurves =
+            serde_json::from_str(json).map_err(|e| EditorError::invalid_parameter("curves", e.to_string()))?;
+        curves.validate()?;
+        Ok(curves)
+    }
+
+    /// Check the control points of every curve
+    pub fn validate(&self) -> Result<(), EditorError> {
+        for (name, points) in self.named() {
+            Spline::new(name, points)?;
+        }
+        Ok(())
+    }
+
+    /// Whether no curve has control points
+    pub fn is_identity(&self) -> bool {
+        self.named().iter().all(|(_, points)| points.is_empty())
+    }
+
+    /// Sample every curve, e.g. to draw exactly what [`super::apply_tone_curves`] applies
+    ///
+    /// # Arguments
+    /// * `size` - Number of samples per curve (2 to 65536, usually 256 or 4096)
+    ///
+    /// # Returns
+    /// Output levels (0.0-255.0) for inputs spaced `255 / (size - 1)` apart
+    pub fn sample(&self, size: usize) -> Result<SampledCurves, EditorError> {
+        if !(2..=65536).contains(&size) {
+            return Err(EditorError::invalid_parameter("size", "must be between 2 and 65536"));
+        }
+        let [master, red, green, blue] = self.splines()?.map(|spline| spline.table(size, |value| value * 255.0));
+        Ok(SampledCurves { master, red, green, blue })
+    }
+
+    /// Lookup table per RGB channel, channel curve followed by the master curve,
+    /// mapping normalized levels (0.0-1.0) to normalized levels
+    pub(crate) fn channel_luts(&self, size: usize) -> Result<[Vec<f32>; 3], EditorError> {
+        let [master, red, green, blue] = self.splines()?;
+        Ok([red, green, blue].map(|channel| channel.table(size, |value| master.eval(value * 255.0) / 255.0)))
+    }
+
+    fn named(&self) -> [(&'static str, &[CurvePoint]); 4] {
+        [("master", &self.master), ("red", &self.red), ("green", &self.green), ("blue", &self.blue)]
+    }
+
+    fn splines(&self) -> Result<[Spline; 4], EditorError> {
+        let [master, red, green, blue] = self.named().map(|(name, points)| Spline::new(name, points));
+        Ok([master?, red?, green?, blue?])
+    }
+}
+
+/// Read a normalized level from a table built by [`ToneCurves::channel_luts`]
+#[inline]
+pub(crate) fn lookup(lut: &[f32], level: f32) -> f32 {
+    let position = level.clamp(0.0, 1.0) * (lut.len() - 1) as f32;
+    let lower = (position as usize).min(lut.len() - 2);
+    let t = position - lower as f32;
+    lut[lower] + (lut[lower + 1] - lut[lower]) * t
+}
+
+/// Monotonic cubic Hermite spline through sorted control points
+struct Spline {
+    xs: Vec<f32>,
+    ys: Vec<f32>,
+    slopes: Vec<f32>,
+}
+
+impl Spline {
+    fn new(name: &str, points: &[CurvePoint]) -> Result<Spline, EditorError> {
+        if points.is_empty() {
+            return Ok(Spline { xs: vec![0.0, 255.0], ys: vec![0.0, 255.0], slopes: vec![1.0, 1.0] });
+        }
+        if points.len() < 2 {
+            return Err(EditorError::invalid_parameter(name, "a curve needs at least two control points"));
+        }
+        if points.iter().flatten().any(|value| !(0.0..=255.0).contains(value)) {
+            return Err(EditorError::invalid_parameter(name, "control points must be between 0 and 255"));
+        }
+
+        let mut sorted = points.to_vec();
+        sorted.sort_by(|a, b| a[0].total_cmp(&b[0]));
+        if sorted.windows(2).any(|pair| pair[0][0] == pair[1][0]) {
+            return Err(EditorError::invalid_parameter(name, "control points need distinct inputs"));
+        }
+        let xs: Vec<f32> = sorted.iter().map(|point| point[0]).collect();
+        let ys: Vec<f32> = sorted.iter().map(|point| point[1]).collect();
+
+        // Secant slopes, then Fritsch-Carlson tangents that keep each segment monotonic
+        let deltas: Vec<f32> = (0..xs.len() - 1).map(|k| (ys[k + 1] - ys[k]) / (xs[k + 1] - xs[k])).collect();
+        let mut slopes = vec![0.0; xs.len()];
+        slopes[0] = deltas[0];
+        slopes[xs.len() - 1] = deltas[deltas.len() - 1];
+        for k in 1..xs.len() - 1 {
+            if deltas[k - 1] * deltas[k] > 0.0 {
+                slopes[k] = (deltas[k - 1] + deltas[k]) / 2.0;
+            }
+        }
+        for (k, &delta) in deltas.iter().enumerate() {
+            if delta == 0.0 {
+                slopes[k] = 0.0;
+                slopes[k + 1] = 0.0;
+                continue;
+            }
+            let alpha = slopes[k] / delta;
+            let beta = slopes[k + 1] / delta;
+            let length = alpha.hypot(beta);
+            if length > 3.0 {
+                slopes[k] = 3.0 / length * alpha * delta;
+                slopes[k + 1] = 3.0 / length * beta * delta;
+            }
+        }
+
+        Ok(Spline { xs, ys, slopes })
+    }
+
+    /// Output level (0.0-255.0) for an input level
+    fn eval(&self, x: f32) -> f32 {
+        let last = self.xs.len() - 1;
+        if x <= self.xs[0] {
+            return self.ys[0];
+        }
+        if x >= self.xs[last] {
+            return self.ys[last];
+        }
+
+        let k = self.xs.partition_point(|&knot| knot <= x) - 1;
+        let h = self.xs[k + 1] - self.xs[k];
+        let t = (x - self.xs[k]) / h;
+        let (t2, t3) = (t * t, t * t * t);
+        let y = (2.0 * t3 - 3.0 * t2 + 1.0) * self.ys[k]
+            + (t3 - 2.0 * t2 + t) * h * self.slopes[k]
+            + (-2.0 * t3 + 3.0 * t2) * self.ys[k + 1]
+            + (t3 - t2) * h * self.slopes[k + 1];
+        y.clamp(0.0, 255.0)
+    }
+
+    /// `size` samples over 0-255, each passed through `finish(output / 255)`
+    fn table(&self, size: usize, finish: impl Fn(f32) -> f32) -> Vec<f32> {
+        (0..size)
+            .map(|i| finish(self.eval(i as f32 * 255.0 / (size - 1) as f32) / 255.0))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s_curve() -> ToneCurves {
+        ToneCurves {
+            master: vec![[0.0, 0.0], [64.0, 40.0], [192.0, 215.0], [255.0, 255.0]],
+            ..ToneCurves::default()
+        }
+    }
+
+    #[test]
+    fn test_spline_is_monotonic_through_points() {
+        let sampled = s_curve().sample(4096).unwrap();
+        assert!(sampled.master.windows(2).all(|pair| pair[1] >= pair[0]));
+        assert!((sampled.master[0] - 0.0).abs() < 1e-4 && (sampled.master[4095] - 255.0).abs() < 1e-4);
+        assert!((s_curve().sample(256).unwrap().master[64] - 40.0).abs() < 1e-3);
+
+        // A flat stretch between equal outputs does not ring
+        let matte = ToneCurves { red: vec![[0.0, 30.0], [40.0, 30.0], [100.0, 30.0], [255.0, 240.0]], ..ToneCurves::default() };
+        let red = matte.sample(256).unwrap().red;
+        assert!(red[..=100].iter().all(|&level| (level - 30.0).abs() < 1e-3));
+        assert!((red[255] - 240.0).abs() < 1e-3);
+
+        let identity = ToneCurves::default().sample(256).unwrap();
+        assert!(identity.green.iter().enumerate().all(|(i, &level)| (level - i as f32).abs() < 1e-3));
+    }
+
+    #[test]
+    fn test_invalid_points() {
+        let curves = |json| ToneCurves::from_json(json).unwrap_err().parameter().map(String::from);
+        assert_eq!(curves(r#"{ "red": [[0, 0]] }"#).as_deref(), Some("red"));
+        assert_eq!(curves(r#"{ "master": [[0, 0], [0, 255]] }"#).as_deref(), Some("master"));
+        assert_eq!(curves(r#"{ "blue": [[0, 0], [300, 255]] }"#).as_deref(), Some("blue"));
+        assert_eq!(curves(r#"{ "alpha": [] }"#).as_deref(), Some("curves"));
+        assert_eq!(ToneCurves::default().sample(1).unwrap_err().parameter(), Some("size"));
+    }
+}
@@ -0,0 +1,222 @@
+//! Per-channel levels
+//!
+//! Each channel maps its input range (black point to white point) through a
+//! gamma curve onto an output range. Raising the output black point lifts the
+//! shadows for a faded print look; moving one channel's points fixes a color
+//! cast. As with Photoshop, the master levels are applied after the channel
+//! levels. All points are 8-bit levels:
+//!
+//! ```json
+//! { "master": { "output_black": 16 }, "blue": { "black_point": 12, "gamma": 0.9 } }
+//! ```
+//!
+//! [`Levels::from_histogram`] derives the input points from a histogram for
+//! auto levels.
+
+use serde::{Deserialize, Serialize};
+
+use super::check_levels;
+use crate::error::{ensure_finite, EditorError};
+
+/// Input range, gamma and output range of one channel
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChannelLevels {
+    /// Input level mapped to the output black (below `white_point`)
+    pub black_point: u8,
+    /// Input level mapped to the output white
+    pub white_point: u8,
+    /// Mid-tone gamma (0.1 to 3.0, 1.0 = no change)
+    pub gamma: f32,
+    /// Darkest output level
+    pub output_black: u8,
+    /// Brightest output level; below `output_black` inverts the channel
+    pub output_white: u8,
+}
+
+impl Default for ChannelLevels {
+    fn default() -> ChannelLevels {
+        ChannelLevels {
+            black_point: 0,
+            white_point: 255,
+            gamma: 1.0,
+            output_black: 0,
+            output_white: 255,
+        }
+    }
+}
+
+impl ChannelLevels {
+    /// Check the points and gamma
+    pub fn validate(&self) -> Result<(), EditorError> {
+        check_levels(self.black_point, self.white_point, self.gamma)
+    }
+
+    /// Whether these levels leave every value unchanged
+    pub fn is_identity(&self) -> bool {
+        *self == ChannelLevels::default()
+    }
+
+    /// Map a normalized level (0.0-1.0)
+    #[inline]
+    pub fn apply(&self, level: f32) -> f32 {
+        let black = self.black_point as f32 / 255.0;
+        let input_range = (self.white_point as f32 - self.black_point as f32) / 255.0;
+        let normalized = ((level - black) / input_range).clamp(0.0, 1.0);
+
+        let gamma = self.gamma.clamp(0.1, 3.0);
+        let curved = if gamma != 1.0 { normalized.powf(1.0 / gamma) } else { normalized };
+
+        let output_black = self.output_black as f32 / 255.0;
+        let output_white = self.output_white as f32 / 255.0;
+        output_black + (output_white - output_black) * curved
+    }
+}
+
+/// Master and per-channel levels
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Levels {
+    /// Applied to R, G and B after their own levels
+    pub master: ChannelLevels,
+    pub red: ChannelLevels,
+    pub green: ChannelLevels,
+    pub blue: ChannelLevels,
+}
+
+impl Levels {
+    /// Parse levels from JSON
+    pub fn from_json(json: &str) -> Result<Levels, EditorError> {
+        let levels: Levels =
+            serde_json::from_str(json).map_err(|e| EditorError::invalid_parameter("levels", e.to_string()))?;
+        levels.validate()?;
+        Ok(levels)
+    }
+
+    /// Check every channel, naming the failing one, e.g. `red.black_point`
+    pub fn validate(&self) -> Result<(), EditorError> {
+        for (name, channel) in [("master", &self.master), ("red", &self.red), ("green", &self.green), ("blue", &self.blue)] {
+            channel.validate().map_err(|e| e.within(name))?;
+        }
+        Ok(())
+    }
+
+    /// Whether these levels leave every value unchanged
+    pub fn is_identity(&self) -> bool {
+        *self == Levels::default()
+    }
+
+    /// Map a normalized level of an RGB channel (0 = red, 1 = green, 2 = blue)
+    #[inline]
+    pub fn apply(&self, channel: usize, level: f32) -> f32 {
+        let own = [&self.red, &self.green, &self.blue][channel];
+        self.master.apply(own.apply(level))
+    }
+
+    /// Auto levels: stretch each channel between its darkest and brightest levels
+    ///
+    /// Setting the points per channel also neutralizes a color cast. A channel
+    /// with a single level is left unchanged.
+    ///
+    /// # Arguments
+    /// * `histogram` - 768 bins as returned by [`super::calculate_histogram`]
+    /// * `clip_percent` - Share of pixels allowed to clip at each end (0.0 to 50.0)
+    ///
+    /// # Returns
+    /// Levels with the input points of each channel set
+    pub fn from_histogram(histogram: &[u32], clip_percent: f32) -> Result<Levels, EditorError> {
+        check_clip_percent(clip_percent)?;
+        if histogram.len() != 768 {
+            return Err(EditorError::invalid_parameter("histogram", "must have 768 bins"));
+        }
+
+        let [red, green, blue] = [0, 1, 2].map(|channel| {
+            let bins = &histogram[channel * 256..(channel + 1) * 256];
+            let total: u64 = bins.iter().map(|&count| count as u64).sum();
+            let clip = (total as f64 * clip_percent as f64 / 100.0) as u64;
+
+            let black_point = clip_point(bins.iter().enumerate(), clip);
+            let white_point = clip_point(bins.iter().enumerate().rev(), clip);
+            match (black_point, white_point) {
+                (Some(black_point), Some(white_point)) if black_point < white_point => ChannelLevels {
+                    black_point,
+                    white_point,
+                    ..ChannelLevels::default()
+                },
+                _ => ChannelLevels::default(),
+            }
+        });
+
+        Ok(Levels { red, green, blue, ..Levels::default() })
+    }
+}
+
+pub(crate) fn check_clip_percent(clip_percent: f32) -> Result<(), EditorError> {
+    ensure_finite("clip_percent", clip_percent)?;
+    if !(0.0..=50.0).contains(&clip_percent) {
+        return Err(EditorError::invalid_parameter("clip_percent", "must be between 0 and 50"));
+    }
+    Ok(())
+}
+
+/// First level, walking the bins in order, past which more than `clip` pixels lie
+fn clip_point<'a>(bins: impl Iterator<Item = (usize, &'a u32)>, clip: u64) -> Option<u8> {
+    let mut seen = 0u64;
+    for (level, &count) in bins {
+        seen += count as u64;
+        if seen > clip {
+            return Some(level as u8);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_range_and_master() {
+        let faded = ChannelLevels { output_black: 40, output_white: 220, ..ChannelLevels::default() };
+        assert!((faded.apply(0.0) - 40.0 / 255.0).abs() < 1e-6);
+        assert!((faded.apply(1.0) - 220.0 / 255.0).abs() < 1e-6);
+
+        let levels = Levels {
+            master: faded,
+            red: ChannelLevels { white_point: 128, ..ChannelLevels::default() },
+            ..Levels::default()
+        };
+        // Red is stretched to white first, then compressed by the master output
+        assert!((levels.apply(0, 128.0 / 255.0) - 220.0 / 255.0).abs() < 1e-6);
+        assert!((levels.apply(1, 128.0 / 255.0) - (40.0 + 180.0 * 128.0 / 255.0) / 255.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_from_histogram_clips_outliers() {
+        let mut histogram = vec![0u32; 768];
+        // Red spans 20-200 with one stray pixel at each end, green is a single level
+        histogram[20..=200].iter_mut().for_each(|count| *count = 10);
+        histogram[0] = 1;
+        histogram[255] = 1;
+        histogram[256 + 90] = 500;
+        histogram[512 + 30..512 + 240].iter_mut().for_each(|count| *count = 5);
+
+        let exact = Levels::from_histogram(&histogram, 0.0).unwrap();
+        assert_eq!((exact.red.black_point, exact.red.white_point), (0, 255));
+
+        let clipped = Levels::from_histogram(&histogram, 0.1).unwrap();
+        assert_eq!((clipped.red.black_point, clipped.red.white_point), (20, 200));
+        assert!(clipped.green.is_identity());
+        assert_eq!((clipped.blue.black_point, clipped.blue.white_point), (30, 239));
+
+        assert_eq!(Levels::from_histogram(&histogram, 60.0).unwrap_err().parameter(), Some("clip_percent"));
+    }
+
+    #[test]
+    fn test_invalid_levels() {
+        let error = Levels::from_json(r#"{ "blue": { "black_point": 200, "white_point": 100 } }"#).unwrap_err();
+        assert_eq!(error.parameter(), Some("blue.black_point"));
+        let error = Levels::from_json(r#"{ "master": { "gamma": 0 } }"#).unwrap_err();
+        assert_eq!(error.parameter(), Some("master.gamma"));
+    }
+}
@@ -1,197 +1,831 @@
-use wasm_bindgen::prelude::*;
-use image::GenericImageView;
-use crate::utils::{load_image, to_bytes, console_log};
+pub mod auto;
+pub mod clahe;
+pub mod curves;
+pub mod levels;
+pub mod white_balance;
+
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::{
+    from_linear, linear_to_oklab, linear_to_srgb_fast, luminance, oklab_to_linear_in_gamut, to_linear,
+};
+use crate::error::{ensure_finite, ensure_positive, EditorError};
+use crate::lut::{CubeLut, LutInterpolation};
+use crate::pixel::{map_pixels, EditorPixel, Image};
+use crate::utils::{console_log, hsl_to_rgb, hsv_to_rgb, rgb_to_hsl, rgb_to_hsv};
+
+pub use auto::{analyze_auto_adjustments, AutoAdjustments};
+pub use clahe::ClaheOptions;
+pub use curves::{CurvePoint, SampledCurves, ToneCurves};
+pub use levels::{ChannelLevels, Levels};
+pub use white_balance::{pick_neutral, WhiteBalance, NEUTRAL_TEMPERATURE};
 
 /// Adjust image brightness
-/// 
+///
 /// # Arguments
-/// * `image_data` - Input image bytes
+/// * `img` - Image to adjust in place
 /// * `value` - Brightness adjustment (-100 to +100)
-/// 
-/// # Returns
-/// Processed image bytes
-#[wasm_bindgen]
-pub fn adjust_brightness(image_data: &[u8], value: i32) -> Vec<u8> {
-    let img = load_image(image_data);
-    let processed = img.brighten(value);
-    to_bytes(&processed)
+pub fn adjust_brightness<P: EditorPixel>(img: &mut Image<P>, value: i32) -> Result<(), EditorError> {
+    // Same as `colorops::brighten_in_place`: shift by `value` 8-bit levels
+    let offset = value as f32 / 255.0;
+    P::map_color_matrix(img, &[[1.0, 0.0, 0.0, offset], [0.0, 1.0, 0.0, offset], [0.0, 0.0, 1.0, offset]]);
+    Ok(())
 }
 
 /// Adjust image contrast
-/// 
+///
 /// # Arguments
-/// * `image_data` - Input image bytes
-/// * `value` - Contrast multiplier (0.0 to 3.0, 1.0 = no change)
-/// 
-/// # Returns
-/// Processed image bytes
-#[wasm_bindgen]
-pub fn adjust_contrast(image_data: &[u8], value: f32) -> Vec<u8> {
-    let img = load_image(image_data);
-    let processed = img.adjust_contrast(value);
-    to_bytes(&processed)
+/// * `img` - Image to adjust in place
+/// * `value` - Contrast change in percent (about -100 to +100, 0 = no change);
+///   the slope around mid-gray is `((100 + value) / 100)²`
+pub fn adjust_contrast<P: EditorPixel>(img: &mut Image<P>, value: f32) -> Result<(), EditorError> {
+    ensure_finite("value", value)?;
+
+    // Same curve as `imageops::contrast`, which would also stretch the alpha channel
+    let percent = ((100.0 + value) / 100.0).powi(2);
+    let offset = 0.5 * (1.0 - percent);
+    P::map_color_matrix(img, &[[percent, 0.0, 0.0, offset], [0.0, percent, 0.0, offset], [0.0, 0.0, percent, offset]]);
+    Ok(())
+}
+
+/// Color model used by [`adjust_saturation_with`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SaturationModel {
+    /// HSL saturation, the classic slider; keeps HSL lightness
+    Hsl,
+    /// HSV saturation; keeps the brightest channel
+    Hsv,
+    /// OKLCh chroma: perceptually even, keeps lightness and hue
+    #[default]
+    Oklch,
+}
+
+impl FromStr for SaturationModel {
+    type Err = EditorError;
+
+    fn from_str(name: &str) -> Result<SaturationModel, EditorError> {
+        match name {
+            "hsl" => Ok(SaturationModel::Hsl),
+            "hsv" => Ok(SaturationModel::Hsv),
+            "oklch" => Ok(SaturationModel::Oklch),
+            _ => Err(EditorError::invalid_parameter(
+                "model",
+                format!("unknown saturation model `{}`, expected hsl, hsv or oklch", name),
+            )),
+        }
+    }
+}
+
+pub(crate) fn check_saturation(value: f32) -> Result<(), EditorError> {
+    ensure_finite("value", value)?;
+    if !(-100.0..=100.0).contains(&value) {
+        return Err(EditorError::invalid_parameter("value", "must be between -100 and 100"));
+    }
+    Ok(())
 }
 
-/// Adjust image saturation/hue
-/// 
+/// Adjust color saturation in OKLCh
+///
 /// # Arguments
-/// * `image_data` - Input image bytes
-/// * `value` - Hue rotation in degrees (-180 to +180)
-/// 
-/// # Returns
-/// Processed image bytes
-#[wasm_bindgen]
-pub fn adjust_saturation(image_data: &[u8], value: f32) -> Vec<u8> {
-    let img = load_image(image_data);
-    let processed = img.huerotate(value as i32);
-    to_bytes(&processed)
+/// * `img` - Image to adjust in place
+/// * `value` - Saturation change (-100 = grayscale, 0 = no change, +100 = double)
+pub fn adjust_saturation<P: EditorPixel>(img: &mut Image<P>, value: f32) -> Result<(), EditorError> {
+    adjust_saturation_with(img, value, SaturationModel::default())
+}
+
+/// Adjust color saturation in the given color model
+///
+/// Hue is never changed. Boosted OKLCh colors that leave the sRGB gamut lose
+/// only as much chroma as needed to fit; HSL and HSV saturation stops at 1.
+///
+/// # Arguments
+/// * `img` - Image to adjust in place
+/// * `value` - Saturation change (-100 = grayscale, 0 = no change, +100 = double)
+/// * `model` - Color model whose saturation or chroma is scaled
+pub fn adjust_saturation_with<P: EditorPixel>(img: &mut Image<P>, value: f32, model: SaturationModel) -> Result<(), EditorError> {
+    console_log("Saturation adjustment function called");
+    check_saturation(value)?;
+
+    if value == 0.0 {
+        console_log("No saturation adjustment needed, returning original image");
+        return Ok(());
+    }
+
+    let factor = 1.0 + value / 100.0;
+    match model {
+        SaturationModel::Hsl => map_pixels(img, |[r, g, b, a]| {
+            let (h, s, l) = rgb_to_hsl(r, g, b);
+            let (r, g, b) = hsl_to_rgb(h, (s * factor).min(1.0), l);
+            [r, g, b, a]
+        }),
+        SaturationModel::Hsv => map_pixels(img, |[r, g, b, a]| {
+            let (h, s, v) = rgb_to_hsv(r, g, b);
+            let (r, g, b) = hsv_to_rgb(h, (s * factor).min(1.0), v);
+            [r, g, b, a]
+        }),
+        SaturationModel::Oklch => map_pixels(img, |pixel| {
+            let [r, g, b, alpha] = to_linear(pixel);
+            let [lightness, a, b] = linear_to_oklab([r, g, b]);
+            let [r, g, b] = oklab_to_linear_in_gamut([lightness, a * factor, b * factor]);
+            from_linear([r, g, b, alpha])
+        }),
+    }
+
+    console_log("Saturation adjustment successful");
+    Ok(())
+}
+
+/// Rotate all hues around the gray axis
+///
+/// Uses the luminance-preserving hue rotation matrix; [`adjust_hue`] shifts
+/// the HSV hue instead.
+///
+/// # Arguments
+/// * `img` - Image to adjust in place
+/// * `degrees` - Rotation in degrees (-180 to +180)
+pub fn rotate_hue<P: EditorPixel>(img: &mut Image<P>, degrees: f32) -> Result<(), EditorError> {
+    ensure_finite("degrees", degrees)?;
+
+    // The matrix of `colorops::huerotate_in_place`, without rounding the angle to whole degrees
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    P::map_color_matrix(
+        img,
+        &[
+            [0.213 + cos * 0.787 - sin * 0.213, 0.715 - cos * 0.715 - sin * 0.715, 0.072 - cos * 0.072 + sin * 0.928, 0.0],
+            [0.213 - cos * 0.213 + sin * 0.143, 0.715 + cos * 0.285 + sin * 0.140, 0.072 - cos * 0.072 - sin * 0.283, 0.0],
+            [0.213 - cos * 0.213 - sin * 0.787, 0.715 - cos * 0.715 + sin * 0.715, 0.072 + cos * 0.928 + sin * 0.072, 0.0],
+        ],
+    );
+    Ok(())
 }
 
 /// Adjust white balance (color temperature)
-/// 
+///
+/// A quick warm/cool slider on top of [`apply_white_balance`]: the value moves
+/// the assumed light away from D65 in even steps of mired (micro reciprocal
+/// degrees), from about 4300 K at -100 to about 13500 K at +100.
+///
 /// # Arguments
-/// * `image_data` - Input image bytes
+/// * `img` - Image to adjust in place
 /// * `value` - Temperature adjustment (-100 to +100, negative=cooler, positive=warmer)
-/// 
-/// # Returns
-/// Processed image bytes
-#[wasm_bindgen]
-pub fn adjust_white_balance(image_data: &[u8], value: f32) -> Vec<u8> {
-    let img = load_image(image_data);
+pub fn adjust_white_balance<P: EditorPixel>(img: &mut Image<P>, value: f32) -> Result<(), EditorError> {
     console_log("White balance adjustment function called");
-    
+    ensure_finite("value", value)?;
+
     if value == 0.0 {
         console_log("No white balance adjustment needed, returning original image");
-        return image_data.to_vec();
-    }
-    
-    // Convert to RGB8 format for pixel manipulation
-    let mut rgb_img = img.to_rgb8();
-    
-    // Normalize value to -1.0 to 1.0 range
-    let factor = value / 100.0; // -1.0 (寒色/cool) to 1.0 (暖色/warm)
-    
-    for pixel in rgb_img.pixels_mut() {
-        let r = pixel[0] as f32;
-        let g = pixel[1] as f32;
-        let b = pixel[2] as f32;
-        
-        let (new_r, new_g, new_b) = if factor > 0.0 {
-            // 暖色に調整 (Make warmer - increase red/orange, decrease blue)
-            (
-                r + (50.0 * factor),
-                g + (20.0 * factor),  // Slightly increase green for natural orange tone
-                b - (40.0 * factor)
-            )
-        } else {
-            // 寒色に調整 (Make cooler - decrease red, increase blue)
-            let abs_factor = factor.abs();
-            (
-                r - (40.0 * abs_factor),
-                g - (10.0 * abs_factor),  // Slightly decrease green
-                b + (50.0 * abs_factor)
-            )
-        };
-        
-        pixel[0] = new_r.clamp(0.0, 255.0) as u8;
-        pixel[1] = new_g.clamp(0.0, 255.0) as u8;
-        pixel[2] = new_b.clamp(0.0, 255.0) as u8;
+        return Ok(());
     }
-    
-    let processed = image::DynamicImage::ImageRgb8(rgb_img);
+
+    // Warmer output means correcting for bluer light, i.e. fewer mired
+    let mired = 1e6 / NEUTRAL_TEMPERATURE - value.clamp(-100.0, 100.0) * 0.8;
+    apply_white_balance(img, &WhiteBalance { temperature: 1e6 / mired, tint: 0.0 })?;
+
     console_log("White balance adjustment successful");
-    
-    to_bytes(&processed)
+    Ok(())
 }
 
-// Advanced adjustments will be added here...
+/// White balance from a color temperature and tint
+///
+/// Colors are adapted in linear light from the given light to D65 with the
+/// Bradford transform (see [`white_balance`]).
+///
+/// # Arguments
+/// * `img` - Image to adjust in place
+/// * `white_balance` - Temperature and tint of the light to neutralize
+pub fn apply_white_balance<P: EditorPixel>(img: &mut Image<P>, white_balance: &WhiteBalance) -> Result<(), EditorError> {
+    white_balance.validate()?;
+    if white_balance.is_identity() {
+        return Ok(());
+    }
+
+    P::map_linear_matrix(img, &white_balance.matrix());
+    Ok(())
+}
 
 /// Adjust hue (color rotation)
-/// 
+///
 /// # Arguments
-/// * `image_data` - Input image bytes
+/// * `img` - Image to adjust in place
 /// * `shift` - Hue shift in degrees (-180 to +180)
-/// 
-/// # Returns
-/// Processed image bytes
-#[wasm_bindgen]
-pub fn adjust_hue(image_data: &[u8], shift: f32) -> Vec<u8> {
-    let img = load_image(image_data);
-    
+pub fn adjust_hue<P: EditorPixel>(img: &mut Image<P>, shift: f32) -> Result<(), EditorError> {
     console_log("Hue adjustment function called");
-    
+    ensure_finite("shift", shift)?;
+
     if shift == 0.0 {
         console_log("No hue shift needed, returning original image");
-        return image_data.to_vec();
+        return Ok(());
     }
-    
-    // Convert to RGB8 format for pixel manipulation
-    let mut rgb_img = img.to_rgb8();
-    
+
     // Normalize hue shift to -180 to +180 degrees, then convert to 0-360 range
     let hue_shift = ((shift % 360.0) + 360.0) % 360.0;
-    
-    for pixel in rgb_img.pixels_mut() {
-        let r = pixel[0] as f32 / 255.0;
-        let g = pixel[1] as f32 / 255.0;
-        let b = pixel[2] as f32 / 255.0;
-        
-        // Convert RGB to HSV
-        let max = r.max(g.max(b));
-        let min = r.min(g.min(b));
-        let delta = max - min;
-        
-        // Calculate HSV values
-        let mut h = if delta == 0.0 {
-            0.0
-        } else if max == r {
-            60.0 * (((g - b) / delta) % 6.0)
-        } else if max == g {
-            60.0 * ((b - r) / delta + 2.0)
+
+    map_pixels(img, |[r, g, b, a]| {
+        let (h, s, v) = rgb_to_hsv(r, g, b);
+
+        // Apply hue shift
+        let (new_r, new_g, new_b) = hsv_to_rgb((h + hue_shift) % 360.0, s, v);
+        [new_r, new_g, new_b, a]
+    });
+
+    console_log("Hue adjustment successful");
+    Ok(())
+}
+
+/// Adjust exposure in photographic stops
+///
+/// The multiplier is applied to linear light, so +1 stop doubles the light of
+/// every pixel; values pushed past white clip.
+///
+/// # Arguments
+/// * `img` - Image to adjust in place
+/// * `stops` - Exposure change in stops (-3.0 to +3.0, each stop doubles or halves)
+pub fn adjust_exposure<P: EditorPixel>(img: &mut Image<P>, stops: f32) -> Result<(), EditorError> {
+    console_log("Exposure adjustment function called");
+    ensure_finite("stops", stops)?;
+
+    if stops == 0.0 {
+        console_log("No exposure adjustment needed, returning original image");
+        return Ok(());
+    }
+
+    // Calculate exposure multiplier: each stop doubles or halves the exposure
+    // Positive stops brighten, negative stops darken
+    let exposure_multiplier = 2.0_f32.powf(stops);
+
+    let m = exposure_multiplier;
+    P::map_linear_matrix(img, &[[m, 0.0, 0.0], [0.0, m, 0.0], [0.0, 0.0, m]]);
+
+    console_log("Exposure adjustment successful");
+    Ok(())
+}
+
+/// Adjust vibrance (saturation boost that protects saturated colors and skin tones)
+///
+/// # Arguments
+/// * `img` - Image to adjust in place
+/// * `amount` - Vibrance adjustment (-100 to +100)
+pub fn adjust_vibrance<P: EditorPixel>(img: &mut Image<P>, amount: f32) -> Result<(), EditorError> {
+    console_log("Vibrance adjustment function called");
+    ensure_finite("amount", amount)?;
+
+    if amount == 0.0 {
+        console_log("No vibrance adjustment needed, returning original image");
+        return Ok(());
+    }
+
+    // Normalize amount to -1.0 to 1.0 range
+    let factor = amount / 100.0;
+
+    map_pixels(img, |[r, g, b, a]| {
+        let (h, current_saturation, v) = rgb_to_hsv(r, g, b);
+        let delta = current_saturation * v;
+
+        // Only adjust pixels that aren't already highly saturated
+        // Vibrance protects skin tones and already saturated colors
+        if v > 0.0 && delta > 0.01 {
+            // Create a protection factor - less effect on already saturated colors
+            // and on skin tones (reddish colors)
+            let skin_tone_protection = if r > g && r > b {
+                // Reduce effect on reddish colors (potential skin tones)
+                0.3
+            } else {
+                1.0
+            };
+
+            let saturation_protection = 1.0 - current_saturation.powf(0.5);
+            let protection_factor = skin_tone_protection * saturation_protection;
+
+            // Apply vibrance adjustment with protection
+            let adjusted_factor = factor * protection_factor;
+            let new_s = (current_saturation + adjusted_factor * (1.0 - current_saturation)).clamp(0.0, 1.0);
+
+            let (new_r, new_g, new_b) = hsv_to_rgb(h, new_s, v);
+            [new_r, new_g, new_b, a]
         } else {
-            60.0 * ((r - g) / delta + 4.0)
-        };
-        
-        if h < 0.0 {
-            h += 360.0;
+            [r, g, b, a]
         }
-        
-        let s = if max == 0.0 { 0.0 } else { delta / max };
-        let v = max;
-        
-        // Apply hue shift
-        h = (h + hue_shift) % 360.0;
-        
-        // Convert back to RGB
-        let c = v * s;
-        let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
-        let m = v - c;
-        
-        let (r_prime, g_prime, b_prime) = if h < 60.0 {
-            (c, x, 0.0)
-        } else if h < 120.0 {
-            (x, c, 0.0)
-        } else if h < 180.0 {
-            (0.0, c, x)
-        } else if h < 240.0 {
-            (0.0, x, c)
-        } else if h < 300.0 {
-            (x, 0.0, c)
+    });
+
+    console_log("Vibrance adjustment successful");
+    Ok(())
+}
+
+/// Brighten or darken highlight regions (luminance above 0.7)
+///
+/// The mask uses the Rec.709 luminance encoded with the sRGB curve, so the
+/// threshold follows perceived brightness; the colors are mixed in linear light.
+///
+/// # Arguments
+/// * `img` - Image to adjust in place
+/// * `amount` - Highlight adjustment (-100 to +100)
+pub fn adjust_highlights<P: EditorPixel>(img: &mut Image<P>, amount: f32) -> Result<(), EditorError> {
+    console_log("Highlight adjustment function called");
+    ensure_finite("amount", amount)?;
+
+    if amount == 0.0 {
+        console_log("No highlight adjustment needed, returning original image");
+        return Ok(());
+    }
+
+    // Normalize amount to -1.0 to 1.0 range
+    let factor = amount / 100.0;
+
+    map_pixels(img, |pixel| {
+        // Calculate luminance to determine if this is a highlight region
+        let [r, g, b, a] = to_linear(pixel);
+        let lightness = linear_to_srgb_fast(luminance(r, g, b));
+
+        // Only adjust pixels in highlight range (above 0.7 luminance)
+        if lightness > 0.7 {
+            // Calculate highlight mask (stronger effect for brighter pixels)
+            let highlight_mask = ((lightness - 0.7) / 0.3).clamp(0.0, 1.0);
+
+            // Apply adjustment with mask
+            let adjustment = factor * highlight_mask;
+            from_linear([
+                r + adjustment * (1.0 - r),
+                g + adjustment * (1.0 - g),
+                b + adjustment * (1.0 - b),
+                a,
+            ])
+        } else {
+            pixel
+        }
+    });
+
+    console_log("Highlight adjustment successful");
+    Ok(())
+}
+
+/// Brighten or darken shadow regions (luminance below 0.3)
+///
+/// Masked like [`adjust_highlights`]; the gain is applied in linear light.
+///
+/// # Arguments
+/// * `img` - Image to adjust in place
+/// * `amount` - Shadow adjustment (-100 to +100)
+pub fn adjust_shadows<P: EditorPixel>(img: &mut Image<P>, amount: f32) -> Result<(), EditorError> {
+    console_log("Shadow adjustment function called");
+    ensure_finite("amount", amount)?;
+
+    if amount == 0.0 {
+        console_log("No shadow adjustment needed, returning original image");
+        return Ok(());
+    }
+
+    // Normalize amount to -1.0 to 1.0 range
+    let factor = amount / 100.0;
+
+    map_pixels(img, |pixel| {
+        // Calculate luminance to determine if this is a shadow region
+        let [r, g, b, a] = to_linear(pixel);
+        let lightness = linear_to_srgb_fast(luminance(r, g, b));
+
+        // Only adjust pixels in shadow range (below 0.3 luminance)
+        if lightness < 0.3 {
+            // Calculate shadow mask (stronger effect for darker pixels)
+            let shadow_mask = (1.0 - (lightness / 0.3)).clamp(0.0, 1.0);
+
+            // Apply adjustment with mask
+            let adjustment = factor * shadow_mask;
+            from_linear([r + adjustment * r, g + adjustment * g, b + adjustment * b, a])
         } else {
-            (c, 0.0, x)
+            pixel
+        }
+    });
+
+    console_log("Shadow adjustment successful");
+    Ok(())
+}
+
+pub(crate) fn check_curves(red_gamma: f32, green_gamma: f32, blue_gamma: f32) -> Result<(), EditorError> {
+    ensure_positive("red_gamma", red_gamma)?;
+    ensure_positive("green_gamma", green_gamma)?;
+    ensure_positive("blue_gamma", blue_gamma)
+}
+
+/// Apply per-channel gamma curves
+///
+/// # Arguments
+/// * `img` - Image to adjust in place
+/// * `red_gamma`, `green_gamma`, `blue_gamma` - Channel gamma (< 1.0 darker, > 1.0 brighter mid-tones)
+pub fn adjust_curves<P: EditorPixel>(img: &mut Image<P>, red_gamma: f32, green_gamma: f32, blue_gamma: f32) -> Result<(), EditorError> {
+    console_log("Color curves adjustment function called");
+    check_curves(red_gamma, green_gamma, blue_gamma)?;
+
+    if red_gamma == 1.0 && green_gamma == 1.0 && blue_gamma == 1.0 {
+        console_log("No curve adjustment needed, returning original image");
+        return Ok(());
+    }
+
+    // Clamp gamma values to reasonable range to prevent extreme results
+    let gammas = [
+        red_gamma.clamp(0.1, 3.0),
+        green_gamma.clamp(0.1, 3.0),
+        blue_gamma.clamp(0.1, 3.0),
+    ];
+
+    // Gamma correction per channel: output = input^(1/gamma)
+    P::map_tone(img, |channel, level| level.powf(1.0 / gammas[channel]));
+
+    console_log("Color curves adjustment successful");
+    Ok(())
+}
+
+/// Apply master and per-channel spline tone curves
+///
+/// 8-bit images use a 256-entry table per channel, float images an
+/// interpolated 4096-entry table (see [`curves`]).
+///
+/// # Arguments
+/// * `img` - Image to adjust in place
+/// * `curves` - Control points of the master, red, green and blue curves
+pub fn apply_tone_curves<P: EditorPixel>(img: &mut Image<P>, curves: &ToneCurves) -> Result<(), EditorError> {
+    console_log("Tone curves adjustment function called");
+    curves.validate()?;
+
+    if curves.is_identity() {
+        console_log("No tone curve adjustment needed, returning original image");
+        return Ok(());
+    }
+
+    let luts = curves.channel_luts(P::TONE_LUT_SIZE)?;
+    P::map_tone(img, |channel, level| curves::lookup(&luts[channel], level));
+
+    console_log("Tone curves adjustment successful");
+    Ok(())
+}
+
+pub(crate) fn check_levels(black_point: u8, white_point: u8, gamma: f32) -> Result<(), EditorError> {
+    if black_point >= white_point {
+        return Err(EditorError::invalid_parameter(
+            "black_point",
+            format!("black point ({}) must be less than white point ({})", black_point, white_point),
+        ));
+    }
+    ensure_positive("gamma", gamma)
+}
+
+/// Levels correction with black point, white point and gamma
+///
+/// # Arguments
+/// * `img` - Image to adjust in place
+/// * `black_point` - Input value mapped to black (must be below `white_point`)
+/// * `white_point` - Input value mapped to white
+/// * `gamma` - Mid-tone gamma (0.1 to 3.0, 1.0 = no change)
+pub fn adjust_levels<P: EditorPixel>(img: &mut Image<P>, black_point: u8, white_point: u8, gamma: f32) -> Result<(), EditorError> {
+    console_log("Levels correction function called");
+    check_levels(black_point, white_point, gamma)?;
+
+    if black_point == 0 && white_point == 255 && gamma == 1.0 {
+        console_log("No levels adjustment needed, returning original image");
+        return Ok(());
+    }
+
+    // Calculate the input range
+    let black = black_point as f32 / 255.0;
+    let input_range = (white_point as f32 - black_point as f32) / 255.0;
+
+    // Clamp gamma to reasonable range to prevent extreme results
+    let gamma_clamped = gamma.clamp(0.1, 3.0);
+
+    P::map_tone(img, |_, level| {
+        // Step 1: Apply input levels (black and white point mapping)
+        let normalized = ((level - black) / input_range).clamp(0.0, 1.0);
+
+        // Step 2: Apply gamma correction
+        if gamma_clamped != 1.0 {
+            normalized.powf(1.0 / gamma_clamped)
+        } else {
+            normalized
+        }
+    });
+
+    console_log("Levels correction successful");
+    Ok(())
+}
+
+/// Per-channel levels with output black and white points
+///
+/// # Arguments
+/// * `img` - Image to adjust in place
+/// * `levels` - Master and per-channel levels (see [`levels`])
+pub fn apply_levels<P: EditorPixel>(img: &mut Image<P>, levels: &Levels) -> Result<(), EditorError> {
+    console_log("Channel levels function called");
+    levels.validate()?;
+
+    if levels.is_identity() {
+        console_log("No levels adjustment needed, returning original image");
+        return Ok(());
+    }
+
+    P::map_tone(img, |channel, level| levels.apply(channel, level));
+
+    console_log("Channel levels successful");
+    Ok(())
+}
+
+pub(crate) fn check_intensity(intensity: f32) -> Result<(), EditorError> {
+    ensure_finite("intensity", intensity)?;
+    if !(0.0..=100.0).contains(&intensity) {
+        return Err(EditorError::invalid_parameter("intensity", "must be between 0 and 100"));
+    }
+    Ok(())
+}
+
+/// Apply a color grade from a `.cube` LUT
+///
+/// # Arguments
+/// * `img` - Image to adjust in place
+/// * `lut` - Parsed LUT (see [`CubeLut::parse`])
+/// * `interpolation` - How colors between the grid points of a 3D table are blended
+/// * `intensity` - Mix with the original colors (0 to 100, 100 = full grade)
+pub fn apply_lut<P: EditorPixel>(img: &mut Image<P>, lut: &CubeLut, interpolation: LutInterpolation, intensity: f32) -> Result<(), EditorError> {
+    console_log("LUT function called");
+    check_intensity(intensity)?;
+
+    if intensity == 0.0 {
+        console_log("No LUT mix needed, returning original image");
+        return Ok(());
+    }
+
+    let mix = intensity / 100.0;
+    map_pixels(img, |[r, g, b, a]| {
+        let graded = lut.map([r, g, b], interpolation);
+        let [r, g, b] = std::array::from_fn(|channel| {
+            let original = [r, g, b][channel];
+            original + (graded[channel] - original) * mix
+        });
+        [r, g, b, a]
+    });
+
+    console_log("LUT successful");
+    Ok(())
+}
+
+/// Auto levels: stretch each channel to the full range
+///
+/// The black and white points come from the histogram of the image, ignoring
+/// `clip_percent` of the pixels at each end so a few stray pixels do not hold
+/// the range open.
+///
+/// # Arguments
+/// * `img` - Image to adjust in place
+/// * `clip_percent` - Share of pixels allowed to clip at each end (0.0 to 50.0, e.g. 0.1)
+///
+/// # Returns
+/// The levels that were applied, to show them in the levels sliders
+pub fn auto_levels<P: EditorPixel>(img: &mut Image<P>, clip_percent: f32) -> Result<Levels, EditorError> {
+    let levels = Levels::from_histogram(&calculate_histogram(img), clip_percent)?;
+    apply_levels(img, &levels)?;
+    Ok(levels)
+}
+
+/// Equalize the histogram of each RGB channel
+///
+/// # Arguments
+/// * `img` - Image to adjust in place
+pub fn histogram_equalization<P: EditorPixel>(img: &mut Image<P>) -> Result<(), EditorError> {
+    console_log("Histogram equalization function called");
+
+    let total_pixels = (img.width() as f32) * (img.height() as f32);
+    if total_pixels == 0.0 {
+        return Ok(());
+    }
+
+    // Calculate histogram for each channel
+    let histogram = calculate_histogram(img);
+
+    // Cumulative distribution function (CDF) of each channel
+    let mut cdfs = [[0.0f32; 256]; 3];
+    for (cdf, channel_hist) in cdfs.iter_mut().zip(histogram.chunks_exact(256)) {
+        let mut sum = 0.0f32;
+        for (entry, count) in cdf.iter_mut().zip(channel_hist) {
+            sum += *count as f32 / total_pixels;
+            *entry = sum;
+        }
+    }
+
+    // Values between two 8-bit levels interpolate, so float images don't band
+    P::map_tone(img, |channel, level| {
+        let position = (level * 255.0).clamp(0.0, 255.0);
+        let lower = position.floor() as usize;
+        let upper = (lower + 1).min(255);
+        let t = position - lower as f32;
+        let cdf = &cdfs[channel];
+        cdf[lower] + (cdf[upper] - cdf[lower]) * t
+    });
+
+    console_log("Histogram equalization successful");
+    Ok(())
+}
+
+/// Contrast-limited adaptive histogram equalization on OKLab lightness
+///
+/// Raises local contrast tile by tile without shifting colors (see [`clahe`]).
+///
+/// # Arguments
+/// * `img` - Image to adjust in place
+/// * `options` - Tile grid and clip limit
+pub fn clahe<P: EditorPixel>(img: &mut Image<P>, options: &ClaheOptions) -> Result<(), EditorError> {
+    console_log("CLAHE function called");
+    options.validate()?;
+    clahe::apply(img, options);
+    console_log("CLAHE successful");
+    Ok(())
+}
+
+/// Calculate the RGB histogram
+///
+/// Float images are binned to the nearest 8-bit level.
+///
+/// # Arguments
+/// * `img` - Image to analyze
+///
+/// # Returns
+/// 768 bins: `[R0..R255, G0..G255, B0..B255]`
+pub fn calculate_histogram<P: EditorPixel>(img: &Image<P>) -> Vec<u32> {
+    console_log("Calculate histogram function called");
+
+    // Format: [R0, R1, R2, ..., R255, G0, G1, G2, ..., G255, B0, B1, B2, ..., B255]
+    let mut result = vec![0u32; 768]; // 256 * 3
+
+    // Count pixel values for each channel
+    for pixel in img.pixels() {
+        let [r, g, b, _] = pixel.to_unit().map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as usize);
+        result[r] += 1;
+        result[256 + g] += 1;
+        result[512 + b] += 1;
+    }
+
+    console_log("Histogram calculation successful");
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{chromatic_adaptation, matrix_apply, srgb8_to_linear, white_point, D65};
+    use image::imageops::colorops;
+    use image::{Rgba, Rgba32FImage, RgbaImage};
+
+    #[test]
+    fn test_exposure_doubles_linear_light() {
+        let mut img = RgbaImage::from_fn(64, 1, |x, _| Rgba([x as u8, (x * 2) as u8, 30, 255]));
+        let source = img.clone();
+        adjust_exposure(&mut img, 1.0).unwrap();
+
+        for (after, before) in img.pixels().zip(source.pixels()) {
+            for channel in 0..3 {
+                let expected = (srgb8_to_linear(before[channel]) * 2.0).min(1.0);
+                let actual = srgb8_to_linear(after[channel]);
+                // Within half an 8-bit level of the doubled light
+                assert!((actual - expected).abs() <= expected * 0.02 + 0.001, "{:?} {:?}", before, after);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tone_curves_match_sampled_curve() {
+        let curves = ToneCurves {
+            master: vec![[0.0, 0.0], [64.0, 40.0], [192.0, 215.0], [255.0, 255.0]],
+            blue: vec![[0.0, 30.0], [255.0, 255.0]],
+            ..ToneCurves::default()
         };
-        
-        let new_r = ((r_prime + m) * 255.0).clamp(0.0, 255.0) as u8;
-        let new_g = ((g_prime + m) * 255.0).clamp(0.0, 255.0) as u8;
-        let new_b = ((b_prime + m) * 255.0).clamp(0.0, 255.0) as u8;
-        
-        pixel[0] = new_r;
-        pixel[1] = new_g;
-        pixel[2] = new_b;
-    }
-    
-    let processed = image::DynamicImage::ImageRgb8(rgb_img);
-    console_log("Hue adjustment successful");
-    
-    to_bytes(&processed)
-}
\ No newline at end of file
+        let sampled = curves.sample(256).unwrap();
+        let mut img = RgbaImage::from_fn(256, 1, |x, _| Rgba([x as u8, x as u8, 0, 200]));
+        apply_tone_curves(&mut img, &curves).unwrap();
+
+        for (x, pixel) in img.enumerate_pixels().map(|(x, _, pixel)| (x as usize, pixel)) {
+            assert_eq!(pixel[0], sampled.master[x].round() as u8);
+            assert_eq!(pixel[3], 200);
+        }
+        // Blue goes through its own curve first, then the master curve
+        let lifted = sampled.blue[0] as usize;
+        assert_eq!(img.get_pixel(0, 0)[2], sampled.master[lifted].round() as u8);
+
+        let mut wide: image::Rgba32FImage = crate::pixel::convert(&RgbaImage::from_fn(256, 1, |x, _| Rgba([x as u8, 0, 0, 255])));
+        apply_tone_curves(&mut wide, &curves).unwrap();
+        for (x, _, pixel) in wide.enumerate_pixels() {
+            assert!((pixel[0] * 255.0 - sampled.master[x as usize]).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn test_auto_levels_removes_cast() {
+        // A dull image with a blue cast: blue sits higher than red and green
+        let mut img = RgbaImage::from_fn(64, 4, |x, _| {
+            let level = 60 + x as u8 * 2;
+            Rgba([level, level, level + 40, 255])
+        });
+        let levels = auto_levels(&mut img, 0.0).unwrap();
+        assert_eq!((levels.blue.black_point, levels.blue.white_point), (100, 226));
+
+        for channel in 0..3 {
+            let values: Vec<u8> = img.pixels().map(|pixel| pixel[channel]).collect();
+            assert_eq!((values.iter().min(), values.iter().max()), (Some(&0), Some(&255)));
+        }
+        assert_eq!(img.get_pixel(10, 0)[0], img.get_pixel(10, 0)[2]);
+    }
+
+    #[test]
+    fn test_saturation_keeps_hue() {
+        // A skin tone must not turn green when saturated
+        let skin = RgbaImage::from_pixel(2, 2, Rgba([224, 172, 140, 255]));
+        for model in [SaturationModel::Hsl, SaturationModel::Hsv, SaturationModel::Oklch] {
+            let mut boosted = skin.clone();
+            adjust_saturation_with(&mut boosted, 60.0, model).unwrap();
+            let [r, g, b, a] = boosted.get_pixel(0, 0).0;
+            assert!(r > g && g > b && a == 255, "{:?} {:?}", model, boosted.get_pixel(0, 0));
+            assert!(r as i32 - b as i32 > 224 - 140, "{:?}", model);
+            let (hue, _, _) = rgb_to_hsv(r as f32, g as f32, b as f32);
+            assert!((hue - 22.5).abs() < 4.0, "{:?} {}", model, hue);
+
+            let mut gray = skin.clone();
+            adjust_saturation_with(&mut gray, -100.0, model).unwrap();
+            let [r, g, b, _] = gray.get_pixel(0, 0).0;
+            assert!(r.abs_diff(g) <= 1 && g.abs_diff(b) <= 1, "{:?} {:?}", model, gray.get_pixel(0, 0));
+        }
+
+        // Saturated colors stay inside the gamut instead of clipping to another hue
+        let mut vivid = RgbaImage::from_pixel(1, 1, Rgba([30, 200, 60, 255]));
+        adjust_saturation(&mut vivid, 100.0).unwrap();
+        let [r, g, b, _] = vivid.get_pixel(0, 0).0;
+        assert!(g > 190 && r < 30 && b < 60, "{:?}", vivid.get_pixel(0, 0));
+
+        assert_eq!(adjust_saturation(&mut vivid, 150.0).unwrap_err().parameter(), Some("value"));
+        assert_eq!("lab".parse::<SaturationModel>().unwrap_err().parameter(), Some("model"));
+    }
+
+    #[test]
+    fn test_rotate_hue_keeps_fractions() {
+        let red = Rgba32FImage::from_pixel(1, 1, Rgba([0.8, 0.2, 0.1, 1.0]));
+        let rotated = |degrees: f32| {
+            let mut img = red.clone();
+            rotate_hue(&mut img, degrees).unwrap();
+            img.get_pixel(0, 0).0
+        };
+        assert_eq!(rotated(0.0), red.get_pixel(0, 0).0.map(|value| value.clamp(0.0, 1.0)));
+        let [half, whole] = [rotated(0.5), rotated(1.0)];
+        assert!(half[1] > red.get_pixel(0, 0)[1] && half[1] < whole[1], "{:?} {:?}", half, whole);
+
+        // Same result as the image crate for whole degrees
+        let mut img = RgbaImage::from_pixel(1, 1, Rgba([200, 50, 25, 255]));
+        let mut expected = img.clone();
+        rotate_hue(&mut img, 40.0).unwrap();
+        colorops::huerotate_in_place(&mut expected, 40);
+        assert!((0..3).all(|c| img.get_pixel(0, 0)[c].abs_diff(expected.get_pixel(0, 0)[c]) <= 1), "{:?} {:?}", img, expected);
+    }
+
+    #[test]
+    fn test_highlights_and_shadows_leave_midtones() {
+        let gray = RgbaImage::from_pixel(4, 4, Rgba([128, 128, 128, 255]));
+        let mut img = gray.clone();
+        adjust_highlights(&mut img, 50.0).unwrap();
+        adjust_shadows(&mut img, 50.0).unwrap();
+        assert_eq!(img, gray);
+
+        // Pure green is perceptually bright, pure blue is dark
+        let mut img = RgbaImage::from_fn(2, 1, |x, _| if x == 0 { Rgba([0, 255, 0, 255]) } else { Rgba([0, 0, 255, 255]) });
+        adjust_shadows(&mut img, 100.0).unwrap();
+        assert_eq!(img.get_pixel(0, 0), &Rgba([0, 255, 0, 255]));
+        assert!(img.get_pixel(1, 0)[2] == 255 && img.get_pixel(1, 0)[0] == 0);
+    }
+
+    #[test]
+    fn test_pick_neutral_removes_tungsten_cast() {
+        // Gray cards lit by 3000 K light with a slight green tint
+        let light = white_point(3000.0, -10.0);
+        let cast = chromatic_adaptation(D65, light);
+        let mut img = Rgba32FImage::from_fn(8, 8, |x, _| {
+            let gray = 0.1 + x as f32 * 0.05;
+            let [r, g, b] = matrix_apply(&cast, [gray; 3]);
+            Rgba(from_linear([r, g, b, 1.0]))
+        });
+        assert!(img.get_pixel(3, 3)[0] > img.get_pixel(3, 3)[2] + 0.1);
+
+        let picked = pick_neutral(&img, 2, 2, 4, 4).unwrap();
+        assert!((picked.temperature - 3000.0).abs() < 30.0, "{:?}", picked);
+        assert!((picked.tint + 10.0).abs() < 1.5, "{:?}", picked);
+
+        apply_white_balance(&mut img, &picked).unwrap();
+        for pixel in img.pixels() {
+            let [r, g, b, _] = pixel.0;
+            assert!((r - g).abs() < 0.01 && (g - b).abs() < 0.01, "{:?}", pixel);
+        }
+
+        // The warm/cool slider goes through the same transform
+        let mut gray = RgbaImage::from_pixel(2, 2, Rgba([128, 128, 128, 255]));
+        adjust_white_balance(&mut gray, 50.0).unwrap();
+        let [r, _, b, _] = gray.get_pixel(0, 0).0;
+        assert!(r > b + 10, "{:?}", gray.get_pixel(0, 0));
+
+        assert_eq!(pick_neutral(&img, 6, 0, 4, 4).unwrap_err().parameter(), Some("x"));
+        let black = RgbaImage::from_pixel(2, 2, Rgba([0, 0, 0, 255]));
+        assert_eq!(pick_neutral(&black, 0, 0, 1, 1).unwrap_err().parameter(), Some("x"));
+    }
+}
@@ -0,0 +1,134 @@
+//! Kelvin white balance
+//!
+//! [`WhiteBalance`] names the light the photo was taken under as a color
+//! temperature plus a green/magenta tint. Applying it runs a Bradford chromatic
+//! adaptation in linear light from that white to D65, so surfaces that were
+//! neutral under the light come out neutral. Setting a lower temperature than
+//! the real light cools the image, a higher one warms it:
+//!
+//! ```json
+//! { "temperature": 3200, "tint": 8 }
+//! ```
+//!
+//! [`pick_neutral`] measures these settings from an area that should be gray.
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::{
+    chromatic_adaptation, linear_to_xy, matrix_apply, temperature_tint, to_linear, white_point, Matrix3, D65, TEMPERATURE_RANGE,
+};
+use crate::error::{ensure_finite, EditorError};
+use crate::pixel::{EditorPixel, Image};
+use crate::transforms::check_area;
+
+/// Temperature of D65, the white point of sRGB
+pub const NEUTRAL_TEMPERATURE: f32 = 6504.0;
+
+/// Light source to correct for
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WhiteBalance {
+    /// Color temperature of the light in Kelvin (1667 to 25000, 6504 = no change)
+    pub temperature: f32,
+    /// Green/magenta shift of the light (-100 to +100, positive = magenta)
+    pub tint: f32,
+}
+
+impl Default for WhiteBalance {
+    fn default() -> WhiteBalance {
+        WhiteBalance { temperature: NEUTRAL_TEMPERATURE, tint: 0.0 }
+    }
+}
+
+impl WhiteBalance {
+    /// Parse white balance settings from JSON
+    pub fn from_json(json: &str) -> Result<WhiteBalance, EditorError> {
+        let white_balance: WhiteBalance =
+            serde_json::from_str(json).map_err(|e| EditorError::invalid_parameter("white_balance", e.to_string()))?;
+        white_balance.validate()?;
+        Ok(white_balance)
+    }
+
+    /// Check temperature and tint
+    pub fn validate(&self) -> Result<(), EditorError> {
+        ensure_finite("temperature", self.temperature)?;
+        ensure_finite("tint", self.tint)?;
+        if !TEMPERATURE_RANGE.contains(&self.temperature) {
+            return Err(EditorError::invalid_parameter("temperature", "must be between 1667 and 25000 Kelvin"));
+        }
+        if !(-100.0..=100.0).contains(&self.tint) {
+            return Err(EditorError::invalid_parameter("tint", "must be between -100 and 100"));
+        }
+        Ok(())
+    }
+
+    /// Whether these settings leave every color unchanged
+    pub fn is_identity(&self) -> bool {
+        *self == WhiteBalance::default()
+    }
+
+    /// Linear sRGB matrix that neutralizes this light
+    ///
+    /// The matrix is scaled so that white stays within range: the brightest
+    /// channel of white ends at 1.0 and highlights keep their detail instead of
+    /// clipping.
+    pub fn matrix(&self) -> Matrix3 {
+        let matrix = chromatic_adaptation(white_point(self.temperature, self.tint), D65);
+        let white = matrix_apply(&matrix, [1.0; 3]);
+        let peak = white.into_iter().fold(1.0f32, f32::max);
+        matrix.map(|row| row.map(|value| value / peak))
+    }
+}
+
+/// Measure the white balance that makes an area neutral
+///
+/// The area is averaged in linear light, so noise and texture even out. Pass a
+/// 1×1 area to sample a single pixel.
+///
+/// # Arguments
+/// * `img` - Image to sample
+/// * `x`, `y` - Top-left corner of the sample area
+/// * `width`, `height` - Size of the sample area
+///
+/// # Returns
+/// Settings for [`super::apply_white_balance`], clamped to their valid ranges
+pub fn pick_neutral<P: EditorPixel>(img: &Image<P>, x: u32, y: u32, width: u32, height: u32) -> Result<WhiteBalance, EditorError> {
+    check_area("sample area", img.dimensions(), x, y, width, height)?;
+
+    let mut sum = [0.0f64; 3];
+    for sample_y in y..y + height {
+        for sample_x in x..x + width {
+            let [r, g, b, _] = to_linear(img.get_pixel(sample_x, sample_y).to_unit());
+            for (total, level) in sum.iter_mut().zip([r, g, b]) {
+                *total += level as f64;
+            }
+        }
+    }
+    let count = (width as f64) * (height as f64);
+    let average = sum.map(|total| (total / count) as f32);
+
+    // Too little light to tell its color from noise
+    let xy = linear_to_xy(average)
+        .filter(|_| average.iter().any(|&channel| channel >= 1e-3))
+        .ok_or_else(|| EditorError::invalid_parameter("x", "sample area is too dark to pick a neutral color"))?;
+
+    let (temperature, tint) = temperature_tint(xy);
+    Ok(WhiteBalance {
+        temperature: temperature.clamp(*TEMPERATURE_RANGE.start(), *TEMPERATURE_RANGE.end()),
+        tint: tint.clamp(-100.0, 100.0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_white_balance() {
+        let error = WhiteBalance::from_json(r#"{ "temperature": 1000 }"#).unwrap_err();
+        assert_eq!(error.parameter(), Some("temperature"));
+        let error = WhiteBalance::from_json(r#"{ "tint": 150 }"#).unwrap_err();
+        assert_eq!(error.parameter(), Some("tint"));
+        assert!(WhiteBalance::from_json("{}").unwrap().is_identity());
+    }
+}
@@ -0,0 +1,415 @@
+//! Color math on linear light
+//!
+//! Image buffers hold sRGB-encoded values, which are spaced for the eye rather
+//! than for light: doubling an encoded value does not double the light it
+//! stands for. Operations that model light (exposure, white balance, mixing
+//! highlights and shadows) decode to linear values with [`srgb_to_linear`],
+//! work there, and encode the result again with [`linear_to_srgb`].
+//!
+//! Both transfer functions have table-driven versions for per-pixel loops:
+//! [`srgb8_to_linear`] looks up an 8-bit level directly, and
+//! [`srgb_to_linear_fast`] / [`linear_to_srgb_fast`] interpolate a 4096-entry
+//! table, which stays within 1/65535 of the exact curve. The encoding table is
+//! indexed by the square root of the input so its steep start gets enough entries.
+//!
+//! Perceptual edits such as saturation work in OKLab, whose lightness and
+//! chroma track what the eye sees; [`oklab_to_linear_in_gamut`] brings results
+//! that no longer fit in sRGB back by lowering chroma only.
+//!
+//! White balance is a chromatic adaptation: [`white_point`] turns a color
+//! temperature and tint into a white point, and [`chromatic_adaptation`] builds
+//! the Bradford transform that makes that white neutral.
+
+use std::sync::OnceLock;
+
+/// Rec.709 / sRGB luminance weights for linear R, G and B
+pub const REC709_LUMA: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+const TABLE_SIZE: usize = 4096;
+
+/// Decode one sRGB-encoded channel (0.0-1.0) to linear light
+#[inline]
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encode one linear channel (0.0-1.0) with the sRGB transfer function
+#[inline]
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Linear value of an 8-bit sRGB level
+#[inline]
+pub fn srgb8_to_linear(level: u8) -> f32 {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| std::array::from_fn(|level| srgb_to_linear(level as f32 / 255.0)))[level as usize]
+}
+
+/// Table-driven [`srgb_to_linear`], input clamped to 0.0-1.0
+#[inline]
+pub fn srgb_to_linear_fast(value: f32) -> f32 {
+    interpolate(decode_table(), value.clamp(0.0, 1.0))
+}
+
+/// Table-driven [`linear_to_srgb`], input clamped to 0.0-1.0
+#[inline]
+pub fn linear_to_srgb_fast(value: f32) -> f32 {
+    interpolate(encode_table(), value.clamp(0.0, 1.0).sqrt())
+}
+
+/// [`srgb_to_linear`] sampled at evenly spaced encoded values
+pub(crate) fn decode_table() -> &'static [f32] {
+    static TABLE: OnceLock<Vec<f32>> = OnceLock::new();
+    TABLE.get_or_init(|| build_table(srgb_to_linear))
+}
+
+/// [`linear_to_srgb`] sampled at evenly spaced square roots of linear values,
+/// which puts more entries where the curve is steep
+pub(crate) fn encode_table() -> &'static [f32] {
+    static TABLE: OnceLock<Vec<f32>> = OnceLock::new();
+    TABLE.get_or_init(|| build_table(|root| linear_to_srgb(root * root)))
+}
+
+/// Decode the color channels of a normalized pixel, keeping alpha
+#[inline]
+pub fn to_linear([r, g, b, a]: [f32; 4]) -> [f32; 4] {
+    [srgb_to_linear_fast(r), srgb_to_linear_fast(g), srgb_to_linear_fast(b), a]
+}
+
+/// Encode the color channels of a linear pixel, keeping alpha
+#[inline]
+pub fn from_linear([r, g, b, a]: [f32; 4]) -> [f32; 4] {
+    [linear_to_srgb_fast(r), linear_to_srgb_fast(g), linear_to_srgb_fast(b), a]
+}
+
+/// Relative luminance (Rec.709 weights) of linear R, G and B
+#[inline]
+pub fn luminance(r: f32, g: f32, b: f32) -> f32 {
+    REC709_LUMA[0] * r + REC709_LUMA[1] * g + REC709_LUMA[2] * b
+}
+
+/// sRGB-encoded luminance of a normalized sRGB pixel (0.0-1.0), e.g. for IRE levels
+#[inline]
+pub fn luma([r, g, b, _]: [f32; 4]) -> f32 {
+    linear_to_srgb_fast(luminance(srgb_to_linear_fast(r), srgb_to_linear_fast(g), srgb_to_linear_fast(b)))
+}
+
+/// OKLab lightness and opponent axes (a: green-red, b: blue-yellow) of a linear sRGB color
+// Coefficients as published with OKLab
+#[allow(clippy::excessive_precision)]
+#[inline]
+pub fn linear_to_oklab([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let l = (0.412_221_470_8 * r + 0.536_332_536_3 * g + 0.051_445_992_9 * b).cbrt();
+    let m = (0.211_903_498_2 * r + 0.680_699_545_1 * g + 0.107_396_956_6 * b).cbrt();
+    let s = (0.088_302_461_9 * r + 0.281_718_837_6 * g + 0.629_978_700_5 * b).cbrt();
+    [
+        0.210_454_255_3 * l + 0.793_617_785_0 * m - 0.004_072_046_8 * s,
+        1.977_998_495_1 * l - 2.428_592_205_0 * m + 0.450_593_709_9 * s,
+        0.025_904_037_1 * l + 0.782_771_766_2 * m - 0.808_675_766_0 * s,
+    ]
+}
+
+/// Linear sRGB color of an OKLab color, possibly outside 0.0-1.0
+#[allow(clippy::excessive_precision)]
+#[inline]
+pub fn oklab_to_linear([lightness, a, b]: [f32; 3]) -> [f32; 3] {
+    let l = (lightness + 0.396_337_777_4 * a + 0.215_803_757_3 * b).powi(3);
+    let m = (lightness - 0.105_561_345_8 * a - 0.063_854_172_8 * b).powi(3);
+    let s = (lightness - 0.089_484_177_5 * a - 1.291_485_548_0 * b).powi(3);
+    [
+        4.076_741_662_1 * l - 3.307_711_591_3 * m + 0.230_969_929_2 * s,
+        -1.268_438_004_6 * l + 2.609_757_401_1 * m - 0.341_319_396_5 * s,
+        -0.004_196_086_3 * l - 0.703_418_614_7 * m + 1.707_614_701_0 * s,
+    ]
+}
+
+/// [`oklab_to_linear`], reducing chroma until the color fits in sRGB
+///
+/// Lightness and hue are kept; only as much colorfulness as needed is given up,
+/// which avoids the hue shifts of clipping each channel separately.
+pub fn oklab_to_linear_in_gamut([lightness, a, b]: [f32; 3]) -> [f32; 3] {
+    const EPSILON: f32 = 1e-4;
+    let in_gamut = |rgb: [f32; 3]| rgb.iter().all(|&channel| (-EPSILON..=1.0 + EPSILON).contains(&channel));
+
+    let rgb = oklab_to_linear([lightness, a, b]);
+    if in_gamut(rgb) {
+        return rgb.map(|channel| channel.clamp(0.0, 1.0));
+    }
+
+    // Gray at any lightness from 0 to 1 is in gamut, so bisect the kept chroma
+    let lightness = lightness.clamp(0.0, 1.0);
+    let (mut low, mut high) = (0.0f32, 1.0f32);
+    for _ in 0..16 {
+        let mid = (low + high) / 2.0;
+        if in_gamut(oklab_to_linear([lightness, a * mid, b * mid])) {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    oklab_to_linear([lightness, a * low, b * low]).map(|channel| channel.clamp(0.0, 1.0))
+}
+
+/// 3×3 matrix acting on column vectors, row by row
+pub type Matrix3 = [[f32; 3]; 3];
+
+/// CIE xy chromaticity of the D65 white point of sRGB
+pub const D65: [f32; 2] = [0.3127, 0.3290];
+
+/// Duv offset from the Planckian locus per unit of tint
+const TINT_DUV: f32 = 0.0002;
+
+/// Color temperature range covered by the locus approximation, in Kelvin
+pub const TEMPERATURE_RANGE: std::ops::RangeInclusive<f32> = 1667.0..=25000.0;
+
+const LINEAR_SRGB_TO_XYZ: Matrix3 = [
+    [0.412_456_4, 0.357_576_1, 0.180_437_5],
+    [0.212_672_9, 0.715_152_2, 0.072_175],
+    [0.019_333_9, 0.119_192, 0.950_304_1],
+];
+
+const XYZ_TO_LINEAR_SRGB: Matrix3 = [
+    [3.240_454_2, -1.537_138_5, -0.498_531_4],
+    [-0.969_266, 1.876_010_8, 0.041_556],
+    [0.055_643_4, -0.204_025_9, 1.057_225_2],
+];
+
+const BRADFORD: Matrix3 = [[0.8951, 0.2664, -0.1614], [-0.7502, 1.7135, 0.0367], [0.0389, -0.0685, 1.0296]];
+
+const BRADFORD_INVERSE: Matrix3 = [
+    [0.986_992_9, -0.147_054_3, 0.159_962_7],
+    [0.432_305_3, 0.518_360_3, 0.049_291_2],
+    [-0.008_528_7, 0.040_042_8, 0.968_486_7],
+];
+
+/// Product of two matrices, `a` applied after `b`
+pub fn matrix_mul(a: &Matrix3, b: &Matrix3) -> Matrix3 {
+    std::array::from_fn(|row| std::array::from_fn(|col| (0..3).map(|k| a[row][k] * b[k][col]).sum()))
+}
+
+/// Apply a matrix to a color
+#[inline]
+pub fn matrix_apply(m: &Matrix3, [r, g, b]: [f32; 3]) -> [f32; 3] {
+    m.map(|row| row[0] * r + row[1] * g + row[2] * b)
+}
+
+/// xy chromaticity of a linear sRGB color, `None` for black
+pub fn linear_to_xy(rgb: [f32; 3]) -> Option<[f32; 2]> {
+    let [x, y, z] = matrix_apply(&LINEAR_SRGB_TO_XYZ, rgb);
+    let sum = x + y + z;
+    (sum > 0.0).then(|| [x / sum, y / sum])
+}
+
+/// White point of a light source given as color temperature and tint
+///
+/// Temperatures follow the Planckian locus (Kim et al. cubic approximation);
+/// tint moves the white point across the locus, positive towards magenta. The
+/// locus is shifted by a constant so that 6504 K without tint is exactly D65,
+/// which makes the default settings of [`chromatic_adaptation`] a no-op.
+///
+/// # Arguments
+/// * `temperature` - Color temperature in Kelvin, clamped to [`TEMPERATURE_RANGE`]
+/// * `tint` - Green (negative) to magenta (positive) shift
+///
+/// # Returns
+/// CIE xy chromaticity of the white point
+pub fn white_point(temperature: f32, tint: f32) -> [f32; 2] {
+    let [u, v] = locus_uv(temperature);
+    let [nu, nv] = locus_normal(temperature);
+    let duv = -tint * TINT_DUV;
+    uv_to_xy([u + nu * duv, v + nv * duv])
+}
+
+/// Color temperature and tint of a white point, the inverse of [`white_point`]
+///
+/// The temperature is the closest point of the locus in the CIE 1960 uv plane,
+/// clamped to [`TEMPERATURE_RANGE`]; the tint is the signed distance from it.
+pub fn temperature_tint([x, y]: [f32; 2]) -> (f32, f32) {
+    let [u, v] = xy_to_uv([x, y]);
+    let distance = |mired: f32| {
+        let [lu, lv] = locus_uv(1e6 / mired);
+        (u - lu).hypot(v - lv)
+    };
+
+    // Scan in mired, where the locus is spaced evenly, then refine by ternary search
+    let (low, high) = (1e6 / TEMPERATURE_RANGE.end(), 1e6 / TEMPERATURE_RANGE.start());
+    let mut best = low;
+    let mut mired = low;
+    while mired <= high {
+        if distance(mired) < distance(best) {
+            best = mired;
+        }
+        mired += 1.0;
+    }
+    let (mut left, mut right) = ((best - 1.0).max(low), (best + 1.0).min(high));
+    for _ in 0..32 {
+        let third = (right - left) / 3.0;
+        if distance(left + third) < distance(right - third) {
+            right -= third;
+        } else {
+            left += third;
+        }
+    }
+
+    let temperature = 1e6 / ((left + right) / 2.0);
+    let [lu, lv] = locus_uv(temperature);
+    let [nu, nv] = locus_normal(temperature);
+    let duv = (u - lu) * nu + (v - lv) * nv;
+    (temperature, -duv / TINT_DUV)
+}
+
+/// Bradford chromatic adaptation in linear sRGB
+///
+/// A color seen under the `source` white is mapped to how it looks under the
+/// `target` white, so a neutral surface lit by `source` becomes neutral.
+///
+/// # Returns
+/// Matrix to apply to linear sRGB colors
+pub fn chromatic_adaptation(source: [f32; 2], target: [f32; 2]) -> Matrix3 {
+    let xy_to_xyz = |[x, y]: [f32; 2]| [x / y, 1.0, (1.0 - x - y) / y];
+    let source = matrix_apply(&BRADFORD, xy_to_xyz(source));
+    let target = matrix_apply(&BRADFORD, xy_to_xyz(target));
+
+    let mut scale = [[0.0; 3]; 3];
+    for channel in 0..3 {
+        scale[channel][channel] = target[channel] / source[channel];
+    }
+    let cone = matrix_mul(&BRADFORD_INVERSE, &matrix_mul(&scale, &BRADFORD));
+    matrix_mul(&XYZ_TO_LINEAR_SRGB, &matrix_mul(&cone, &LINEAR_SRGB_TO_XYZ))
+}
+
+/// Planckian locus in CIE 1960 uv, shifted to pass through D65 at 6504 K
+fn locus_uv(temperature: f32) -> [f32; 2] {
+    let [u, v] = planckian_uv(temperature);
+    let [u65, v65] = xy_to_uv(D65);
+    let [pu, pv] = planckian_uv(6504.0);
+    [u + u65 - pu, v + v65 - pv]
+}
+
+/// Unit normal of the locus, pointing towards green (larger v)
+fn locus_normal(temperature: f32) -> [f32; 2] {
+    let [u0, v0] = planckian_uv(temperature - 1.0);
+    let [u1, v1] = planckian_uv(temperature + 1.0);
+    let (du, dv) = (u1 - u0, v1 - v0);
+    let length = du.hypot(dv);
+    let normal = [-dv / length, du / length];
+    if normal[1] < 0.0 {
+        normal.map(|component| -component)
+    } else {
+        normal
+    }
+}
+
+fn planckian_uv(temperature: f32) -> [f32; 2] {
+    let t = temperature.clamp(*TEMPERATURE_RANGE.start(), *TEMPERATURE_RANGE.end()) as f64;
+    let (t2, t3) = (t * t, t * t * t);
+    let x = if t <= 4000.0 {
+        -0.266_123_9e9 / t3 - 0.234_358_9e6 / t2 + 0.877_695_6e3 / t + 0.179_910
+    } else {
+        -3.025_846_9e9 / t3 + 2.107_037_9e6 / t2 + 0.222_634_7e3 / t + 0.240_390
+    };
+    let (x2, x3) = (x * x, x * x * x);
+    let y = if t <= 2222.0 {
+        -1.106_381_4 * x3 - 1.348_110_20 * x2 + 2.185_558_32 * x - 0.202_196_83
+    } else if t <= 4000.0 {
+        -0.954_947_6 * x3 - 1.374_185_93 * x2 + 2.091_370_15 * x - 0.167_488_67
+    } else {
+        3.081_758_0 * x3 - 5.873_386_70 * x2 + 3.751_129_97 * x - 0.370_014_83
+    };
+    xy_to_uv([x as f32, y as f32])
+}
+
+fn xy_to_uv([x, y]: [f32; 2]) -> [f32; 2] {
+    let denominator = -2.0 * x + 12.0 * y + 3.0;
+    [4.0 * x / denominator, 6.0 * y / denominator]
+}
+
+fn uv_to_xy([u, v]: [f32; 2]) -> [f32; 2] {
+    let denominator = 2.0 * u - 8.0 * v + 4.0;
+    [3.0 * u / denominator, 2.0 * v / denominator]
+}
+
+fn build_table(f: fn(f32) -> f32) -> Vec<f32> {
+    (0..TABLE_SIZE).map(|i| f(i as f32 / (TABLE_SIZE - 1) as f32)).collect()
+}
+
+#[inline]
+fn interpolate(table: &[f32], value: f32) -> f32 {
+    let position = value * (table.len() - 1) as f32;
+    let lower = (position as usize).min(table.len() - 2);
+    let t = position - lower as f32;
+    table[lower] + (table[lower + 1] - table[lower]) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_functions_roundtrip() {
+        for level in 0..=255u8 {
+            let encoded = level as f32 / 255.0;
+            let linear = srgb8_to_linear(level);
+            assert!((linear_to_srgb(linear) - encoded).abs() < 1e-5, "{}", level);
+        }
+        // Middle gray is about a fifth of the light of white
+        assert!((srgb_to_linear(0.5) - 0.214).abs() < 1e-3);
+        assert!((luminance(1.0, 1.0, 1.0) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_oklab_roundtrip_and_gamut() {
+        for rgb in [[1.0, 0.0, 0.0], [0.2, 0.5, 0.9], [0.5, 0.5, 0.5], [0.0, 0.0, 0.0]] {
+            let back = oklab_to_linear(linear_to_oklab(rgb));
+            assert!(back.iter().zip(rgb.iter()).all(|(a, b)| (a - b).abs() < 1e-4), "{:?}", back);
+        }
+        // White has lightness 1 and no chroma
+        let [l, a, b] = linear_to_oklab([1.0, 1.0, 1.0]);
+        assert!((l - 1.0).abs() < 1e-4 && a.abs() < 1e-4 && b.abs() < 1e-4);
+
+        // Doubling the chroma of pure red leaves the gamut; the result keeps its hue
+        let [l, a, b] = linear_to_oklab([1.0, 0.0, 0.0]);
+        let mapped = oklab_to_linear_in_gamut([l, a * 2.0, b * 2.0]);
+        assert!(mapped.iter().all(|channel| (0.0..=1.0).contains(channel)));
+        let [_, a2, b2] = linear_to_oklab(mapped);
+        assert!((b2.atan2(a2) - b.atan2(a)).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_tables_match_exact_curves() {
+        for i in 0..=10_000 {
+            let value = i as f32 / 10_000.0;
+            assert!((srgb_to_linear_fast(value) - srgb_to_linear(value)).abs() < 1.0 / 65535.0, "{}", value);
+            assert!((linear_to_srgb_fast(value) - linear_to_srgb(value)).abs() < 1.0 / 65535.0, "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_white_point_roundtrip() {
+        // 6504 K without tint is D65, and adapting from it changes nothing
+        let d65 = white_point(6504.0, 0.0);
+        assert!((d65[0] - D65[0]).abs() < 1e-4 && (d65[1] - D65[1]).abs() < 1e-4);
+        let identity = chromatic_adaptation(d65, D65);
+        assert!((0..3).all(|row| (0..3).all(|col| (identity[row][col] - (row == col) as u8 as f32).abs() < 1e-3)));
+
+        for (temperature, tint) in [(2700.0, 0.0), (3200.0, 20.0), (5000.0, -35.0), (9000.0, 10.0)] {
+            let (back_temperature, back_tint) = temperature_tint(white_point(temperature, tint));
+            assert!((back_temperature - temperature).abs() < temperature * 0.005, "{} -> {}", temperature, back_temperature);
+            assert!((back_tint - tint).abs() < 1.0, "{} -> {}", tint, back_tint);
+        }
+
+        // Tungsten light is orange: red well above blue
+        let [x, y] = white_point(2700.0, 0.0);
+        let rgb = matrix_apply(&XYZ_TO_LINEAR_SRGB, [x / y, 1.0, (1.0 - x - y) / y]);
+        assert!(rgb[0] > rgb[2] * 2.0);
+    }
+}
@@ -0,0 +1,297 @@
+use image::{Rgba32FImage, RgbaImage};
+
+use crate::adjustments::{self, AutoAdjustments, Levels, WhiteBalance};
+use crate::formats::{self, ExportOptions};
+use crate::metadata::{ExifInfo, Metadata};
+use crate::pixel::{self, dither_to_rgba8};
+use crate::statistics::{self, ImageStatistics, StatisticsOptions};
+use crate::utils::{load_image_with, LoadOptions};
+use crate::{EditorError, Operation, Pipeline};
+
+/// Decoded image handle for chaining edits without re-encoding
+///
+/// The byte-oriented exports decode their input and encode a PNG on every call.
+/// `EditorImage` decodes once, applies each operation to the in-memory buffer and
+/// only encodes when [`EditorImage::encode`] is called. The decoded source is kept
+/// alongside the working copy so a slider change can [`reset`](EditorImage::reset)
+/// and replay the adjustment chain without decoding again. The metadata of the
+/// source file is written back on export.
+///
+/// Edits run on a float working buffer, so a chain of adjustments does not
+/// round to 8 bits after every step and 16-bit sources keep their precision.
+#[derive(Debug, Clone)]
+pub struct EditorImage {
+    original: Rgba32FImage,
+    current: Rgba32FImage,
+    metadata: Metadata,
+}
+
+impl EditorImage {
+    /// Wrap an already decoded 8-bit image
+    pub fn from_rgba(img: RgbaImage) -> EditorImage {
+        EditorImage::from_rgba32f(pixel::convert(&img))
+    }
+
+    /// Wrap an already decoded float image (sRGB, straight alpha, 0.0-1.0)
+    pub fn from_rgba32f(img: Rgba32FImage) -> EditorImage {
+        EditorImage {
+            original: img.clone(),
+            current: img,
+            metadata: Metadata::default(),
+        }
+    }
+
+    /// Decode image bytes into a new editing handle, applying the EXIF orientation
+    pub fn decode(image_data: &[u8]) -> Result<EditorImage, EditorError> {
+        EditorImage::decode_with(image_data, &LoadOptions::default())
+    }
+
+    /// Decode image bytes with explicit options
+    pub fn decode_with(image_data: &[u8], options: &LoadOptions) -> Result<EditorImage, EditorError> {
+        let (img, metadata) = load_image_with(image_data, options)?;
+        Ok(EditorImage {
+            metadata,
+            ..EditorImage::from_rgba32f(img)
+        })
+    }
+
+    /// Borrow the current working image
+    pub fn image(&self) -> &Rgba32FImage {
+        &self.current
+    }
+
+    /// 8-bit copy of the current image, dithered the same way as 8-bit exports
+    pub fn to_rgba8(&self) -> RgbaImage {
+        dither_to_rgba8(&self.current)
+    }
+
+    /// Metadata of the source file
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Common EXIF fields of the source file, `None` without EXIF
+    pub fn exif_info(&self) -> Result<Option<ExifInfo>, EditorError> {
+        self.metadata.exif_info()
+    }
+
+    /// Current width in pixels
+    pub fn width(&self) -> u32 {
+        self.current.width()
+    }
+
+    /// Current height in pixels
+    pub fn height(&self) -> u32 {
+        self.current.height()
+    }
+
+    /// Discard all edits and restore the decoded source image
+    pub fn reset(&mut self) {
+        self.current = self.original.clone();
+    }
+
+    /// Make the current state the new source image used by `reset`
+    pub fn commit(&mut self) {
+        self.original = self.current.clone();
+    }
+
+    /// Apply one operation to the current image
+    ///
+    /// Every operation checks its arguments before touching a pixel, so on error
+    /// the current image is left untouched.
+    pub fn apply_operation(&mut self, operation: &Operation) -> Result<(), EditorError> {
+        operation.apply(&mut self.current)
+    }
+
+    /// Apply auto levels to the current image
+    ///
+    /// # Returns
+    /// The levels that were applied (see [`adjustments::auto_levels`])
+    pub fn auto_levels(&mut self, clip_percent: f32) -> Result<Levels, EditorError> {
+        adjustments::auto_levels(&mut self.current, clip_percent)
+    }
+
+    /// Measure the white balance that makes an area of the current image neutral
+    ///
+    /// # Returns
+    /// Settings that neutralize the area when applied to the current image
+    /// (see [`adjustments::pick_neutral`])
+    pub fn pick_neutral(&self, x: u32, y: u32, width: u32, height: u32) -> Result<WhiteBalance, EditorError> {
+        adjustments::pick_neutral(&self.current, x, y, width, height)
+    }
+
+    /// Recommend slider values for the decoded source image
+    ///
+    /// The source is measured rather than the current image, so the result can
+    /// replace the slider settings that are replayed after a [`reset`](EditorImage::reset)
+    /// (see [`adjustments::analyze_auto_adjustments`]).
+    pub fn analyze_auto_adjustments(&self) -> AutoAdjustments {
+        adjustments::analyze_auto_adjustments(&self.original)
+    }
+
+    /// Run a parsed pipeline on the current image
+    ///
+    /// Runs of point operations are fused (see [`Pipeline::compile`]). On error
+    /// the current image is left untouched.
+    pub fn run_pipeline(&mut self, pipeline: &Pipeline) -> Result<(), EditorError> {
+        let compiled = pipeline.compile()?;
+        let mut processed = self.current.clone();
+        compiled.execute(&mut processed)?;
+        self.current = processed;
+        Ok(())
+    }
+
+    /// Encode the current image as PNG bytes, keeping the source metadata
+    pub fn encode(&self) -> Result<Vec<u8>, EditorError> {
+        self.encode_as(&ExportOptions::default())
+    }
+
+    /// Encode the current image with explicit format and metadata settings
+    /// (see [`crate::formats`])
+    pub fn encode_as(&self, options: &ExportOptions) -> Result<Vec<u8>, EditorError> {
+        let metadata = self.metadata.filtered(&options.metadata);
+        formats::encode_with_metadata(&self.current, &options.format, &metadata)
+    }
+
+    /// Histogram of the current image (see [`adjustments::calculate_histogram`])
+    pub fn histogram(&self) -> Vec<u32> {
+        adjustments::calculate_histogram(&self.current)
+    }
+
+    /// Statistics of the current image (see [`statistics::image_statistics`])
+    pub fn statistics(&self, options: &StatisticsOptions) -> Result<ImageStatistics, EditorError> {
+        statistics::image_statistics(&self.current, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::ExportFormat;
+    use crate::metadata::{tests::sample_exif, MetadataOptions};
+    use crate::utils::{load_image, to_bytes};
+    use crate::{filters, transforms};
+    use image::Rgba;
+    use std::collections::HashSet;
+
+    fn create_test_image() -> RgbaImage {
+        RgbaImage::from_fn(64, 48, |x, y| {
+            Rgba([(x * 4) as u8, (y * 5) as u8, 128, 255])
+        })
+    }
+
+    #[test]
+    fn test_chained_operations_match_single_steps() {
+        let bytes = to_bytes(&create_test_image()).unwrap();
+
+        let mut editor = EditorImage::decode(&bytes).unwrap();
+        editor.apply_operation(&Operation::Brightness { value: 20 }).unwrap();
+        editor.apply_operation(&Operation::Contrast { value: 1.5 }).unwrap();
+        editor.apply_operation(&Operation::Sepia).unwrap();
+
+        let mut expected: Rgba32FImage = load_image_with(&bytes, &LoadOptions::default()).unwrap().0;
+        adjustments::adjust_brightness(&mut expected, 20).unwrap();
+        adjustments::adjust_contrast(&mut expected, 1.5).unwrap();
+        filters::apply_sepia(&mut expected).unwrap();
+        assert_eq!(editor.image(), &expected);
+        let from_editor = load_image(&editor.encode().unwrap()).unwrap();
+        assert_eq!(from_editor, dither_to_rgba8(&expected));
+    }
+
+    #[test]
+    fn test_float_buffer_does_not_band() {
+        let gradient = RgbaImage::from_fn(256, 1, |x, _| Rgba([x as u8, x as u8, x as u8, 255]));
+        let levels = |img: &RgbaImage| img.pixels().map(|pixel| pixel[0]).collect::<HashSet<_>>().len();
+
+        // Darkening by three stops leaves about 100 levels in an 8-bit buffer
+        let mut narrow = gradient.clone();
+        adjustments::adjust_exposure(&mut narrow, -3.0).unwrap();
+        adjustments::adjust_exposure(&mut narrow, 3.0).unwrap();
+        assert!(levels(&narrow) <= 100);
+
+        let mut editor = EditorImage::from_rgba(gradient.clone());
+        editor.apply_operation(&Operation::Exposure { stops: -3.0 }).unwrap();
+        editor.apply_operation(&Operation::Exposure { stops: 3.0 }).unwrap();
+        assert_eq!(editor.to_rgba8(), gradient);
+    }
+
+    #[test]
+    fn test_reset_and_commit() {
+        let mut editor = EditorImage::from_rgba(create_test_image());
+        editor.apply_operation(&Operation::Rotate { angle: 90 }).unwrap();
+        assert_eq!((editor.width(), editor.height()), (48, 64));
+
+        editor.reset();
+        assert_eq!((editor.width(), editor.height()), (64, 48));
+
+        editor.apply_operation(&Operation::Crop { x: 0, y: 0, width: 32, height: 16 }).unwrap();
+        editor.commit();
+        editor.apply_operation(&Operation::Brightness { value: 10 }).unwrap();
+        editor.reset();
+        assert_eq!((editor.width(), editor.height()), (32, 16));
+        assert_eq!(editor.to_rgba8(), transforms::crop(&create_test_image(), 0, 0, 32, 16).unwrap());
+    }
+
+    #[test]
+    fn test_failed_operation_keeps_image() {
+        let mut editor = EditorImage::from_rgba(create_test_image());
+        editor.apply_operation(&Operation::Brightness { value: 10 }).unwrap();
+        let before = editor.image().clone();
+
+        let error = editor
+            .apply_operation(&Operation::Crop { x: 60, y: 0, width: 10, height: 10 })
+            .unwrap_err();
+        assert_eq!(error.parameter(), Some("x"));
+        assert_eq!(editor.image(), &before);
+
+        let error = editor
+            .apply_operation(&Operation::GaussianBlur { sigma: -1.0 })
+            .unwrap_err();
+        assert_eq!(error.parameter(), Some("sigma"));
+        assert_eq!(editor.image(), &before);
+    }
+
+    #[test]
+    fn test_decode_rejects_garbage() {
+        let error = EditorImage::decode(b"definitely not an image").err().unwrap();
+        assert_eq!(error.code(), "DECODE_ERROR");
+    }
+
+    #[test]
+    fn test_exif_orientation_and_metadata_export() {
+        // A landscape sensor image tagged "rotate 90° clockwise", as phones write portraits
+        let jpeg = ExportFormat::Jpeg { quality: 90, chroma_subsampling: Default::default() };
+        let source = Metadata {
+            exif: Some(sample_exif(6)),
+            icc_profile: None,
+            xmp: None,
+        };
+        let bytes = formats::encode_with_metadata(&create_test_image(), &jpeg, &source).unwrap();
+
+        let editor = EditorImage::decode(&bytes).unwrap();
+        assert_eq!((editor.width(), editor.height()), (48, 64));
+        assert_eq!(editor.exif_info().unwrap().unwrap().model.as_deref(), Some("Pixel 8"));
+
+        let unrotated = EditorImage::decode_with(&bytes, &LoadOptions { auto_orient: false }).unwrap();
+        assert_eq!((unrotated.width(), unrotated.height()), (64, 48));
+
+        // The exported file is upright for any viewer and still carries EXIF
+        let exported = editor.encode().unwrap();
+        let reloaded = EditorImage::decode(&exported).unwrap();
+        assert_eq!((reloaded.width(), reloaded.height()), (48, 64));
+        let info = reloaded.exif_info().unwrap().unwrap();
+        assert_eq!(info.orientation, Some(1));
+        assert!(info.gps.is_some());
+
+        let options = ExportOptions {
+            format: jpeg,
+            metadata: MetadataOptions { strip_gps: true, ..MetadataOptions::keep_all() },
+        };
+        let private = EditorImage::decode(&editor.encode_as(&options).unwrap()).unwrap();
+        assert_eq!(private.exif_info().unwrap().unwrap().gps, None);
+
+        let options = ExportOptions { format: jpeg, metadata: MetadataOptions::strip_all() };
+        let stripped = EditorImage::decode(&editor.encode_as(&options).unwrap()).unwrap();
+        assert!(stripped.metadata().is_empty());
+    }
+}
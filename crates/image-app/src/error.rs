@@ -0,0 +1,119 @@
+//! Error type shared by every operation in the crate
+//!
+//! Rust callers get [`EditorError`] directly. With the `wasm` feature it is
+//! converted into a JavaScript `Error` object carrying `code`, `message` and, for
+//! invalid arguments, the offending `parameter`.
+
+use std::fmt;
+
+/// Everything that can go wrong while decoding, processing or encoding an image
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditorError {
+    /// The input bytes are not an image in a supported format
+    Decode(String),
+    /// The processed image could not be encoded
+    Encode(String),
+    /// The image format is not compiled into this build
+    UnsupportedFormat(String),
+    /// An argument is outside its valid range
+    InvalidParameter { parameter: String, message: String },
+    /// A pipeline document does not match the pipeline schema
+    InvalidPipeline(String),
+    /// A pipeline was written by a newer format version
+    UnsupportedPipelineVersion(u32),
+}
+
+impl EditorError {
+    /// Build an [`EditorError::InvalidParameter`]
+    pub fn invalid_parameter(parameter: &str, message: impl Into<String>) -> EditorError {
+        EditorError::InvalidParameter {
+            parameter: parameter.to_string(),
+            message: message.into(),
+        }
+    }
+
+    /// Stable machine-readable error code
+    pub fn code(&self) -> &'static str {
+        match self {
+            EditorError::Decode(_) => "DECODE_ERROR",
+            EditorError::Encode(_) => "ENCODE_ERROR",
+            EditorError::UnsupportedFormat(_) => "UNSUPPORTED_FORMAT",
+            EditorError::InvalidParameter { .. } => "INVALID_PARAMETER",
+            EditorError::InvalidPipeline(_) => "INVALID_PIPELINE",
+            EditorError::UnsupportedPipelineVersion(_) => "UNSUPPORTED_PIPELINE_VERSION",
+        }
+    }
+
+    /// Name of the offending argument, if the error is about one
+    pub fn parameter(&self) -> Option<&str> {
+        match self {
+            EditorError::InvalidParameter { parameter, .. } => Some(parameter),
+            _ => None,
+        }
+    }
+
+    /// Prefix the offending parameter with its location in a pipeline
+    pub(crate) fn at_pipeline_step(self, step: usize) -> EditorError {
+        self.within(&format!("operations[{}]", step))
+    }
+
+    /// Prefix the offending parameter with the field that contains it
+    pub(crate) fn within(self, field: &str) -> EditorError {
+        match self {
+            EditorError::InvalidParameter { parameter, message } => EditorError::InvalidParameter {
+                parameter: format!("{}.{}", field, parameter),
+                message,
+            },
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for EditorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditorError::Decode(message) => write!(f, "Failed to decode image: {}", message),
+            EditorError::Encode(message) => write!(f, "Failed to encode image: {}", message),
+            EditorError::UnsupportedFormat(message) => write!(f, "Unsupported format: {}", message),
+            EditorError::InvalidParameter { parameter, message } => {
+                write!(f, "Invalid parameter `{}`: {}", parameter, message)
+            }
+            EditorError::InvalidPipeline(message) => write!(f, "Invalid pipeline: {}", message),
+            EditorError::UnsupportedPipelineVersion(version) => write!(
+                f,
+                "Unsupported pipeline version {} (newest supported is {})",
+                version,
+                crate::PIPELINE_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EditorError {}
+
+/// Fail unless `value` is a finite number
+pub(crate) fn ensure_finite(parameter: &str, value: f32) -> Result<(), EditorError> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(EditorError::invalid_parameter(parameter, "must be a finite number"))
+    }
+}
+
+/// Fail unless `value` is finite and not negative
+pub(crate) fn ensure_non_negative(parameter: &str, value: f32) -> Result<(), EditorError> {
+    ensure_finite(parameter, value)?;
+    if value < 0.0 {
+        return Err(EditorError::invalid_parameter(parameter, "must not be negative"));
+    }
+    Ok(())
+}
+
+/// Fail unless `value` is finite and strictly positive
+pub(crate) fn ensure_positive(parameter: &str, value: f32) -> Result<(), EditorError> {
+    ensure_finite(parameter, value)?;
+    if value <= 0.0 {
+        return Err(EditorError::invalid_parameter(parameter, "must be greater than 0"));
+    }
+    Ok(())
+}
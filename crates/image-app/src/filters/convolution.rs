@@ -0,0 +1,203 @@
+//! Convolution on float buffers
+//!
+//! Filters convolve premultiplied [`Rgba32FImage`] buffers (see
+//! [`crate::utils::premultiply`]) so transparent pixels carry no color into
+//! their neighbors. Samples outside the image are read according to a
+//! [`BorderMode`], so every output pixel, the outermost rows and columns
+//! included, comes from a full kernel.
+
+use std::str::FromStr;
+
+use image::{Rgba, Rgba32FImage};
+use serde::{Deserialize, Serialize};
+
+use crate::error::EditorError;
+
+/// What a kernel reads beyond the edges of the image
+///
+/// In JSON: `"clamp"`, `"mirror"`, `"wrap"` or `{ "constant": [r, g, b, a] }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BorderMode {
+    /// Repeat the edge pixel
+    #[default]
+    Clamp,
+    /// Reflect at the edge without repeating the edge pixel (`c b | a b c | b a`)
+    Mirror,
+    /// Continue from the opposite edge, for tiling textures
+    Wrap,
+    /// A fixed 8-bit RGBA color; transparent lets only the image itself count
+    Constant([u8; 4]),
+}
+
+impl FromStr for BorderMode {
+    type Err = EditorError;
+
+    /// Parse a mode name; `"constant"` reads transparent pixels
+    fn from_str(name: &str) -> Result<BorderMode, EditorError> {
+        match name {
+            "clamp" => Ok(BorderMode::Clamp),
+            "mirror" => Ok(BorderMode::Mirror),
+            "wrap" => Ok(BorderMode::Wrap),
+            "constant" => Ok(BorderMode::Constant([0; 4])),
+            _ => Err(EditorError::invalid_parameter(
+                "border",
+                format!("unknown border mode `{}`, expected clamp, mirror, wrap or constant", name),
+            )),
+        }
+    }
+}
+
+impl BorderMode {
+    /// Source index of every coordinate from `-radius` to `size + radius - 1`
+    ///
+    /// `None` marks coordinates that read the constant color instead.
+    pub(crate) fn indices(self, size: u32, radius: usize) -> Vec<Option<u32>> {
+        let size = size as i64;
+        (-(radius as i64)..size + radius as i64)
+            .map(|coordinate| {
+                if (0..size).contains(&coordinate) {
+                    return Some(coordinate as u32);
+                }
+                let index = match self {
+                    BorderMode::Clamp => coordinate.clamp(0, size - 1),
+                    BorderMode::Mirror if size == 1 => 0,
+                    BorderMode::Mirror => {
+                        let period = 2 * (size - 1);
+                        let folded = coordinate.rem_euclid(period);
+                        if folded < size { folded } else { period - folded }
+                    }
+                    BorderMode::Wrap => coordinate.rem_euclid(size),
+                    BorderMode::Constant(_) => return None,
+                };
+                Some(index as u32)
+            })
+            .collect()
+    }
+
+    /// Premultiplied unit value of the constant color, transparent for the other modes
+    pub(crate) fn constant(self) -> [f32; 4] {
+        match self {
+            BorderMode::Constant([r, g, b, a]) => {
+                let alpha = a as f32 / 255.0;
+                let premultiplied = |value: u8| value as f32 / 255.0 * alpha;
+                [premultiplied(r), premultiplied(g), premultiplied(b), alpha]
+            }
+            _ => [0.0; 4],
+        }
+    }
+}
+
+/// Normalized 1-D Gaussian kernel reaching three standard deviations out
+pub(crate) fn gaussian_kernel(sigma: f32) -> Vec<f32> {
+    let radius = (sigma * 3.0).ceil().max(1.0) as i32;
+    let weights: Vec<f32> = (-radius..=radius)
+        .map(|offset| (-(offset * offset) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total: f32 = weights.iter().sum();
+    weights.into_iter().map(|weight| weight / total).collect()
+}
+
+/// Convolve every channel with a horizontal and then a vertical 1-D kernel
+///
+/// Both kernels must have an odd length; their centers sit on the output pixel.
+pub(crate) fn convolve_separable(img: &Rgba32FImage, horizontal: &[f32], vertical: &[f32], border: BorderMode) -> Rgba32FImage {
+    let rows = convolve_1d(img, horizontal, true, border, border.constant());
+    // Beyond the top and bottom the rows pass reads the constant color filtered along the row
+    let row_sum: f32 = horizontal.iter().sum();
+    convolve_1d(&rows, vertical, false, border, border.constant().map(|value| value * row_sum))
+}
+
+fn convolve_1d(img: &Rgba32FImage, kernel: &[f32], horizontal: bool, border: BorderMode, constant: [f32; 4]) -> Rgba32FImage {
+    let (width, height) = img.dimensions();
+    let radius = kernel.len() / 2;
+    let indices = border.indices(if horizontal { width } else { height }, radius);
+
+    Rgba32FImage::from_fn(width, height, |x, y| {
+        let start = if horizontal { x } else { y } as usize;
+        let mut sum = [0.0f32; 4];
+        for (&weight, &index) in kernel.iter().zip(&indices[start..start + kernel.len()]) {
+            let sample = match index {
+                Some(index) if horizontal => img.get_pixel(index, y).0,
+                Some(index) => img.get_pixel(x, index).0,
+                None => constant,
+            };
+            for (total, value) in sum.iter_mut().zip(sample) {
+                *total += value * weight;
+            }
+        }
+        Rgba(sum)
+    })
+}
+
+/// Convolve every channel with a 2-D kernel given row by row
+///
+/// `kernel_width` and `kernel_height` must be odd; the kernel center sits on
+/// the output pixel. Like the separable path this runs on premultiplied
+/// buffers, so the result's alpha is the convolved coverage.
+pub(crate) fn convolve(img: &Rgba32FImage, kernel: &[f32], kernel_width: u32, kernel_height: u32, border: BorderMode) -> Rgba32FImage {
+    let (width, height) = img.dimensions();
+    let columns = border.indices(width, kernel_width as usize / 2);
+    let rows = border.indices(height, kernel_height as usize / 2);
+    let constant = border.constant();
+
+    Rgba32FImage::from_fn(width, height, |x, y| {
+        let mut sum = [0.0f32; 4];
+        for (row, &source_y) in kernel.chunks_exact(kernel_width as usize).zip(&rows[y as usize..]) {
+            for (&weight, &source_x) in row.iter().zip(&columns[x as usize..]) {
+                if weight == 0.0 {
+                    continue;
+                }
+                let sample = match (source_x, source_y) {
+                    (Some(sx), Some(sy)) => img.get_pixel(sx, sy).0,
+                    _ => constant,
+                };
+                for (total, value) in sum.iter_mut().zip(sample) {
+                    *total += value * weight;
+                }
+            }
+        }
+        Rgba(sum)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blur_keeps_flat_areas_up_to_the_border() {
+        let flat = Rgba32FImage::from_pixel(5, 4, Rgba([0.2, 0.4, 0.6, 1.0]));
+        let kernel = gaussian_kernel(2.0);
+        assert!((kernel.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+
+        for border in [BorderMode::Clamp, BorderMode::Mirror, BorderMode::Wrap] {
+            let blurred = convolve_separable(&flat, &kernel, &kernel, border);
+            for (a, b) in blurred.pixels().zip(flat.pixels()) {
+                assert!(a.0.iter().zip(b.0).all(|(a, b)| (a - b).abs() < 1e-5), "{:?} {:?}", border, a);
+            }
+        }
+    }
+
+    #[test]
+    fn test_border_indices() {
+        let indices = |mode: BorderMode| mode.indices(4, 3);
+        let some = |values: [u32; 10]| values.map(Some).to_vec();
+        assert_eq!(indices(BorderMode::Clamp), some([0, 0, 0, 0, 1, 2, 3, 3, 3, 3]));
+        assert_eq!(indices(BorderMode::Mirror), some([3, 2, 1, 0, 1, 2, 3, 2, 1, 0]));
+        assert_eq!(indices(BorderMode::Wrap), some([1, 2, 3, 0, 1, 2, 3, 0, 1, 2]));
+        assert_eq!(indices(BorderMode::Constant([0; 4]))[..4], [None, None, None, Some(0)]);
+        assert_eq!(BorderMode::Mirror.indices(1, 2), vec![Some(0); 5]);
+
+        // The separable and the 2-D path agree, constant border included
+        let img = Rgba32FImage::from_fn(6, 5, |x, y| Rgba([x as f32 / 5.0, y as f32 / 4.0, 0.5, 1.0]));
+        let border = BorderMode::Constant([255, 0, 0, 255]);
+        let kernel = [0.25, 0.5, 0.25];
+        let outer: Vec<f32> = kernel.iter().flat_map(|a| kernel.iter().map(move |b| a * b)).collect();
+        let separable = convolve_separable(&img, &kernel, &kernel, border);
+        let full = convolve(&img, &outer, 3, 3, border);
+        for (a, b) in separable.pixels().zip(full.pixels()) {
+            assert!(a.0.iter().zip(b.0).all(|(a, b)| (a - b).abs() < 1e-5), "{:?} {:?}", a, b);
+        }
+    }
+}
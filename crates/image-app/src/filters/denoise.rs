@@ -0,0 +1,248 @@
+//! Fast edge-preserving denoise
+//!
+//! A guided filter (He, Sun and Tang) run in YCbCr: every output value is a
+//! linear fit of its window, so areas whose variation is within the noise level
+//! are averaged while edges well above it keep their shape. All window sums come
+//! from running box filters, so the cost per pixel does not grow with the
+//! radius, and rows are spread over all cores on native builds.
+//!
+//! Luminance (grain) and chroma (color blotches) are filtered with their own
+//! strength; chroma uses windows twice as wide, because color noise is coarser
+//! and the eye forgives soft color far more than soft detail:
+//!
+//! ```json
+//! { "luminance": 30, "chroma": 60, "radius": 2 }
+//! ```
+//!
+//! Window statistics are weighted by alpha, so hidden colors of transparent
+//! pixels never bleed into visible ones. Windows are cut off at the image
+//! edges rather than padded, which leaves no border artifacts.
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::REC709_LUMA;
+use crate::error::{ensure_finite, EditorError};
+use crate::parallel::for_each_chunk;
+use crate::pixel::{map_pixels_at, EditorPixel, Image};
+
+/// Noise level in luma (0.0-1.0) smoothed away at full luminance strength
+const LUMA_SIGMA: f32 = 0.12;
+/// Noise level in Cb/Cr (-0.5 to 0.5) smoothed away at full chroma strength
+const CHROMA_SIGMA: f32 = 0.2;
+/// Rows each worker sums vertically in one run
+const BLOCK_ROWS: usize = 64;
+
+/// Settings of [`super::denoise`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DenoiseOptions {
+    /// Luminance noise reduction (0 to 100, 0 = off)
+    pub luminance: f32,
+    /// Color noise reduction (0 to 100, 0 = off)
+    pub chroma: f32,
+    /// Window radius in pixels for luminance, doubled for chroma (1 to 32)
+    pub radius: u32,
+}
+
+impl Default for DenoiseOptions {
+    fn default() -> DenoiseOptions {
+        DenoiseOptions { luminance: 50.0, chroma: 50.0, radius: 2 }
+    }
+}
+
+impl DenoiseOptions {
+    /// Parse options from JSON
+    pub fn from_json(json: &str) -> Result<DenoiseOptions, EditorError> {
+        let options: DenoiseOptions =
+            serde_json::from_str(json).map_err(|e| EditorError::invalid_parameter("denoise", e.to_string()))?;
+        options.validate()?;
+        Ok(options)
+    }
+
+    /// Check the strengths and radius
+    pub fn validate(&self) -> Result<(), EditorError> {
+        for (name, value) in [("luminance", self.luminance), ("chroma", self.chroma)] {
+            ensure_finite(name, value)?;
+            if !(0.0..=100.0).contains(&value) {
+                return Err(EditorError::invalid_parameter(name, "must be between 0 and 100"));
+            }
+        }
+        if !(1..=32).contains(&self.radius) {
+            return Err(EditorError::invalid_parameter("radius", "must be between 1 and 32"));
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn apply<P: EditorPixel>(img: &mut Image<P>, options: &DenoiseOptions) {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    if width == 0 || height == 0 {
+        return;
+    }
+    let [kr, kg, kb] = REC709_LUMA;
+
+    // Y, Cb and Cr planes of the encoded values, plus alpha
+    let mut planes: [Vec<f32>; 3] = std::array::from_fn(|_| Vec::with_capacity(width * height));
+    let mut alpha = Vec::with_capacity(width * height);
+    for pixel in img.pixels() {
+        let [r, g, b, a] = pixel.to_unit();
+        let y = kr * r + kg * g + kb * b;
+        planes[0].push(y);
+        planes[1].push((b - y) / (2.0 * (1.0 - kb)));
+        planes[2].push((r - y) / (2.0 * (1.0 - kr)));
+        alpha.push(a);
+    }
+
+    let radius = options.radius as usize;
+    let settings = [
+        (options.luminance, LUMA_SIGMA, radius),
+        (options.chroma, CHROMA_SIGMA, radius * 2),
+        (options.chroma, CHROMA_SIGMA, radius * 2),
+    ];
+    for (plane, (strength, sigma, radius)) in planes.iter_mut().zip(settings) {
+        if strength > 0.0 {
+            let noise = sigma * strength / 100.0;
+            *plane = guided_filter(plane, &alpha, width, height, radius, noise * noise);
+        }
+    }
+
+    map_pixels_at(img, |x, y, [_, _, _, a]| {
+        let index = y as usize * width + x as usize;
+        let [y, cb, cr] = [planes[0][index], planes[1][index], planes[2][index]];
+        let r = y + 2.0 * (1.0 - kr) * cr;
+        let b = y + 2.0 * (1.0 - kb) * cb;
+        let g = (y - kr * r - kb * b) / kg;
+        [r, g, b, a]
+    });
+}
+
+/// Self-guided filter of one plane with alpha-weighted window statistics
+///
+/// `epsilon` is the variance below which a window counts as flat.
+fn guided_filter(plane: &[f32], alpha: &[f32], width: usize, height: usize, radius: usize, epsilon: f32) -> Vec<f32> {
+    let weights = box_sum(alpha, width, height, radius);
+    let weighted: Vec<f32> = plane.iter().zip(alpha).map(|(&value, &a)| value * a).collect();
+    let squares: Vec<f32> = plane.iter().zip(alpha).map(|(&value, &a)| value * value * a).collect();
+    let sums = box_sum(&weighted, width, height, radius);
+    let square_sums = box_sum(&squares, width, height, radius);
+
+    // Linear model value * scale + offset of every window
+    let mut scale = vec![1.0; plane.len()];
+    let mut offset = vec![0.0; plane.len()];
+    for index in 0..plane.len() {
+        // Nothing visible in the window, keep the value
+        if weights[index] <= f32::EPSILON {
+            continue;
+        }
+        let mean = sums[index] / weights[index];
+        let variance = (square_sums[index] / weights[index] - mean * mean).max(0.0);
+        scale[index] = variance / (variance + epsilon);
+        offset[index] = mean * (1.0 - scale[index]);
+    }
+
+    // Every pixel averages the models of all windows covering it
+    let scale = box_sum(&scale, width, height, radius);
+    let offset = box_sum(&offset, width, height, radius);
+    let window = |position: usize, size: usize| (position + radius).min(size - 1) - position.saturating_sub(radius) + 1;
+    (0..plane.len())
+        .map(|index| {
+            let count = (window(index % width, width) * window(index / width, height)) as f32;
+            (scale[index] * plane[index] + offset[index]) / count
+        })
+        .collect()
+}
+
+/// Sum of every `2 * radius + 1` square window, cut off at the image edges
+fn box_sum(values: &[f32], width: usize, height: usize, radius: usize) -> Vec<f32> {
+    let mut rows = vec![0.0; values.len()];
+    for_each_chunk(&mut rows, width, |y, row| {
+        let source = &values[y * width..(y + 1) * width];
+        let mut sum: f32 = source[..=radius.min(width - 1)].iter().sum();
+        for (x, total) in row.iter_mut().enumerate() {
+            *total = sum;
+            if x + radius + 1 < width {
+                sum += source[x + radius + 1];
+            }
+            if x >= radius {
+                sum -= source[x - radius];
+            }
+        }
+    });
+
+    let mut sums = vec![0.0; values.len()];
+    let row = |y: usize| &rows[y * width..(y + 1) * width];
+    for_each_chunk(&mut sums, width * BLOCK_ROWS, |block, chunk| {
+        let first = block * BLOCK_ROWS;
+        // Start each block from a full window, then slide it down
+        let mut sum = vec![0.0f32; width];
+        for y in first.saturating_sub(radius)..=(first + radius).min(height - 1) {
+            sum.iter_mut().zip(row(y)).for_each(|(total, value)| *total += value);
+        }
+        for (offset, output) in chunk.chunks_exact_mut(width).enumerate() {
+            let y = first + offset;
+            output.copy_from_slice(&sum);
+            if y + radius + 1 < height {
+                sum.iter_mut().zip(row(y + radius + 1)).for_each(|(total, value)| *total += value);
+            }
+            if y >= radius {
+                sum.iter_mut().zip(row(y - radius)).for_each(|(total, value)| *total -= value);
+            }
+        }
+    });
+    sums
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, Rgba32FImage};
+
+    /// Repeatable noise between -1.0 and 1.0
+    fn noise(x: u32, y: u32, seed: u32) -> f32 {
+        let hash = (x.wrapping_mul(73_856_093) ^ y.wrapping_mul(19_349_663) ^ seed.wrapping_mul(83_492_791)).wrapping_mul(2_654_435_761);
+        (hash >> 8) as f32 / (1 << 23) as f32 - 1.0
+    }
+
+    fn spread(img: &Rgba32FImage, channel: impl Fn([f32; 4]) -> f32, x_range: std::ops::Range<u32>) -> f32 {
+        let values: Vec<f32> = img.enumerate_pixels().filter(|(x, _, _)| x_range.contains(x)).map(|(_, _, p)| channel(p.0)).collect();
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        (values.iter().map(|value| (value - mean).powi(2)).sum::<f32>() / values.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_smooths_noise_and_keeps_edges() {
+        // Gray halves with grain, a hard edge at x = 32
+        let mut img = Rgba32FImage::from_fn(64, 48, |x, y| {
+            let base = if x < 32 { 0.3 } else { 0.7 };
+            let grain = noise(x, y, 1) * 0.03;
+            Rgba([base + grain, base + grain, base + grain, 1.0])
+        });
+        let luma = |[r, g, b, _]: [f32; 4]| REC709_LUMA[0] * r + REC709_LUMA[1] * g + REC709_LUMA[2] * b;
+        let before = spread(&img, luma, 4..28);
+        apply(&mut img, &DenoiseOptions { luminance: 60.0, chroma: 0.0, radius: 3 });
+
+        assert!(spread(&img, luma, 4..28) < before / 3.0, "{} {}", before, spread(&img, luma, 4..28));
+        let step = img.get_pixel(33, 20)[0] - img.get_pixel(30, 20)[0];
+        assert!(step > 0.35, "{}", step);
+        // Untouched chroma of a gray image stays gray
+        assert!(img.pixels().all(|p| (p[0] - p[1]).abs() < 1e-5 && (p[1] - p[2]).abs() < 1e-5));
+    }
+
+    #[test]
+    fn test_chroma_only() {
+        // Color blotches on a flat mid tone, luma held constant
+        let mut img = Rgba32FImage::from_fn(48, 48, |x, y| {
+            let tint = noise(x / 2, y / 2, 7) * 0.05;
+            Rgba([0.5 + tint, 0.5 - tint * REC709_LUMA[0] / REC709_LUMA[1], 0.5, 1.0])
+        });
+        let red_minus_blue = |[r, _, b, _]: [f32; 4]| r - b;
+        let before = spread(&img, red_minus_blue, 0..48);
+        let original = img.clone();
+        apply(&mut img, &DenoiseOptions { luminance: 0.0, chroma: 80.0, radius: 2 });
+
+        assert!(spread(&img, red_minus_blue, 0..48) < before / 3.0);
+        let luma = |p: &Rgba<f32>| REC709_LUMA[0] * p[0] + REC709_LUMA[1] * p[1] + REC709_LUMA[2] * p[2];
+        assert!(img.pixels().zip(original.pixels()).all(|(a, b)| (luma(a) - luma(b)).abs() < 1e-5));
+        assert_eq!(DenoiseOptions::from_json(r#"{ "radius": 0 }"#).unwrap_err().parameter(), Some("radius"));
+    }
+}
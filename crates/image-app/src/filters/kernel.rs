@@ -0,0 +1,456 @@
+//! Custom convolution kernels
+//!
+//! [`super::convolve`] runs any odd-sized kernel over chosen channels, with a
+//! divisor, a bias and a [`BorderMode`]. Kernels are either given weight by
+//! weight or picked from a small library of named ones:
+//!
+//! ```json
+//! { "kernel": { "name": "custom", "width": 3, "height": 3, "weights": [1, 2, 1, 2, 4, 2, 1, 2, 1] } }
+//! { "kernel": { "name": "motion_blur", "length": 15, "angle": 30 }, "border": "mirror" }
+//! { "kernel": { "name": "emboss", "angle": 135 }, "bias": 128 }
+//! ```
+//!
+//! Kernels that are the outer product of a row and a column (box blurs, most
+//! blurs designed by hand) are detected and run as two 1-D passes.
+//!
+//! Angles are in degrees in image coordinates: 0 points right, 90 points down.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::convolution::{convolve, convolve_separable, BorderMode};
+use crate::error::{ensure_finite, EditorError};
+use crate::pixel::{map_pixels_at, EditorPixel, Image};
+use crate::utils::premultiply;
+
+/// Largest kernel width or height
+pub const MAX_KERNEL_SIZE: u32 = 127;
+
+/// Weights of a convolution kernel, row by row
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Kernel {
+    /// Columns (odd, 1 to [`MAX_KERNEL_SIZE`])
+    pub width: u32,
+    /// Rows (odd, 1 to [`MAX_KERNEL_SIZE`])
+    pub height: u32,
+    /// `width * height` weights, the center one on the output pixel
+    pub weights: Vec<f32>,
+}
+
+impl Kernel {
+    /// Build a kernel from its weights
+    ///
+    /// # Arguments
+    /// * `weights` - `width * height` weights, row by row
+    /// * `width`, `height` - Odd kernel size (1 to [`MAX_KERNEL_SIZE`])
+    pub fn new(weights: Vec<f32>, width: u32, height: u32) -> Result<Kernel, EditorError> {
+        let kernel = Kernel { width, height, weights };
+        kernel.validate()?;
+        Ok(kernel)
+    }
+
+    /// Check the size and weights
+    pub fn validate(&self) -> Result<(), EditorError> {
+        for (name, size) in [("width", self.width), ("height", self.height)] {
+            if size % 2 == 0 || size > MAX_KERNEL_SIZE {
+                return Err(EditorError::invalid_parameter(
+                    name,
+                    format!("must be odd and between 1 and {}", MAX_KERNEL_SIZE),
+                ));
+            }
+        }
+        let expected = (self.width * self.height) as usize;
+        if self.weights.len() != expected {
+            return Err(EditorError::invalid_parameter(
+                "weights",
+                format!("expected {} weights for a {}x{} kernel, got {}", expected, self.width, self.height, self.weights.len()),
+            ));
+        }
+        if self.weights.iter().any(|weight| !weight.is_finite()) {
+            return Err(EditorError::invalid_parameter("weights", "must be finite numbers"));
+        }
+        Ok(())
+    }
+
+    /// Sum of all weights
+    pub fn sum(&self) -> f32 {
+        self.weights.iter().sum()
+    }
+
+    /// Laplacian edge detection: flat areas turn black, edges light up
+    pub fn edge_detect() -> Kernel {
+        Kernel { width: 3, height: 3, weights: vec![0.0, 1.0, 0.0, 1.0, -4.0, 1.0, 0.0, 1.0, 0.0] }
+    }
+
+    /// Outline: like edge detection but reacting to diagonal edges as well
+    pub fn outline() -> Kernel {
+        Kernel { width: 3, height: 3, weights: vec![-1.0, -1.0, -1.0, -1.0, 8.0, -1.0, -1.0, -1.0, -1.0] }
+    }
+
+    /// Square of equal weights reaching `radius` pixels out
+    pub fn box_blur(radius: u32) -> Kernel {
+        let size = radius * 2 + 1;
+        Kernel { width: size, height: size, weights: vec![1.0; (size * size) as usize] }
+    }
+
+    /// Antialiased line of `length` pixels through the center at `angle` degrees
+    ///
+    /// Even lengths are rounded up to the next odd kernel size.
+    pub fn motion_blur(length: u32, angle: f32) -> Kernel {
+        let size = length | 1;
+        let radius = (size / 2) as i32;
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let weights = (-radius..=radius)
+            .flat_map(|dy| (-radius..=radius).map(move |dx| (dx as f32, dy as f32)))
+            .map(|(dx, dy)| {
+                let along = dx * cos + dy * sin;
+                let across = -dx * sin + dy * cos;
+                if along.abs() > radius as f32 + 0.5 {
+                    0.0
+                } else {
+                    (1.0 - across.abs()).max(0.0)
+                }
+            })
+            .collect();
+        Kernel { width: size, height: size, weights }
+    }
+
+    /// 3x3 relief lit along `angle` degrees, with a weight of 1 in the center
+    ///
+    /// At 45 degrees this is the classic `[-2 -1 0; -1 1 1; 0 1 2]` emboss.
+    pub fn emboss(angle: f32) -> Kernel {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let weights = (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (dx as f32, dy as f32)))
+            .map(|(dx, dy)| {
+                if dx == 0.0 && dy == 0.0 {
+                    return 1.0;
+                }
+                let weight = std::f32::consts::SQRT_2 * (dx * cos + dy * sin);
+                // Snap rounding noise so axis-aligned and diagonal angles give exact weights
+                (weight * 1e4).round() / 1e4
+            })
+            .collect();
+        Kernel { width: 3, height: 3, weights }
+    }
+
+    /// Row and column whose outer product is this kernel, if there are any
+    fn factors(&self) -> Option<(Vec<f32>, Vec<f32>)> {
+        let width = self.width as usize;
+        let (pivot_index, &pivot) = self.weights.iter().enumerate().max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))?;
+        if pivot == 0.0 {
+            return None;
+        }
+        let (pivot_row, pivot_column) = (pivot_index / width, pivot_index % width);
+        let horizontal = self.weights[pivot_row * width..(pivot_row + 1) * width].to_vec();
+        let vertical: Vec<f32> = self.weights.chunks_exact(width).map(|row| row[pivot_column] / pivot).collect();
+
+        let tolerance = pivot.abs() * 1e-5;
+        let separable = self.weights.chunks_exact(width).zip(&vertical).all(|(row, &scale)| {
+            row.iter().zip(&horizontal).all(|(&weight, &column)| (weight - scale * column).abs() <= tolerance)
+        });
+        separable.then_some((horizontal, vertical))
+    }
+}
+
+/// Kernel given by its weights or by name, tagged by `name` in JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "name", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ConvolutionKernel {
+    /// Explicit weights
+    Custom(Kernel),
+    /// See [`Kernel::edge_detect`]
+    EdgeDetect,
+    /// See [`Kernel::outline`]
+    Outline,
+    /// See [`Kernel::box_blur`]
+    BoxBlur { radius: u32 },
+    /// See [`Kernel::motion_blur`]
+    MotionBlur { length: u32, angle: f32 },
+    /// See [`Kernel::emboss`]
+    Emboss { angle: f32 },
+}
+
+impl ConvolutionKernel {
+    /// Weights of this kernel
+    pub fn kernel(&self) -> Result<Kernel, EditorError> {
+        let max_radius = MAX_KERNEL_SIZE / 2;
+        match *self {
+            ConvolutionKernel::Custom(ref kernel) => kernel.validate().map(|_| kernel.clone()),
+            ConvolutionKernel::EdgeDetect => Ok(Kernel::edge_detect()),
+            ConvolutionKernel::Outline => Ok(Kernel::outline()),
+            ConvolutionKernel::BoxBlur { radius } => {
+                if radius > max_radius {
+                    return Err(EditorError::invalid_parameter("radius", format!("must be at most {}", max_radius)));
+                }
+                Ok(Kernel::box_blur(radius))
+            }
+            ConvolutionKernel::MotionBlur { length, angle } => {
+                if !(1..MAX_KERNEL_SIZE).contains(&length) {
+                    return Err(EditorError::invalid_parameter(
+                        "length",
+                        format!("must be between 1 and {}", MAX_KERNEL_SIZE - 1),
+                    ));
+                }
+                ensure_finite("angle", angle)?;
+                Ok(Kernel::motion_blur(length, angle))
+            }
+            ConvolutionKernel::Emboss { angle } => {
+                ensure_finite("angle", angle)?;
+                Ok(Kernel::emboss(angle))
+            }
+        }
+    }
+}
+
+/// Channels a kernel is applied to, written as letters of `"rgba"` in JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Channels {
+    pub red: bool,
+    pub green: bool,
+    pub blue: bool,
+    pub alpha: bool,
+}
+
+impl Channels {
+    /// Color only, alpha kept
+    pub const RGB: Channels = Channels { red: true, green: true, blue: true, alpha: false };
+    /// Color and alpha
+    pub const RGBA: Channels = Channels { red: true, green: true, blue: true, alpha: true };
+}
+
+impl Default for Channels {
+    fn default() -> Channels {
+        Channels::RGB
+    }
+}
+
+impl FromStr for Channels {
+    type Err = EditorError;
+
+    /// Parse letters such as `"rgb"`, `"a"` or `"rb"`
+    fn from_str(letters: &str) -> Result<Channels, EditorError> {
+        if letters.is_empty() {
+            return Err(EditorError::invalid_parameter("channels", "must name at least one channel"));
+        }
+        let mut channels = Channels { red: false, green: false, blue: false, alpha: false };
+        for letter in letters.chars() {
+            match letter.to_ascii_lowercase() {
+                'r' => channels.red = true,
+                'g' => channels.green = true,
+                'b' => channels.blue = true,
+                'a' => channels.alpha = true,
+                _ => {
+                    return Err(EditorError::invalid_parameter(
+                        "channels",
+                        format!("unknown channel `{}`, expected letters of \"rgba\"", letter),
+                    ))
+                }
+            }
+        }
+        Ok(channels)
+    }
+}
+
+impl TryFrom<String> for Channels {
+    type Error = EditorError;
+
+    fn try_from(letters: String) -> Result<Channels, EditorError> {
+        letters.parse()
+    }
+}
+
+impl From<Channels> for String {
+    fn from(channels: Channels) -> String {
+        channels.to_string()
+    }
+}
+
+impl fmt::Display for Channels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (enabled, letter) in [(self.red, 'r'), (self.green, 'g'), (self.blue, 'b'), (self.alpha, 'a')] {
+            if enabled {
+                write!(f, "{}", letter)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Settings of [`super::convolve`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConvolveOptions {
+    pub kernel: ConvolutionKernel,
+    /// Weighted sums are divided by this, the sum of the weights when omitted
+    /// (or 1 for kernels summing to zero)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub divisor: Option<f32>,
+    /// Offset in 8-bit levels added after dividing, e.g. 128 for emboss
+    #[serde(default)]
+    pub bias: f32,
+    #[serde(default)]
+    pub channels: Channels,
+    #[serde(default)]
+    pub border: BorderMode,
+}
+
+impl ConvolveOptions {
+    /// Options running `kernel` with the automatic divisor, no bias, on color only
+    pub fn new(kernel: ConvolutionKernel) -> ConvolveOptions {
+        ConvolveOptions { kernel, divisor: None, bias: 0.0, channels: Channels::default(), border: BorderMode::default() }
+    }
+
+    /// Parse options from JSON
+    pub fn from_json(json: &str) -> Result<ConvolveOptions, EditorError> {
+        let options: ConvolveOptions =
+            serde_json::from_str(json).map_err(|e| EditorError::invalid_parameter("convolve", e.to_string()))?;
+        options.validate()?;
+        Ok(options)
+    }
+
+    /// Check the kernel, divisor, bias and channels
+    ///
+    /// Kernels whose weights cancel out (edge detection, outlines) turn flat
+    /// areas to zero, so they cannot filter alpha without erasing the image.
+    pub fn validate(&self) -> Result<(), EditorError> {
+        let kernel = self.kernel.kernel().map_err(|e| e.within("kernel"))?;
+        if self.channels.alpha && kernel.sum().abs() < 1e-6 {
+            return Err(EditorError::invalid_parameter(
+                "channels",
+                "alpha cannot be filtered with a kernel whose weights sum to zero",
+            ));
+        }
+        if let Some(divisor) = self.divisor {
+            ensure_finite("divisor", divisor)?;
+            if divisor == 0.0 {
+                return Err(EditorError::invalid_parameter("divisor", "must not be zero"));
+            }
+        }
+        ensure_finite("bias", self.bias)
+    }
+}
+
+/// Divisor used when none is given: the weight sum, or 1 when the weights cancel out
+pub(crate) fn auto_divisor(kernel: &Kernel) -> f32 {
+    let sum = kernel.sum();
+    if sum.abs() < 1e-6 {
+        1.0
+    } else {
+        sum
+    }
+}
+
+/// Convolve the chosen channels of every pixel with `kernel`
+///
+/// Runs on premultiplied color, so hidden colors never leak into visible
+/// pixels. When alpha is filtered too, colors are the alpha-weighted average
+/// scaled by the kernel sum over `divisor`, like every other channel, and
+/// alpha is its own convolution over `divisor`. Otherwise the shape stays as
+/// it is and the coverage transparent neighbors lack is filled in with the
+/// center pixel's own color, which keeps kernels with negative weights
+/// balanced next to transparency. `bias` is on the normalized 0.0-1.0 scale
+/// and added to every filtered channel.
+pub(crate) fn apply<P: EditorPixel>(img: &mut Image<P>, kernel: &Kernel, divisor: f32, bias: f32, channels: Channels, border: BorderMode) {
+    let source = premultiply(img);
+    let convolved = match kernel.factors() {
+        Some((horizontal, vertical)) => convolve_separable(&source, &horizontal, &vertical, border),
+        None => convolve(&source, &kernel.weights, kernel.width, kernel.height, border),
+    };
+    let kernel_sum = kernel.sum();
+    let gain = kernel_sum / divisor;
+    let enabled = [channels.red, channels.green, channels.blue];
+
+    map_pixels_at(img, |x, y, [r, g, b, a]| {
+        let [sum_r, sum_g, sum_b, alpha_sum] = convolved.get_pixel(x, y).0;
+        let color = [r, g, b];
+        let filter = |channel: usize| {
+            let sum = [sum_r, sum_g, sum_b][channel];
+            if !channels.alpha {
+                (sum + (kernel_sum - alpha_sum) * color[channel]) / divisor + bias
+            } else if alpha_sum.abs() > f32::EPSILON {
+                sum / alpha_sum * gain + bias
+            } else {
+                // Nothing visible to sample from
+                color[channel] * gain + bias
+            }
+        };
+        let [r, g, b] = std::array::from_fn(|channel| if enabled[channel] { filter(channel) } else { color[channel] });
+        let a = if channels.alpha { alpha_sum / divisor + bias } else { a };
+        [r, g, b, a]
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_named_kernels() {
+        assert_eq!(Kernel::emboss(45.0).weights, [-2.0, -1.0, 0.0, -1.0, 1.0, 1.0, 0.0, 1.0, 2.0]);
+        // A horizontal motion blur is a single row, which factors into a 1-D pass
+        let motion = Kernel::motion_blur(5, 0.0);
+        assert_eq!(&motion.weights[10..15], &[1.0; 5]);
+        assert_eq!(motion.sum(), 5.0);
+        assert!(motion.factors().is_some() && Kernel::box_blur(2).factors().is_some());
+        assert!(Kernel::outline().factors().is_none());
+        // Diagonal lines are symmetric about the center
+        let diagonal = Kernel::motion_blur(7, 45.0);
+        assert_eq!(diagonal.weights[8], diagonal.weights[40]);
+        assert!(diagonal.weights[6] == 0.0 && diagonal.weights[8] == 1.0);
+
+        let json = r#"{ "kernel": { "name": "custom", "width": 2, "height": 3, "weights": [1, 1, 1, 1, 1, 1] } }"#;
+        assert_eq!(ConvolveOptions::from_json(json).unwrap_err().parameter(), Some("kernel.width"));
+        let json = r#"{ "kernel": { "name": "box_blur", "radius": 1 }, "channels": "rgx" }"#;
+        assert!(ConvolveOptions::from_json(json).is_err());
+        let options = ConvolveOptions::from_json(r#"{ "kernel": { "name": "emboss", "angle": 45 }, "channels": "ga" }"#).unwrap();
+        assert_eq!((options.channels.green, options.channels.red, options.channels.to_string()), (true, false, "ga".to_string()));
+        // Weights that cancel out would turn alpha to zero everywhere
+        let json = r#"{ "kernel": { "name": "outline" }, "channels": "ga" }"#;
+        assert_eq!(ConvolveOptions::from_json(json).unwrap_err().parameter(), Some("channels"));
+        let json = r#"{ "kernel": { "name": "edge_detect" }, "divisor": 2, "channels": "rgba" }"#;
+        assert_eq!(ConvolveOptions::from_json(json).unwrap_err().parameter(), Some("channels"));
+        assert!(ConvolveOptions::from_json(r#"{ "kernel": { "name": "edge_detect" }, "channels": "rgb" }"#).is_ok());
+    }
+
+    #[test]
+    fn test_apply_kernel_to_channels() {
+        // Edge detection turns flat areas black, border included, and leaves alpha alone
+        let mut img = RgbaImage::from_fn(8, 8, |x, _| if x < 4 { Rgba([50, 100, 150, 200]) } else { Rgba([200, 100, 50, 200]) });
+        apply(&mut img, &Kernel::edge_detect(), 1.0, 0.0, Channels::RGB, BorderMode::Mirror);
+        assert_eq!(img.get_pixel(0, 0).0, [0, 0, 0, 200]);
+        assert_eq!(img.get_pixel(7, 7).0, [0, 0, 0, 200]);
+        assert!(img.get_pixel(3, 4)[0] > 0 && img.get_pixel(4, 4)[2] > 0);
+
+        // Blurring with alpha softens the shape without bringing out hidden colors
+        let shape = RgbaImage::from_fn(9, 9, |x, _| if x < 4 { Rgba([255, 0, 0, 255]) } else { Rgba([0, 255, 0, 0]) });
+        let mut img = shape.clone();
+        apply(&mut img, &Kernel::box_blur(1), 9.0, 0.0, Channels::RGBA, BorderMode::Clamp);
+        assert_eq!(img.get_pixel(4, 4).0, [255, 0, 0, 85]);
+        assert_eq!(img.get_pixel(3, 4).0, [255, 0, 0, 170]);
+        // The 2-D path agrees with the separable one
+        let mut weights = vec![1.0; 9];
+        weights[0] = 1.0 + 1e-3;
+        let mut full = shape.clone();
+        apply(&mut full, &Kernel { weights, ..Kernel::box_blur(1) }, 9.0, 0.0, Channels::RGBA, BorderMode::Clamp);
+        assert_eq!(full.get_pixel(4, 4).0, [255, 0, 0, 85]);
+
+        // An explicit divisor scales color and alpha alike
+        let opaque = RgbaImage::from_pixel(5, 5, Rgba([200, 100, 40, 255]));
+        let mut rgb = opaque.clone();
+        apply(&mut rgb, &Kernel::box_blur(1), 18.0, 0.0, Channels::RGB, BorderMode::Clamp);
+        let mut rgba = opaque.clone();
+        apply(&mut rgba, &Kernel::box_blur(1), 18.0, 0.0, Channels::RGBA, BorderMode::Clamp);
+        assert_eq!(rgb.get_pixel(2, 2).0, [100, 50, 20, 255]);
+        assert_eq!(rgba.get_pixel(2, 2).0, [100, 50, 20, 128]);
+        // and so does the bias
+        let mut rgba = opaque;
+        apply(&mut rgba, &Kernel::box_blur(1), 9.0, 10.0 / 255.0, Channels::RGBA, BorderMode::Clamp);
+        assert_eq!(rgba.get_pixel(2, 2).0, [210, 110, 50, 255]);
+    }
+}
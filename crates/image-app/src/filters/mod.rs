@@ -1,229 +1,442 @@
-use wasm_bindgen::prelude::*;
-use crate::utils::{load_image, to_bytes, console_log};
+mod convolution;
+pub mod denoise;
+pub mod kernel;
+pub mod unsharp;
+
+use image::imageops;
+
+use crate::error::{ensure_finite, ensure_non_negative, EditorError};
+use crate::pixel::{EditorPixel, Image};
+use crate::utils::{console_log, is_opaque, premultiply, unpremultiply};
+
+pub use convolution::BorderMode;
+pub use denoise::DenoiseOptions;
+pub use kernel::{Channels, ConvolutionKernel, ConvolveOptions, Kernel};
+pub use unsharp::UnsharpMaskOptions;
 
 /// Apply Gaussian blur to image
-/// 
+///
 /// # Arguments
-/// * `image_data` - Input image bytes
+/// * `img` - Image to filter in place
 /// * `sigma` - Blur intensity (0.0 to 10.0)
-/// 
-/// # Returns
-/// Processed image bytes
-#[wasm_bindgen]
-pub fn gaussian_blur(image_data: &[u8], sigma: f32) -> Vec<u8> {
-    let img = load_image(image_data);
+pub fn gaussian_blur<P: EditorPixel>(img: &mut Image<P>, sigma: f32) -> Result<(), EditorError> {
     console_log("Gaussian blur function called");
-    
-    if sigma <= 0.0 {
-        console_log("Sigma must be greater than 0, returning original image");
-        return image_data.to_vec();
+    ensure_non_negative("sigma", sigma)?;
+
+    if sigma == 0.0 {
+        console_log("No blur needed, returning original image");
+        return Ok(());
     }
-    
-    let processed = img.blur(sigma);
+
+    // Blur premultiplied so transparent pixels don't darken the edges
+    *img = if is_opaque(img) {
+        imageops::blur(img, sigma)
+    } else {
+        unpremultiply(&imageops::blur(&premultiply(img), sigma))
+    };
     console_log("Gaussian blur successful");
-    to_bytes(&processed)
+    Ok(())
 }
 
 /// Apply sharpening filter to image
-/// 
+///
+/// A quick unsharp mask with a 1-pixel radius and no threshold; use
+/// [`unsharp_mask`] for control over radius, threshold and color.
+///
+/// # Arguments
+/// * `img` - Image to filter in place
+/// * `amount` - Sharpening intensity (0.0 to 1.0, 1.0 adds the detail back three times)
+pub fn sharpen<P: EditorPixel>(img: &mut Image<P>, amount: f32) -> Result<(), EditorError> {
+    sharpen_with(img, amount, BorderMode::default())
+}
+
+/// Apply sharpening filter with the given border handling
+///
 /// # Arguments
-/// * `image_data` - Input image bytes
-/// * `amount` - Sharpening intensity (0.0 to 1.0)
-/// 
-/// # Returns
-/// Processed image bytes
-#[wasm_bindgen]
-pub fn sharpen(image_data: &[u8], amount: f32) -> Vec<u8> {
-    let img = load_image(image_data);
+/// * `img` - Image to filter in place
+/// * `amount` - Sharpening intensity (0.0 to 1.0, 1.0 adds the detail back three times)
+/// * `border` - What the filter reads beyond the image edges
+pub fn sharpen_with<P: EditorPixel>(img: &mut Image<P>, amount: f32, border: BorderMode) -> Result<(), EditorError> {
     console_log("Sharpen function called");
-    
-    if amount <= 0.0 {
-        console_log("Amount must be greater than 0, returning original image");
-        return image_data.to_vec();
-    }
-    
-    // Convert to RGB8 for pixel manipulation
-    let rgb_img = img.to_rgb8();
-    let (width, height) = rgb_img.dimensions();
-    
-    // Create a sharpening kernel (unsharp mask approach)
-    // We'll use a simple 3x3 kernel for sharpening
-    let kernel = [
-        0.0, -amount, 0.0,
-        -amount, 1.0 + 4.0 * amount, -amount,
-        0.0, -amount, 0.0
-    ];
-    
-    let mut output = image::ImageBuffer::new(width, height);
-    
-    // Apply the kernel to each pixel (excluding edges)
-    for y in 1..height-1 {
-        for x in 1..width-1 {
-            let mut r_sum: f32 = 0.0;
-            let mut g_sum: f32 = 0.0;
-            let mut b_sum: f32 = 0.0;
-            
-            // Apply the 3x3 kernel
-            for ky in 0..3 {
-                for kx in 0..3 {
-                    let px = (x as i32 + kx as i32 - 1) as u32;
-                    let py = (y as i32 + ky as i32 - 1) as u32;
-                    let pixel = rgb_img.get_pixel(px, py);
-                    let weight = kernel[ky * 3 + kx];
-                    
-                    r_sum += pixel[0] as f32 * weight;
-                    g_sum += pixel[1] as f32 * weight;
-                    b_sum += pixel[2] as f32 * weight;
-                }
-            }
-            
-            let r = r_sum.clamp(0.0, 255.0) as u8;
-            let g = g_sum.clamp(0.0, 255.0) as u8;
-            let b = b_sum.clamp(0.0, 255.0) as u8;
-            
-            output.put_pixel(x, y, image::Rgb([r, g, b]));
-        }
-    }
-    
-    // Handle edges by copying original pixels
-    for y in 0..height {
-        for x in 0..width {
-            if x == 0 || x == width-1 || y == 0 || y == height-1 {
-                output.put_pixel(x, y, *rgb_img.get_pixel(x, y));
-            }
-        }
+    ensure_non_negative("amount", amount)?;
+
+    if amount == 0.0 {
+        console_log("No sharpening needed, returning original image");
+        return Ok(());
     }
-    
-    let processed = image::DynamicImage::ImageRgb8(output);
+
+    unsharp::apply(img, &UnsharpMaskOptions { amount: amount * 300.0, border, ..UnsharpMaskOptions::default() });
+
     console_log("Sharpen successful");
-    
-    to_bytes(&processed)
+    Ok(())
+}
+
+/// Sharpen with an unsharp mask
+///
+/// # Arguments
+/// * `img` - Image to filter in place
+/// * `options` - Radius, amount, threshold and luminance-only mode (see [`unsharp`])
+pub fn unsharp_mask<P: EditorPixel>(img: &mut Image<P>, options: &UnsharpMaskOptions) -> Result<(), EditorError> {
+    console_log("Unsharp mask function called");
+    options.validate()?;
+
+    if options.amount == 0.0 {
+        console_log("No sharpening needed, returning original image");
+        return Ok(());
+    }
+
+    unsharp::apply(img, options);
+
+    console_log("Unsharp mask successful");
+    Ok(())
 }
 
 /// Convert image to grayscale
-/// 
+///
 /// # Arguments
-/// * `image_data` - Input image bytes
-/// 
-/// # Returns
-/// Processed image bytes
-#[wasm_bindgen]
-pub fn to_grayscale(image_data: &[u8]) -> Vec<u8> {
-    let img = load_image(image_data);
+/// * `img` - Image to convert in place (alpha is kept)
+pub fn to_grayscale<P: EditorPixel>(img: &mut Image<P>) -> Result<(), EditorError> {
     console_log("Grayscale conversion function called");
-    
-    let processed = img.grayscale();
+
+    P::map_color_matrix(img, &[[0.299, 0.587, 0.114, 0.0]; 3]);
+
     console_log("Grayscale conversion successful");
-    
-    to_bytes(&processed)
+    Ok(())
 }
 
-/// Apply sepia effect to image
-/// 
+/// Apply sepia tone effect
+///
 /// # Arguments
-/// * `image_data` - Input image bytes
-/// 
-/// # Returns
-/// Processed image bytes
-#[wasm_bindgen]
-pub fn apply_sepia(image_data: &[u8]) -> Vec<u8> {
-    let img = load_image(image_data);
+/// * `img` - Image to filter in place
+pub fn apply_sepia<P: EditorPixel>(img: &mut Image<P>) -> Result<(), EditorError> {
     console_log("Sepia effect function called");
-    
-    // Convert to RGB8 format first to ensure we can modify pixels
-    let mut rgb_img = img.to_rgb8();
-    
-    for pixel in rgb_img.pixels_mut() {
-        let r = pixel[0] as f32;
-        let g = pixel[1] as f32;
-        let b = pixel[2] as f32;
-        
-        // Sepia transformation matrix
-        let sepia_r = (r * 0.393) + (g * 0.769) + (b * 0.189);
-        let sepia_g = (r * 0.349) + (g * 0.686) + (b * 0.168);
-        let sepia_b = (r * 0.272) + (g * 0.534) + (b * 0.131);
-        
-        pixel[0] = sepia_r.min(255.0) as u8;
-        pixel[1] = sepia_g.min(255.0) as u8;
-        pixel[2] = sepia_b.min(255.0) as u8;
-    }
-    
-    let processed = image::DynamicImage::ImageRgb8(rgb_img);
+
+    // Sepia transformation matrix
+    P::map_color_matrix(img, &[[0.393, 0.769, 0.189, 0.0], [0.349, 0.686, 0.168, 0.0], [0.272, 0.534, 0.131, 0.0]]);
+
     console_log("Sepia effect successful");
-    
-    to_bytes(&processed)
+    Ok(())
 }
 
-/// Apply emboss effect to image
-/// 
+/// Darken the image towards its corners
+///
 /// # Arguments
-/// * `image_data` - Input image bytes
-/// 
-/// # Returns
-/// Processed image bytes
-#[wasm_bindgen]
-pub fn apply_emboss(image_data: &[u8]) -> Vec<u8> {
-    let img = load_image(image_data);
-    console_log("Emboss effect function called");
-    
-    // Convert to RGB8 format for pixel manipulation
-    let rgb_img = img.to_rgb8();
-    let (width, height) = rgb_img.dimensions();
-    
-    // Create output buffer
-    let mut output = image::ImageBuffer::new(width, height);
-    
-    // Emboss kernel - creates 3D raised effect
-    // This kernel emphasizes edges in a directional manner
-    let emboss_kernel = [
-        -2.0, -1.0,  0.0,
-        -1.0,  1.0,  1.0,
-         0.0,  1.0,  2.0
-    ];
-    
-    // Apply emboss filter
-    for y in 1..height-1 {
-        for x in 1..width-1 {
-            let mut r_sum: f32 = 0.0;
-            let mut g_sum: f32 = 0.0;
-            let mut b_sum: f32 = 0.0;
-            
-            // Apply the 3x3 emboss kernel
-            for ky in 0..3 {
-                for kx in 0..3 {
-                    let px = (x as i32 + kx as i32 - 1) as u32;
-                    let py = (y as i32 + ky as i32 - 1) as u32;
-                    let pixel = rgb_img.get_pixel(px, py);
-                    let weight = emboss_kernel[ky * 3 + kx];
-                    
-                    r_sum += pixel[0] as f32 * weight;
-                    g_sum += pixel[1] as f32 * weight;
-                    b_sum += pixel[2] as f32 * weight;
-                }
+/// * `img` - Image to filter in place
+/// * `strength` - Darkening at the corners (0 to 100)
+/// * `radius` - Untouched central area as a percentage of the half diagonal (0 to 100)
+pub fn apply_vignette<P: EditorPixel>(img: &mut Image<P>, strength: f32, radius: f32) -> Result<(), EditorError> {
+    console_log("Vignette effect function called");
+    ensure_finite("strength", strength)?;
+    ensure_finite("radius", radius)?;
+
+    if strength == 0.0 {
+        console_log("No vignette effect needed, returning original image");
+        return Ok(());
+    }
+
+    let (width, height) = img.dimensions();
+
+    // Calculate center of the image
+    let center_x = width as f32 / 2.0;
+    let center_y = height as f32 / 2.0;
+
+    // Calculate maximum distance from center to corner
+    let max_distance = ((center_x * center_x) + (center_y * center_y)).sqrt();
+
+    // Normalize strength and radius
+    let vignette_strength = strength / 100.0; // 0.0 to 1.0
+    let vignette_radius = radius / 100.0; // 0.0 to 1.0
+
+    // Calculate effective radius for vignette
+    let effective_radius = max_distance * vignette_radius;
+
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        // Calculate distance from center
+        let dx = x as f32 - center_x;
+        let dy = y as f32 - center_y;
+        let distance = (dx * dx + dy * dy).sqrt();
+
+        // Calculate vignette factor
+        let vignette_factor = if distance <= effective_radius {
+            1.0 // No darkening within the radius
+        } else {
+            // Smooth transition from radius to edge
+            let normalized_distance = (distance - effective_radius) / (max_distance - effective_radius);
+            let falloff = 1.0 - (normalized_distance * vignette_strength);
+            falloff.max(0.0) // Prevent negative values
+        };
+
+        // Apply vignette by darkening the pixel
+        let [r, g, b, a] = pixel.to_unit();
+        *pixel = P::from_unit([r * vignette_factor, g * vignette_factor, b * vignette_factor, a]);
+    }
+
+    console_log("Vignette effect successful");
+    Ok(())
+}
+
+/// Edge-preserving noise reduction (bilateral filter)
+///
+/// See [`denoise`] for a much faster filter with separate luminance and chroma strength.
+///
+/// # Arguments
+/// * `img` - Image to filter in place
+/// * `strength` - Noise reduction strength (0 to 100)
+pub fn reduce_noise<P: EditorPixel>(img: &mut Image<P>, strength: f32) -> Result<(), EditorError> {
+    reduce_noise_with(img, strength, BorderMode::default())
+}
+
+/// Edge-preserving noise reduction with the given border handling
+///
+/// # Arguments
+/// * `img` - Image to filter in place
+/// * `strength` - Noise reduction strength (0 to 100)
+/// * `border` - What the filter reads beyond the image edges
+pub fn reduce_noise_with<P: EditorPixel>(img: &mut Image<P>, strength: f32, border: BorderMode) -> Result<(), EditorError> {
+    console_log("Noise reduction function called");
+    ensure_non_negative("strength", strength)?;
+
+    if strength == 0.0 {
+        console_log("No noise reduction needed, returning original image");
+        return Ok(());
+    }
+
+    // Premultiplied, so neighbors are weighted by their alpha and the border color works alike
+    let source = premultiply(img);
+    let (width, height) = source.dimensions();
+
+    // Normalize strength to determine filter size and intensity
+    let normalized_strength = strength / 100.0; // 0.0 to 1.0
+
+    // Apply bilateral filter-like noise reduction
+    // This preserves edges while smoothing noise
+    let filter_radius = (normalized_strength * 3.0 + 1.0) as i32; // 1 to 4 pixels
+    let spatial_sigma = normalized_strength * 2.0 + 0.5; // Spatial smoothing
+    let intensity_sigma = normalized_strength * 30.0 + 10.0; // Intensity threshold
+
+    let columns = border.indices(width, filter_radius as usize);
+    let rows = border.indices(height, filter_radius as usize);
+    let constant = border.constant();
+    // Straight intensities on the 0-255 scale the sigmas are tuned for, and the alpha
+    let levels = |[r, g, b, a]: [f32; 4]| {
+        let scale = if a > 0.0 { 255.0 / a } else { 0.0 };
+        ([r * scale, g * scale, b * scale], a)
+    };
+
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        let ([center_r, center_g, center_b], center_a) = levels(source.get_pixel(x, y).0);
+
+        let mut sum_r = 0.0;
+        let mut sum_g = 0.0;
+        let mut sum_b = 0.0;
+        let mut weight_sum = 0.0;
+
+        // Sample pixels in the neighborhood
+        for dy in -filter_radius..=filter_radius {
+            for dx in -filter_radius..=filter_radius {
+                let source_x = columns[(x as i32 + dx + filter_radius) as usize];
+                let source_y = rows[(y as i32 + dy + filter_radius) as usize];
+                let sample = match (source_x, source_y) {
+                    (Some(sx), Some(sy)) => source.get_pixel(sx, sy).0,
+                    _ => constant,
+                };
+                let ([neighbor_r, neighbor_g, neighbor_b], neighbor_alpha) = levels(sample);
+
+                // Calculate spatial weight (Gaussian)
+                let spatial_distance = ((dx * dx + dy * dy) as f32).sqrt();
+                let spatial_weight = (-spatial_distance * spatial_distance / (2.0 * spatial_sigma * spatial_sigma)).exp();
+
+                // Calculate intensity weight (preserve edges)
+                let intensity_diff_r = (center_r - neighbor_r).abs();
+                let intensity_diff_g = (center_g - neighbor_g).abs();
+                let intensity_diff_b = (center_b - neighbor_b).abs();
+                let intensity_diff = (intensity_diff_r + intensity_diff_g + intensity_diff_b) / 3.0;
+                let intensity_weight = (-intensity_diff * intensity_diff / (2.0 * intensity_sigma * intensity_sigma)).exp();
+
+                // Combine weights; transparent neighbors carry no color
+                let total_weight = spatial_weight * intensity_weight * neighbor_alpha;
+
+                sum_r += neighbor_r * total_weight;
+                sum_g += neighbor_g * total_weight;
+                sum_b += neighbor_b * total_weight;
+                weight_sum += total_weight;
             }
-            
-            // Add bias to center the values around middle gray (128)
-            // This prevents the image from being too dark
-            let bias = 128.0;
-            let embossed_r = (r_sum + bias).clamp(0.0, 255.0) as u8;
-            let embossed_g = (g_sum + bias).clamp(0.0, 255.0) as u8;
-            let embossed_b = (b_sum + bias).clamp(0.0, 255.0) as u8;
-            
-            output.put_pixel(x, y, image::Rgb([embossed_r, embossed_g, embossed_b]));
+        }
+
+        // Normalize and apply
+        if weight_sum > 0.0 {
+            let filtered = [sum_r, sum_g, sum_b].map(|sum| sum / weight_sum / 255.0);
+            *pixel = P::from_unit([filtered[0], filtered[1], filtered[2], center_a]);
         }
     }
-    
-    // Handle edges by copying original pixels (or setting to gray)
-    for y in 0..height {
-        for x in 0..width {
-            if x == 0 || x == width-1 || y == 0 || y == height-1 {
-                // Set edge pixels to middle gray for consistent emboss effect
-                output.put_pixel(x, y, image::Rgb([128, 128, 128]));
+
+    console_log("Noise reduction successful");
+    Ok(())
+}
+
+/// Fast edge-preserving noise reduction
+///
+/// # Arguments
+/// * `img` - Image to filter in place
+/// * `options` - Luminance and chroma strength and window radius (see [`denoise`])
+pub fn denoise<P: EditorPixel>(img: &mut Image<P>, options: &DenoiseOptions) -> Result<(), EditorError> {
+    console_log("Denoise function called");
+    options.validate()?;
+
+    if options.luminance == 0.0 && options.chroma == 0.0 {
+        console_log("No noise reduction needed, returning original image");
+        return Ok(());
+    }
+
+    denoise::apply(img, options);
+
+    console_log("Denoise successful");
+    Ok(())
+}
+
+/// Apply emboss effect
+///
+/// # Arguments
+/// * `img` - Image to filter in place
+pub fn apply_emboss<P: EditorPixel>(img: &mut Image<P>) -> Result<(), EditorError> {
+    apply_emboss_with(img, BorderMode::default())
+}
+
+/// Apply emboss effect with the given border handling
+///
+/// # Arguments
+/// * `img` - Image to filter in place
+/// * `border` - What the filter reads beyond the image edges
+pub fn apply_emboss_with<P: EditorPixel>(img: &mut Image<P>, border: BorderMode) -> Result<(), EditorError> {
+    console_log("Emboss effect function called");
+
+    // Classic emboss kernel, lit from the top left - creates 3D raised effect
+    let emboss_kernel = Kernel::emboss(45.0);
+
+    // Add bias to center the values around middle gray (128)
+    // This prevents the image from being too dark
+    let middle_gray = 128.0 / 255.0;
+    kernel::apply(img, &emboss_kernel, 1.0, middle_gray, Channels::RGB, border);
+
+    console_log("Emboss effect successful");
+    Ok(())
+}
+
+/// Convolve the image with a custom kernel
+///
+/// # Arguments
+/// * `img` - Image to filter in place
+/// * `kernel` - `width * height` weights, row by row
+/// * `width`, `height` - Odd kernel size (1 to 127)
+/// * `divisor` - Weighted sums are divided by this, the sum of the weights when `None`
+/// * `bias` - Offset in 8-bit levels added after dividing
+/// * `channels` - Channels to filter, the others are kept; alpha needs weights that do not sum to zero
+pub fn apply_kernel<P: EditorPixel>(
+    img: &mut Image<P>,
+    kernel: &[f32],
+    width: u32,
+    height: u32,
+    divisor: Option<f32>,
+    bias: f32,
+    channels: Channels,
+) -> Result<(), EditorError> {
+    let kernel = Kernel::new(kernel.to_vec(), width, height)?;
+    convolve(img, &ConvolveOptions { divisor, bias, channels, ..ConvolveOptions::new(ConvolutionKernel::Custom(kernel)) })
+}
+
+/// Convolve the image with a custom or named kernel
+///
+/// # Arguments
+/// * `img` - Image to filter in place
+/// * `options` - Kernel, divisor, bias, channels and border handling (see [`kernel`])
+pub fn convolve<P: EditorPixel>(img: &mut Image<P>, options: &ConvolveOptions) -> Result<(), EditorError> {
+    console_log("Convolution function called");
+    options.validate()?;
+
+    let kernel = options.kernel.kernel()?;
+    let divisor = options.divisor.unwrap_or_else(|| kernel::auto_divisor(&kernel));
+    kernel::apply(img, &kernel, divisor, options.bias / 255.0, options.channels, options.border);
+
+    console_log("Convolution successful");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    /// Opaque red square in the middle of a transparent canvas whose hidden color is green
+    fn create_logo() -> RgbaImage {
+        RgbaImage::from_fn(32, 32, |x, y| {
+            if (8..24).contains(&x) && (8..24).contains(&y) {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 255, 0, 0])
             }
+        })
+    }
+
+    #[test]
+    fn test_point_filters_keep_alpha() {
+        let logo = create_logo();
+
+        let mut img = logo.clone();
+        apply_sepia(&mut img).unwrap();
+        to_grayscale(&mut img).unwrap();
+        apply_vignette(&mut img, 80.0, 20.0).unwrap();
+        assert!(img.pixels().zip(logo.pixels()).all(|(a, b)| a[3] == b[3]));
+    }
+
+    #[test]
+    fn test_convolutions_ignore_hidden_colors() {
+        let logo = create_logo();
+
+        let mut sharpened = logo.clone();
+        sharpen(&mut sharpened, 1.0).unwrap();
+        let mut denoised = logo.clone();
+        reduce_noise(&mut denoised, 50.0).unwrap();
+        for img in [&sharpened, &denoised] {
+            assert!(img.pixels().zip(logo.pixels()).all(|(a, b)| a[3] == b[3]));
+            // The edge of the square must not pick up the green of its transparent surroundings
+            let edge = img.get_pixel(8, 16);
+            assert!(edge[0] >= 254 && edge[1] <= 1 && edge[2] <= 1, "{:?}", edge);
         }
+
+        let mut blurred = logo.clone();
+        gaussian_blur(&mut blurred, 2.0).unwrap();
+        let edge = blurred.get_pixel(7, 16);
+        assert!(edge[3] > 0 && edge[3] < 255);
+        assert!(edge[0] > 250 && edge[1] < 5);
     }
-    
-    let processed = image::DynamicImage::ImageRgb8(output);
-    console_log("Emboss effect successful");
-    to_bytes(&processed)
-}
\ No newline at end of file
+
+    #[test]
+    fn test_convolutions_leave_no_border_artifacts() {
+        // Emboss of a flat color is that color plus the bias everywhere, corners included
+        let flat = RgbaImage::from_pixel(6, 5, Rgba([40, 80, 120, 255]));
+        for border in [BorderMode::Clamp, BorderMode::Mirror, BorderMode::Wrap, BorderMode::Constant([0; 4])] {
+            let mut embossed = flat.clone();
+            apply_emboss_with(&mut embossed, border).unwrap();
+            assert!(embossed.pixels().all(|pixel| pixel == embossed.get_pixel(2, 2)), "{:?}", border);
+            assert_eq!(embossed.get_pixel(0, 0).0, [168, 208, 248, 255]);
+
+            let mut denoised = flat.clone();
+            reduce_noise_with(&mut denoised, 80.0, border).unwrap();
+            let mut sharpened = flat.clone();
+            sharpen_with(&mut sharpened, 1.0, border).unwrap();
+            assert_eq!(denoised, flat);
+            assert_eq!(sharpened, flat);
+        }
+
+        // An opaque constant border reads as an edge, a wrapped one brings in the far side
+        let mut framed = RgbaImage::from_pixel(16, 16, Rgba([40, 80, 120, 255]));
+        sharpen_with(&mut framed, 1.0, BorderMode::Constant([255, 255, 255, 255])).unwrap();
+        assert!(framed.get_pixel(0, 8)[0] < 40 && framed.get_pixel(8, 8)[0] == 40);
+        let stripe = RgbaImage::from_fn(6, 1, |x, _| if x == 5 { Rgba([255, 255, 255, 255]) } else { Rgba([0, 0, 0, 255]) });
+        let mut wrapped = stripe.clone();
+        apply_emboss_with(&mut wrapped, BorderMode::Wrap).unwrap();
+        let mut clamped = stripe.clone();
+        apply_emboss_with(&mut clamped, BorderMode::Clamp).unwrap();
+        assert_ne!(wrapped.get_pixel(0, 0), clamped.get_pixel(0, 0));
+    }
+}
@@ -0,0 +1,153 @@
+//! Unsharp mask
+//!
+//! The classic darkroom sharpening: a Gaussian-blurred copy is subtracted from
+//! the image to isolate fine detail, and that detail is added back scaled by
+//! `amount`. The blur radius sets how fine the sharpened detail is, so large
+//! prints can use a wider radius than a 3x3 kernel reaches. Differences below
+//! `threshold` levels count as noise or texture and are left alone.
+//!
+//! With `luminance_only` the detail is measured and added on luma alone and
+//! applied equally to R, G and B, which avoids colored halos along edges:
+//!
+//! ```json
+//! { "radius": 1.5, "amount": 120, "threshold": 3, "luminance_only": true }
+//! ```
+//!
+//! `border` picks what the blur reads beyond the edges (see [`BorderMode`]).
+
+use serde::{Deserialize, Serialize};
+
+use super::convolution::{convolve_separable, gaussian_kernel, BorderMode};
+use crate::color::REC709_LUMA;
+use crate::error::{ensure_finite, EditorError};
+use crate::pixel::{map_pixels_at, EditorPixel, Image};
+use crate::utils::premultiply;
+
+/// Settings of [`super::unsharp_mask`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UnsharpMaskOptions {
+    /// Standard deviation of the blur in pixels (0.1 to 100.0)
+    pub radius: f32,
+    /// Strength in percent of the detail added back (0 to 500)
+    pub amount: f32,
+    /// Smallest difference in 8-bit levels that gets sharpened (0 to 255)
+    pub threshold: f32,
+    /// Sharpen luma only, keeping colors along edges
+    pub luminance_only: bool,
+    /// Samples of the blur beyond the image edges
+    pub border: BorderMode,
+}
+
+impl Default for UnsharpMaskOptions {
+    fn default() -> UnsharpMaskOptions {
+        UnsharpMaskOptions { radius: 1.0, amount: 100.0, threshold: 0.0, luminance_only: false, border: BorderMode::Clamp }
+    }
+}
+
+impl UnsharpMaskOptions {
+    /// Parse options from JSON
+    pub fn from_json(json: &str) -> Result<UnsharpMaskOptions, EditorError> {
+        let options: UnsharpMaskOptions =
+            serde_json::from_str(json).map_err(|e| EditorError::invalid_parameter("unsharp_mask", e.to_string()))?;
+        options.validate()?;
+        Ok(options)
+    }
+
+    /// Check radius, amount and threshold
+    pub fn validate(&self) -> Result<(), EditorError> {
+        for (name, value, range) in [
+            ("radius", self.radius, 0.1..=100.0),
+            ("amount", self.amount, 0.0..=500.0),
+            ("threshold", self.threshold, 0.0..=255.0),
+        ] {
+            ensure_finite(name, value)?;
+            if !range.contains(&value) {
+                return Err(EditorError::invalid_parameter(
+                    name,
+                    format!("must be between {} and {}", range.start(), range.end()),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn apply<P: EditorPixel>(img: &mut Image<P>, options: &UnsharpMaskOptions) {
+    let kernel = gaussian_kernel(options.radius);
+    let blurred = convolve_separable(&premultiply(img), &kernel, &kernel, options.border);
+    let amount = options.amount / 100.0;
+    let threshold = options.threshold / 255.0;
+    let luma = |[r, g, b]: [f32; 3]| REC709_LUMA[0] * r + REC709_LUMA[1] * g + REC709_LUMA[2] * b;
+
+    map_pixels_at(img, |x, y, [r, g, b, a]| {
+        let [br, bg, bb, ba] = blurred.get_pixel(x, y).0;
+        // Nothing visible nearby to compare with
+        if ba <= f32::EPSILON {
+            return [r, g, b, a];
+        }
+        let soft = [br / ba, bg / ba, bb / ba];
+
+        if options.luminance_only {
+            let detail = luma([r, g, b]) - luma(soft);
+            if detail.abs() < threshold {
+                return [r, g, b, a];
+            }
+            [r + amount * detail, g + amount * detail, b + amount * detail, a]
+        } else {
+            let rgb = [r, g, b];
+            let [r, g, b] = std::array::from_fn(|channel| {
+                let detail = rgb[channel] - soft[channel];
+                if detail.abs() < threshold {
+                    rgb[channel]
+                } else {
+                    rgb[channel] + amount * detail
+                }
+            });
+            [r, g, b, a]
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    /// Vertical edge from dark gray to light gray with faint noise on the dark side
+    fn create_edge() -> RgbaImage {
+        RgbaImage::from_fn(16, 4, |x, y| {
+            if x < 8 {
+                let noise = ((x + y) % 2) as u8;
+                Rgba([60 + noise, 60 + noise, 60 + noise, 255])
+            } else {
+                Rgba([180, 180, 180, 255])
+            }
+        })
+    }
+
+    #[test]
+    fn test_sharpens_edges_above_threshold() {
+        let edge = create_edge();
+        let mut img = edge.clone();
+        apply(&mut img, &UnsharpMaskOptions { radius: 1.0, amount: 150.0, threshold: 4.0, ..UnsharpMaskOptions::default() });
+
+        // Overshoot on both sides of the edge
+        assert!(img.get_pixel(7, 1)[0] < 55 && img.get_pixel(8, 1)[0] > 190, "{:?} {:?}", img.get_pixel(7, 1), img.get_pixel(8, 1));
+        // The 1-level noise is below the threshold and stays as it was
+        assert_eq!(img.get_pixel(2, 1), edge.get_pixel(2, 1));
+        assert_eq!(img.get_pixel(3, 1), edge.get_pixel(3, 1));
+        // The flat far sides, border included, are untouched
+        assert_eq!(img.get_pixel(15, 0), edge.get_pixel(15, 0));
+    }
+
+    #[test]
+    fn test_luminance_only_keeps_colors() {
+        let mut img = RgbaImage::from_fn(8, 2, |x, _| if x < 4 { Rgba([200, 40, 40, 255]) } else { Rgba([40, 40, 200, 255]) });
+        apply(&mut img, &UnsharpMaskOptions { amount: 200.0, luminance_only: true, ..UnsharpMaskOptions::default() });
+        // The same offset lands on every channel, so the differences between channels hold
+        let [r, g, b, _] = img.get_pixel(3, 0).0;
+        assert_eq!((r as i32 - g as i32, g as i32 - b as i32), (160, 0));
+        assert_eq!(UnsharpMaskOptions::from_json(r#"{ "radius": 0 }"#).unwrap_err().parameter(), Some("radius"));
+    }
+}
@@ -0,0 +1,687 @@
+//! Format-aware export and optional decoders
+//!
+//! PNG and JPEG are always available. WebP, TIFF, GIF, BMP, QOI and AVIF sit
+//! behind cargo features of the same name so each deployment only ships the
+//! codecs it needs. Enabling a feature also lets [`crate::load_image`] decode
+//! that format (GIF decodes the first frame); AVIF is export only.
+//!
+//! WebP export is lossless by default. Lossy WebP uses libwebp and needs the
+//! `webp-lossy` feature, which requires a C toolchain for the target.
+//!
+//! Export settings are plain data so they can be passed from JavaScript as JSON,
+//! optionally with the metadata to keep (see [`MetadataOptions`]):
+//!
+//! ```json
+//! { "format": "jpeg", "quality": 85, "chroma_subsampling": "4:4:4", "metadata": { "strip_gps": true } }
+//! ```
+
+use std::io::Cursor;
+
+use image::buffer::ConvertBuffer;
+use image::codecs::png::{self, PngEncoder};
+use image::{DynamicImage, ExtendedColorType, ImageEncoder, Rgb, RgbImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::error::EditorError;
+use crate::metadata::{self, Metadata, MetadataOptions};
+use crate::pixel::{EditorPixel, Image, Rgba16Image};
+use crate::utils::is_opaque;
+
+fn default_quality() -> u8 {
+    90
+}
+
+fn default_bit_depth() -> u8 {
+    8
+}
+
+fn default_lossless() -> bool {
+    true
+}
+
+fn default_avif_speed() -> u8 {
+    6
+}
+
+/// PNG deflate effort
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PngCompression {
+    /// Fastest encoding, larger files
+    #[default]
+    Fast,
+    /// Balance between speed and size
+    Balanced,
+    /// Smallest files, slowest encoding
+    Best,
+}
+
+/// PNG scanline prediction filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PngFilter {
+    None,
+    Sub,
+    Up,
+    Avg,
+    Paeth,
+    /// Pick the best filter per scanline
+    #[default]
+    Adaptive,
+}
+
+/// JPEG chroma subsampling
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ChromaSubsampling {
+    /// Full color resolution, best for graphics and text
+    #[serde(rename = "4:4:4")]
+    Yuv444,
+    /// Half horizontal color resolution
+    #[serde(rename = "4:2:2")]
+    Yuv422,
+    /// Half color resolution in both directions, smallest photos
+    #[default]
+    #[serde(rename = "4:2:0")]
+    Yuv420,
+}
+
+/// Output format and its encoder settings, tagged by `format` in JSON
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "format", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ExportFormat {
+    Png {
+        #[serde(default)]
+        compression: PngCompression,
+        #[serde(default)]
+        filter: PngFilter,
+        /// 8 or 16 bits per channel
+        #[serde(default = "default_bit_depth")]
+        bit_depth: u8,
+    },
+    /// Transparent pixels are flattened onto white
+    Jpeg {
+        /// 1 to 100
+        #[serde(default = "default_quality")]
+        quality: u8,
+        #[serde(default)]
+        chroma_subsampling: ChromaSubsampling,
+    },
+    Webp {
+        #[serde(default = "default_lossless")]
+        lossless: bool,
+        /// 1 to 100, only used for lossy encoding
+        #[serde(default = "default_quality")]
+        quality: u8,
+    },
+    Tiff {
+        /// 8 or 16 bits per channel
+        #[serde(default = "default_bit_depth")]
+        bit_depth: u8,
+    },
+    /// Colors are quantized to a 256-color palette
+    Gif,
+    Bmp,
+    Qoi,
+    Avif {
+        /// 1 to 100
+        #[serde(default = "default_quality")]
+        quality: u8,
+        /// 1 (slowest, smallest) to 10 (fastest)
+        #[serde(default = "default_avif_speed")]
+        speed: u8,
+    },
+}
+
+impl Default for ExportFormat {
+    fn default() -> ExportFormat {
+        ExportFormat::png(default_bit_depth())
+    }
+}
+
+impl ExportFormat {
+    /// PNG with the default compression and filter at the given bit depth (8 or 16)
+    pub fn png(bit_depth: u8) -> ExportFormat {
+        ExportFormat::Png {
+            compression: PngCompression::default(),
+            filter: PngFilter::default(),
+            bit_depth,
+        }
+    }
+
+    /// Parse export settings from JSON
+    pub fn from_json(json: &str) -> Result<ExportFormat, EditorError> {
+        let format: ExportFormat = serde_json::from_str(json)
+            .map_err(|e| EditorError::invalid_parameter("options", e.to_string()))?;
+        format.validate()?;
+        Ok(format)
+    }
+
+    /// Name used in the `format` tag
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Png { .. } => "png",
+            ExportFormat::Jpeg { .. } => "jpeg",
+            ExportFormat::Webp { .. } => "webp",
+            ExportFormat::Tiff { .. } => "tiff",
+            ExportFormat::Gif => "gif",
+            ExportFormat::Bmp => "bmp",
+            ExportFormat::Qoi => "qoi",
+            ExportFormat::Avif { .. } => "avif",
+        }
+    }
+
+    /// MIME type of the encoded bytes
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Png { .. } => "image/png",
+            ExportFormat::Jpeg { .. } => "image/jpeg",
+            ExportFormat::Webp { .. } => "image/webp",
+            ExportFormat::Tiff { .. } => "image/tiff",
+            ExportFormat::Gif => "image/gif",
+            ExportFormat::Bmp => "image/bmp",
+            ExportFormat::Qoi => "image/x-qoi",
+            ExportFormat::Avif { .. } => "image/avif",
+        }
+    }
+
+    /// Check the settings without encoding anything
+    pub fn validate(&self) -> Result<(), EditorError> {
+        match *self {
+            ExportFormat::Jpeg { quality, .. } | ExportFormat::Avif { quality, .. } => check_quality(quality)?,
+            ExportFormat::Webp { lossless: false, quality } => check_quality(quality)?,
+            _ => {}
+        }
+        if let ExportFormat::Png { bit_depth, .. } | ExportFormat::Tiff { bit_depth } = *self {
+            if bit_depth != 8 && bit_depth != 16 {
+                return Err(EditorError::invalid_parameter("bit_depth", "must be 8 or 16"));
+            }
+        }
+        if let ExportFormat::Avif { speed, .. } = *self {
+            if !(1..=10).contains(&speed) {
+                return Err(EditorError::invalid_parameter("speed", "must be between 1 and 10"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Export format together with the metadata to carry over
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct ExportOptions {
+    #[serde(flatten)]
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub metadata: MetadataOptions,
+}
+
+impl ExportOptions {
+    /// Parse export settings from JSON
+    pub fn from_json(json: &str) -> Result<ExportOptions, EditorError> {
+        let options: ExportOptions = serde_json::from_str(json)
+            .map_err(|e| EditorError::invalid_parameter("options", e.to_string()))?;
+        options.format.validate()?;
+        Ok(options)
+    }
+}
+
+impl From<ExportFormat> for ExportOptions {
+    fn from(format: ExportFormat) -> ExportOptions {
+        ExportOptions { format, metadata: MetadataOptions::default() }
+    }
+}
+
+fn check_quality(quality: u8) -> Result<(), EditorError> {
+    if (1..=100).contains(&quality) {
+        Ok(())
+    } else {
+        Err(EditorError::invalid_parameter("quality", "must be between 1 and 100"))
+    }
+}
+
+/// Formats [`crate::load_image`] can decode in this build
+pub fn import_formats() -> Vec<&'static str> {
+    let mut formats = vec!["png", "jpeg"];
+    if cfg!(feature = "webp") {
+        formats.push("webp");
+    }
+    if cfg!(feature = "tiff") {
+        formats.push("tiff");
+    }
+    if cfg!(feature = "gif") {
+        formats.push("gif");
+    }
+    if cfg!(feature = "bmp") {
+        formats.push("bmp");
+    }
+    if cfg!(feature = "qoi") {
+        formats.push("qoi");
+    }
+    formats
+}
+
+/// Formats [`encode`] can write in this build
+pub fn export_formats() -> Vec<&'static str> {
+    let mut formats = vec!["png", "jpeg"];
+    if cfg!(feature = "webp") {
+        formats.push("webp");
+    }
+    if cfg!(feature = "tiff") {
+        formats.push("tiff");
+    }
+    if cfg!(feature = "gif") {
+        formats.push("gif");
+    }
+    if cfg!(feature = "bmp") {
+        formats.push("bmp");
+    }
+    if cfg!(feature = "qoi") {
+        formats.push("qoi");
+    }
+    if cfg!(feature = "avif") {
+        formats.push("avif");
+    }
+    formats
+}
+
+/// Encode an image in the requested format
+///
+/// Float images are dithered when written with 8 bits per channel; 16-bit PNG
+/// and TIFF keep their full precision.
+///
+/// # Arguments
+/// * `img` - The image to encode
+/// * `format` - Output format and encoder settings
+///
+/// # Returns
+/// Encoded bytes, or `UnsupportedFormat` if the codec is not compiled in
+pub fn encode<P: EditorPixel>(img: &Image<P>, format: &ExportFormat) -> Result<Vec<u8>, EditorError> {
+    encode_with_metadata(img, format, &Metadata::default())
+}
+
+/// Encode an image and embed metadata blocks
+///
+/// PNG, JPEG and WebP carry EXIF, XMP and the ICC profile; other formats drop
+/// them. Filter the metadata with [`Metadata::filtered`] first to strip fields.
+///
+/// # Arguments
+/// * `img` - The image to encode
+/// * `format` - Output format and encoder settings
+/// * `metadata` - Blocks to write into the file
+///
+/// # Returns
+/// Encoded bytes, or `UnsupportedFormat` if the codec is not compiled in
+pub fn encode_with_metadata<P: EditorPixel>(img: &Image<P>, format: &ExportFormat, metadata: &Metadata) -> Result<Vec<u8>, EditorError> {
+    format.validate()?;
+    let narrow = || P::to_rgba8(img);
+
+    let mut buffer = Cursor::new(Vec::new());
+    match *format {
+        ExportFormat::Png { compression, filter, bit_depth } => {
+            let compression = match compression {
+                PngCompression::Fast => png::CompressionType::Fast,
+                PngCompression::Balanced => png::CompressionType::Default,
+                PngCompression::Best => png::CompressionType::Best,
+            };
+            let filter = match filter {
+                PngFilter::None => png::FilterType::NoFilter,
+                PngFilter::Sub => png::FilterType::Sub,
+                PngFilter::Up => png::FilterType::Up,
+                PngFilter::Avg => png::FilterType::Avg,
+                PngFilter::Paeth => png::FilterType::Paeth,
+                PngFilter::Adaptive => png::FilterType::Adaptive,
+            };
+            let mut encoder = PngEncoder::new_with_quality(&mut buffer, compression, filter);
+            if let Some(icc_profile) = &metadata.icc_profile {
+                encoder
+                    .set_icc_profile(icc_profile.clone())
+                    .map_err(|e| EditorError::Encode(e.to_string()))?;
+            }
+            if bit_depth == 16 {
+                write_wide(encoder, P::to_rgba16(img))?;
+            } else {
+                write_with(encoder, &narrow(), true)?;
+            }
+        }
+        ExportFormat::Jpeg { quality, chroma_subsampling } => {
+            encode_jpeg(&narrow(), quality, chroma_subsampling, metadata, buffer.get_mut())?
+        }
+        ExportFormat::Webp { lossless, quality } => encode_webp(&narrow(), lossless, quality, &mut buffer)?,
+        ExportFormat::Tiff { bit_depth } => encode_tiff(img, bit_depth, &mut buffer)?,
+        ExportFormat::Gif => encode_gif(&narrow(), &mut buffer)?,
+        ExportFormat::Bmp => encode_bmp(&narrow(), &mut buffer)?,
+        ExportFormat::Qoi => encode_qoi(&narrow(), &mut buffer)?,
+        ExportFormat::Avif { quality, speed } => encode_avif(&narrow(), quality, speed, &mut buffer)?,
+    }
+
+    let bytes = buffer.into_inner();
+    Ok(match format {
+        ExportFormat::Png { .. } => metadata::insert_png_chunks(bytes, metadata),
+        ExportFormat::Webp { .. } => metadata::insert_webp_chunks(bytes, img.width(), img.height(), metadata)?,
+        _ => bytes,
+    })
+}
+
+/// Write with an `image` encoder, as RGB when the image is opaque and `drop_alpha` is set
+fn write_with<E: ImageEncoder>(encoder: E, img: &RgbaImage, drop_alpha: bool) -> Result<(), EditorError> {
+    let result = if drop_alpha && is_opaque(img) {
+        let rgb: RgbImage = img.convert();
+        encoder.write_image(&rgb, rgb.width(), rgb.height(), ExtendedColorType::Rgb8)
+    } else {
+        encoder.write_image(img, img.width(), img.height(), ExtendedColorType::Rgba8)
+    };
+    result.map_err(|e| EditorError::Encode(e.to_string()))
+}
+
+/// Write 16 bits per channel with an `image` encoder, as RGB when the image is opaque
+fn write_wide<E: ImageEncoder>(encoder: E, img: Rgba16Image) -> Result<(), EditorError> {
+    let opaque = img.pixels().all(|pixel| pixel[3] == u16::MAX);
+    let img = DynamicImage::ImageRgba16(img);
+    let img = if opaque { DynamicImage::ImageRgb16(img.to_rgb16()) } else { img };
+    img.write_with_encoder(encoder).map_err(|e| EditorError::Encode(e.to_string()))
+}
+
+// Unused when every codec is enabled
+#[allow(dead_code)]
+fn unsupported(format: &str, feature: &str) -> EditorError {
+    EditorError::UnsupportedFormat(format!(
+        "{} export is not available in this build (enable the `{}` feature)",
+        format, feature
+    ))
+}
+
+fn encode_jpeg(
+    img: &RgbaImage,
+    quality: u8,
+    chroma_subsampling: ChromaSubsampling,
+    metadata: &Metadata,
+    output: &mut Vec<u8>,
+) -> Result<(), EditorError> {
+    use jpeg_encoder::{ColorType, Encoder, SamplingFactor};
+
+    let (width, height) = img.dimensions();
+    let (Ok(jpeg_width), Ok(jpeg_height)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(EditorError::Encode(format!(
+            "{}x{} exceeds the JPEG size limit of 65535 pixels per side",
+            width, height
+        )));
+    };
+
+    // JPEG has no alpha channel, composite onto white
+    let flattened = RgbImage::from_fn(width, height, |x, y| {
+        let [r, g, b, a] = img.get_pixel(x, y).0;
+        let alpha = a as u32;
+        let blend = |channel: u8| ((channel as u32 * alpha + 255 * (255 - alpha) + 127) / 255) as u8;
+        Rgb([blend(r), blend(g), blend(b)])
+    });
+
+    let mut encoder = Encoder::new(output, quality);
+    encoder.set_sampling_factor(match chroma_subsampling {
+        ChromaSubsampling::Yuv444 => SamplingFactor::F_1_1,
+        ChromaSubsampling::Yuv422 => SamplingFactor::F_2_1,
+        ChromaSubsampling::Yuv420 => SamplingFactor::F_2_2,
+    });
+    for segment in metadata::jpeg_app1_segments(metadata) {
+        encoder
+            .add_app_segment(1, &segment)
+            .map_err(|e| EditorError::Encode(e.to_string()))?;
+    }
+    if let Some(icc_profile) = &metadata.icc_profile {
+        encoder
+            .add_icc_profile(icc_profile)
+            .map_err(|e| EditorError::Encode(e.to_string()))?;
+    }
+    encoder
+        .encode(&flattened, jpeg_width, jpeg_height, ColorType::Rgb)
+        .map_err(|e| EditorError::Encode(e.to_string()))
+}
+
+#[cfg(feature = "webp")]
+fn encode_webp(img: &RgbaImage, lossless: bool, quality: u8, output: &mut Cursor<Vec<u8>>) -> Result<(), EditorError> {
+    if lossless {
+        return write_with(image::codecs::webp::WebPEncoder::new_lossless(output), img, true);
+    }
+    encode_webp_lossy(img, quality, output.get_mut())
+}
+
+#[cfg(not(feature = "webp"))]
+fn encode_webp(_img: &RgbaImage, _lossless: bool, _quality: u8, _output: &mut Cursor<Vec<u8>>) -> Result<(), EditorError> {
+    Err(unsupported("WebP", "webp"))
+}
+
+#[cfg(feature = "webp-lossy")]
+fn encode_webp_lossy(img: &RgbaImage, quality: u8, output: &mut Vec<u8>) -> Result<(), EditorError> {
+    let (width, height) = img.dimensions();
+    let too_large = || EditorError::Encode(format!("{}x{} is too large for WebP", width, height));
+    let width = i32::try_from(width).map_err(|_| too_large())?;
+    let height = i32::try_from(height).map_err(|_| too_large())?;
+    let stride = width.checked_mul(4).ok_or_else(too_large)?;
+
+    let mut encoded: *mut u8 = std::ptr::null_mut();
+    // SAFETY: the buffer holds `height` rows of `stride` bytes, and libwebp hands
+    // back `size` bytes at `encoded`, which are copied and then released with WebPFree
+    unsafe {
+        let size = libwebp_sys::WebPEncodeRGBA(img.as_ptr(), width, height, stride, quality as f32, &mut encoded);
+        if size == 0 || encoded.is_null() {
+            return Err(EditorError::Encode("libwebp failed to encode the image".to_string()));
+        }
+        output.extend_from_slice(std::slice::from_raw_parts(encoded, size));
+        libwebp_sys::WebPFree(encoded.cast());
+    }
+    Ok(())
+}
+
+#[cfg(all(feature = "webp", not(feature = "webp-lossy")))]
+fn encode_webp_lossy(_img: &RgbaImage, _quality: u8, _output: &mut Vec<u8>) -> Result<(), EditorError> {
+    Err(unsupported("Lossy WebP", "webp-lossy"))
+}
+
+#[cfg(feature = "tiff")]
+fn encode_tiff<P: EditorPixel>(img: &Image<P>, bit_depth: u8, output: &mut Cursor<Vec<u8>>) -> Result<(), EditorError> {
+    let encoder = image::codecs::tiff::TiffEncoder::new(output);
+    if bit_depth == 16 {
+        write_wide(encoder, P::to_rgba16(img))
+    } else {
+        write_with(encoder, &P::to_rgba8(img), true)
+    }
+}
+
+#[cfg(not(feature = "tiff"))]
+fn encode_tiff<P: EditorPixel>(_img: &Image<P>, _bit_depth: u8, _output: &mut Cursor<Vec<u8>>) -> Result<(), EditorError> {
+    Err(unsupported("TIFF", "tiff"))
+}
+
+#[cfg(feature = "gif")]
+fn encode_gif(img: &RgbaImage, output: &mut Cursor<Vec<u8>>) -> Result<(), EditorError> {
+    image::codecs::gif::GifEncoder::new(output)
+        .encode(img, img.width(), img.height(), ExtendedColorType::Rgba8)
+        .map_err(|e| EditorError::Encode(e.to_string()))
+}
+
+#[cfg(not(feature = "gif"))]
+fn encode_gif(_img: &RgbaImage, _output: &mut Cursor<Vec<u8>>) -> Result<(), EditorError> {
+    Err(unsupported("GIF", "gif"))
+}
+
+#[cfg(feature = "bmp")]
+fn encode_bmp(img: &RgbaImage, output: &mut Cursor<Vec<u8>>) -> Result<(), EditorError> {
+    write_with(image::codecs::bmp::BmpEncoder::new(output), img, true)
+}
+
+#[cfg(not(feature = "bmp"))]
+fn encode_bmp(_img: &RgbaImage, _output: &mut Cursor<Vec<u8>>) -> Result<(), EditorError> {
+    Err(unsupported("BMP", "bmp"))
+}
+
+#[cfg(feature = "qoi")]
+fn encode_qoi(img: &RgbaImage, output: &mut Cursor<Vec<u8>>) -> Result<(), EditorError> {
+    write_with(image::codecs::qoi::QoiEncoder::new(output), img, true)
+}
+
+#[cfg(not(feature = "qoi"))]
+fn encode_qoi(_img: &RgbaImage, _output: &mut Cursor<Vec<u8>>) -> Result<(), EditorError> {
+    Err(unsupported("QOI", "qoi"))
+}
+
+#[cfg(feature = "avif")]
+fn encode_avif(img: &RgbaImage, quality: u8, speed: u8, output: &mut Cursor<Vec<u8>>) -> Result<(), EditorError> {
+    write_with(image::codecs::avif::AvifEncoder::new_with_speed_quality(output, speed, quality), img, false)
+}
+
+#[cfg(not(feature = "avif"))]
+fn encode_avif(_img: &RgbaImage, _quality: u8, _speed: u8, _output: &mut Cursor<Vec<u8>>) -> Result<(), EditorError> {
+    Err(unsupported("AVIF", "avif"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load_image;
+    use image::{Rgba, Rgba32FImage};
+
+    fn create_test_image() -> RgbaImage {
+        RgbaImage::from_fn(48, 32, |x, y| {
+            Rgba([(x * 5) as u8, (y * 7) as u8, 120, if x < 8 { 0 } else { 255 }])
+        })
+    }
+
+    /// Encode, decode and compare; lossless formats must round-trip exactly
+    fn roundtrip(format: ExportFormat) -> RgbaImage {
+        let img = create_test_image();
+        let bytes = encode(&img, &format).unwrap();
+        assert_eq!(image::guess_format(&bytes).unwrap().to_mime_type(), format.mime_type());
+        let decoded = load_image(&bytes).unwrap();
+        assert_eq!(decoded.dimensions(), img.dimensions());
+        decoded
+    }
+
+    #[test]
+    fn test_png_and_jpeg_export() {
+        for compression in [PngCompression::Fast, PngCompression::Balanced, PngCompression::Best] {
+            let format = ExportFormat::Png { compression, filter: PngFilter::Paeth, bit_depth: 8 };
+            assert_eq!(roundtrip(format), create_test_image());
+        }
+
+        for chroma_subsampling in [ChromaSubsampling::Yuv444, ChromaSubsampling::Yuv422, ChromaSubsampling::Yuv420] {
+            let decoded = roundtrip(ExportFormat::Jpeg { quality: 95, chroma_subsampling });
+            // Transparent pixels are flattened onto white
+            let flattened = decoded.get_pixel(2, 16);
+            assert!(flattened.0.iter().all(|&channel| channel > 240), "{:?}", flattened);
+        }
+
+        let small = encode(&create_test_image(), &ExportFormat::Jpeg { quality: 20, chroma_subsampling: ChromaSubsampling::Yuv420 }).unwrap();
+        let large = encode(&create_test_image(), &ExportFormat::Jpeg { quality: 100, chroma_subsampling: ChromaSubsampling::Yuv444 }).unwrap();
+        assert!(small.len() < large.len());
+    }
+
+    #[test]
+    fn test_options_from_json() {
+        assert_eq!(
+            ExportFormat::from_json(r#"{ "format": "jpeg", "chroma_subsampling": "4:4:4" }"#).unwrap(),
+            ExportFormat::Jpeg { quality: 90, chroma_subsampling: ChromaSubsampling::Yuv444 }
+        );
+        assert_eq!(ExportFormat::from_json(r#"{ "format": "png" }"#).unwrap(), ExportFormat::default());
+
+        let error = ExportFormat::from_json(r#"{ "format": "jpeg", "quality": 0 }"#).unwrap_err();
+        assert_eq!(error.parameter(), Some("quality"));
+        let error = ExportFormat::from_json(r#"{ "format": "png", "bit_depth": 12 }"#).unwrap_err();
+        assert_eq!(error.parameter(), Some("bit_depth"));
+        let error = ExportFormat::from_json(r#"{ "format": "jxl" }"#).unwrap_err();
+        assert_eq!(error.parameter(), Some("options"));
+    }
+
+    #[test]
+    fn test_export_options_from_json() {
+        let options = ExportOptions::from_json(r#"{ "format": "png", "metadata": { "strip_gps": true } }"#).unwrap();
+        assert_eq!(options.format, ExportFormat::default());
+        assert_eq!(options.metadata, MetadataOptions { strip_gps: true, ..MetadataOptions::keep_all() });
+        assert_eq!(ExportOptions::from_json(r#"{ "format": "bmp" }"#).unwrap().metadata, MetadataOptions::keep_all());
+
+        let error = ExportOptions::from_json(r#"{ "format": "png", "metadata": { "gps": false } }"#).unwrap_err();
+        assert_eq!(error.parameter(), Some("options"));
+    }
+
+    #[test]
+    fn test_metadata_roundtrip() {
+        let metadata = Metadata {
+            exif: Some(crate::metadata::tests::sample_exif(1)),
+            icc_profile: Some(b"not a real profile".to_vec()),
+            xmp: Some(b"<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"/>".to_vec()),
+        };
+        let mut formats = vec![ExportFormat::default(), ExportFormat::Jpeg { quality: 80, chroma_subsampling: ChromaSubsampling::Yuv420 }];
+        if cfg!(feature = "webp") {
+            formats.push(ExportFormat::Webp { lossless: true, quality: 90 });
+        }
+        if cfg!(feature = "webp-lossy") {
+            formats.push(ExportFormat::Webp { lossless: false, quality: 90 });
+        }
+        for format in formats {
+            let bytes = encode_with_metadata(&create_test_image(), &format, &metadata).unwrap();
+            let (decoded, read) = crate::load_image_with::<Rgba<u8>>(&bytes, &Default::default()).unwrap();
+            assert_eq!(decoded.dimensions(), (48, 32));
+            assert_eq!(read, metadata, "{}", format.name());
+        }
+    }
+
+    #[test]
+    fn test_16_bit_export_keeps_precision() {
+        // Steps of 1/4096 collapse to a handful of levels at 8 bits
+        let img = Rgba32FImage::from_fn(64, 8, |x, y| {
+            Rgba([0.5 + x as f32 / 4096.0, y as f32 / 4096.0, 0.25, if x < 4 { 0.5 } else { 1.0 }])
+        });
+        let mut formats = vec![ExportFormat::Png { compression: PngCompression::Fast, filter: PngFilter::Adaptive, bit_depth: 16 }];
+        if cfg!(feature = "tiff") {
+            formats.push(ExportFormat::Tiff { bit_depth: 16 });
+        }
+        for format in formats {
+            let bytes = encode(&img, &format).unwrap();
+            assert_eq!(image::load_from_memory(&bytes).unwrap().color(), image::ColorType::Rgba16);
+            let decoded: Rgba32FImage = crate::load_image_with(&bytes, &Default::default()).unwrap().0;
+            for (a, b) in decoded.pixels().zip(img.pixels()) {
+                assert!(a.0.iter().zip(b.0.iter()).all(|(a, b)| (a - b).abs() <= 1.0 / 65535.0), "{:?} {:?}", a, b);
+            }
+        }
+
+        let opaque = Rgba32FImage::from_pixel(4, 4, Rgba([0.1, 0.2, 0.3, 1.0]));
+        let bytes = encode(&opaque, &ExportFormat::from_json(r#"{ "format": "png", "bit_depth": 16 }"#).unwrap()).unwrap();
+        assert_eq!(image::load_from_memory(&bytes).unwrap().color(), image::ColorType::Rgb16);
+    }
+
+    #[cfg(feature = "webp")]
+    #[test]
+    fn test_webp_export() {
+        assert_eq!(roundtrip(ExportFormat::Webp { lossless: true, quality: 90 }), create_test_image());
+
+        let lossy = encode(&create_test_image(), &ExportFormat::Webp { lossless: false, quality: 75 });
+        if cfg!(feature = "webp-lossy") {
+            assert_eq!(load_image(&lossy.unwrap()).unwrap().dimensions(), (48, 32));
+        } else {
+            assert_eq!(lossy.unwrap_err().code(), "UNSUPPORTED_FORMAT");
+        }
+    }
+
+    #[cfg(all(feature = "tiff", feature = "gif", feature = "bmp", feature = "qoi"))]
+    #[test]
+    fn test_optional_format_roundtrips() {
+        for format in [ExportFormat::Tiff { bit_depth: 8 }, ExportFormat::Bmp, ExportFormat::Qoi] {
+            assert_eq!(roundtrip(format), create_test_image());
+        }
+        roundtrip(ExportFormat::Gif);
+    }
+
+    #[cfg(not(feature = "tiff"))]
+    #[test]
+    fn test_disabled_format_is_reported() {
+        let error = encode(&create_test_image(), &ExportFormat::Tiff { bit_depth: 8 }).unwrap_err();
+        assert_eq!(error.code(), "UNSUPPORTED_FORMAT");
+        assert!(!export_formats().contains(&"tiff"));
+    }
+
+    #[cfg(not(feature = "bmp"))]
+    #[test]
+    fn test_disabled_decoder_is_reported() {
+        // 1x1 BMP header and pixel
+        let mut bmp = b"BM".to_vec();
+        bmp.extend_from_slice(&[0; 64]);
+        assert_eq!(load_image(&bmp).unwrap_err().code(), "UNSUPPORTED_FORMAT");
+    }
+}
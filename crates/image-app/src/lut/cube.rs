@@ -0,0 +1,299 @@
+//! `.cube` LUT files
+//!
+//! Reads the Adobe and Resolve flavors of the text format: a header of
+//! keywords followed by one `r g b` line per table entry, red varying fastest.
+//!
+//! ```text
+//! TITLE "Warm fade"
+//! LUT_3D_SIZE 33
+//! DOMAIN_MIN 0.0 0.0 0.0
+//! DOMAIN_MAX 1.0 1.0 1.0
+//! 0.031 0.018 0.006
+//! ...
+//! ```
+//!
+//! A file holds a 1D table (`LUT_1D_SIZE`), a 3D table (`LUT_3D_SIZE`) or, as
+//! written by Resolve, both: the 1D entries come first and shape the input of
+//! the cube. `DOMAIN_MIN` / `DOMAIN_MAX` give the input range of every table,
+//! `LUT_1D_INPUT_RANGE` / `LUT_3D_INPUT_RANGE` that of one. Comments (`#`),
+//! blank lines and unknown keywords are skipped.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{check_point_operations, sample_cube, Lut1d, Lut3d, LutInterpolation};
+use crate::error::EditorError;
+use crate::pipeline::Operation;
+
+/// Largest `LUT_1D_SIZE` accepted
+pub const MAX_1D_SIZE: usize = 65536;
+/// Largest `LUT_3D_SIZE` accepted
+pub const MAX_3D_SIZE: usize = 256;
+
+/// Input range of a table: `min` maps to its first entry, `max` to its last
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Domain {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Default for Domain {
+    fn default() -> Domain {
+        Domain { min: [0.0; 3], max: [1.0; 3] }
+    }
+}
+
+impl Domain {
+    /// Position of a color within the range, 0.0-1.0 inside it
+    fn normalize(&self, rgb: [f32; 3]) -> [f32; 3] {
+        std::array::from_fn(|channel| (rgb[channel] - self.min[channel]) / (self.max[channel] - self.min[channel]))
+    }
+}
+
+/// Contents of a `.cube` file
+///
+/// In pipeline JSON a LUT is the text of the file, so presets carry their look.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CubeLut {
+    pub title: Option<String>,
+    /// 1D table applied first
+    pub shaper: Option<(Lut1d, Domain)>,
+    /// 3D table applied to the shaper's output
+    pub cube: Option<(Lut3d, Domain)>,
+}
+
+impl CubeLut {
+    /// Parse the text of a `.cube` file
+    ///
+    /// # Returns
+    /// The tables, or an `InvalidParameter` error for `cube` naming the line
+    /// that could not be read
+    pub fn parse(text: &str) -> Result<CubeLut, EditorError> {
+        let error = |line: usize, message: String| EditorError::invalid_parameter("cube", format!("line {}: {}", line, message));
+        let mut title = None;
+        let (mut size_1d, mut size_3d) = (None, None);
+        let (mut domain_1d, mut domain_3d) = (Domain::default(), Domain::default());
+        let mut entries = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+            let floats = |count: usize| -> Result<Vec<f32>, EditorError> {
+                let values: Vec<f32> = rest.split_whitespace().map(str::parse).collect::<Result<_, _>>().map_err(|_| error(number, format!("expected {} numbers after {}", count, keyword)))?;
+                if values.len() != count || values.iter().any(|value| !value.is_finite()) {
+                    return Err(error(number, format!("expected {} numbers after {}", count, keyword)));
+                }
+                Ok(values)
+            };
+            let size = |max: usize| -> Result<usize, EditorError> {
+                match rest.parse::<usize>() {
+                    Ok(size) if (2..=max).contains(&size) => Ok(size),
+                    _ => Err(error(number, format!("{} must be between 2 and {}", keyword, max))),
+                }
+            };
+            let range = || -> Result<Domain, EditorError> {
+                let values = floats(2)?;
+                Ok(Domain { min: [values[0]; 3], max: [values[1]; 3] })
+            };
+
+            match keyword {
+                "TITLE" => title = Some(rest.trim_matches('"').to_string()),
+                "LUT_1D_SIZE" => size_1d = Some(size(MAX_1D_SIZE)?),
+                "LUT_3D_SIZE" => size_3d = Some(size(MAX_3D_SIZE)?),
+                "DOMAIN_MIN" => {
+                    let min = floats(3)?;
+                    domain_1d.min = [min[0], min[1], min[2]];
+                    domain_3d.min = domain_1d.min;
+                }
+                "DOMAIN_MAX" => {
+                    let max = floats(3)?;
+                    domain_1d.max = [max[0], max[1], max[2]];
+                    domain_3d.max = domain_1d.max;
+                }
+                "LUT_1D_INPUT_RANGE" => domain_1d = range()?,
+                "LUT_3D_INPUT_RANGE" => domain_3d = range()?,
+                _ if keyword.starts_with(|c: char| c.is_ascii_alphabetic()) => {}
+                _ => {
+                    let values: Vec<f32> = line.split_whitespace().map(str::parse).collect::<Result<_, _>>().map_err(|_| error(number, "expected an `r g b` entry".to_string()))?;
+                    match values[..] {
+                        [r, g, b] if values.iter().all(|value| value.is_finite()) => entries.push([r, g, b]),
+                        _ => return Err(error(number, "expected an `r g b` entry".to_string())),
+                    }
+                }
+            }
+        }
+
+        for (name, domain) in [("LUT_1D", &domain_1d), ("LUT_3D", &domain_3d)] {
+            if (0..3).any(|channel| domain.min[channel] >= domain.max[channel]) {
+                return Err(EditorError::invalid_parameter("cube", format!("{} input range is empty", name)));
+            }
+        }
+        let expected = size_1d.unwrap_or(0) + size_3d.map_or(0, |size| size * size * size);
+        if expected == 0 {
+            return Err(EditorError::invalid_parameter("cube", "missing LUT_1D_SIZE or LUT_3D_SIZE"));
+        }
+        if entries.len() != expected {
+            return Err(EditorError::invalid_parameter("cube", format!("expected {} entries, found {}", expected, entries.len())));
+        }
+
+        let cube_entries = entries.split_off(size_1d.unwrap_or(0));
+        let shaper = size_1d.map(|_| {
+            let channels = std::array::from_fn(|channel| entries.iter().map(|entry| entry[channel]).collect());
+            (Lut1d { channels }, domain_1d)
+        });
+        let cube = size_3d.map(|size| (Lut3d { size, table: cube_entries }, domain_3d));
+        Ok(CubeLut { title, shaper, cube })
+    }
+
+    /// Bake a stack of point operations into a 3D LUT for other tools
+    ///
+    /// # Arguments
+    /// * `operations` - Point operations in order (see [`Operation::point_kind`])
+    /// * `size` - Grid points along each axis (2 to 256, 33 is common)
+    ///
+    /// # Returns
+    /// A LUT that is exact at every grid point, or an error naming the first
+    /// step that is invalid or not a point operation
+    pub fn bake(operations: &[Operation], size: usize) -> Result<CubeLut, EditorError> {
+        if !(2..=MAX_3D_SIZE).contains(&size) {
+            return Err(EditorError::invalid_parameter("size", format!("must be between 2 and {}", MAX_3D_SIZE)));
+        }
+        check_point_operations(operations, 0)?;
+        let cube = sample_cube(operations, size, 0)?;
+        Ok(CubeLut { title: None, shaper: None, cube: Some((cube, Domain::default())) })
+    }
+
+    /// Map one normalized color through the shaper and the cube
+    pub fn map(&self, rgb: [f32; 3], interpolation: LutInterpolation) -> [f32; 3] {
+        let rgb = match &self.shaper {
+            Some((shaper, domain)) => shaper.map(domain.normalize(rgb)),
+            None => rgb,
+        };
+        match &self.cube {
+            Some((cube, domain)) => cube.map(domain.normalize(rgb), interpolation),
+            None => rgb,
+        }
+    }
+}
+
+impl fmt::Display for CubeLut {
+    /// Write the LUT in the `.cube` format
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(title) = &self.title {
+            writeln!(f, "TITLE \"{}\"", title.replace('"', "'"))?;
+        }
+        let domains = [self.shaper.as_ref().map(|(_, domain)| *domain), self.cube.as_ref().map(|(_, domain)| *domain)];
+        match domains {
+            // Resolve's per-table ranges are the same for all channels
+            [Some(a), Some(b)] if a != b => {
+                writeln!(f, "LUT_1D_INPUT_RANGE {} {}", a.min[0], a.max[0])?;
+                writeln!(f, "LUT_3D_INPUT_RANGE {} {}", b.min[0], b.max[0])?;
+            }
+            [Some(domain), _] | [None, Some(domain)] if domain != Domain::default() => {
+                writeln!(f, "DOMAIN_MIN {} {} {}", domain.min[0], domain.min[1], domain.min[2])?;
+                writeln!(f, "DOMAIN_MAX {} {} {}", domain.max[0], domain.max[1], domain.max[2])?;
+            }
+            _ => {}
+        }
+        if let Some((shaper, _)) = &self.shaper {
+            writeln!(f, "LUT_1D_SIZE {}", shaper.channels[0].len())?;
+        }
+        if let Some((cube, _)) = &self.cube {
+            writeln!(f, "LUT_3D_SIZE {}", cube.size)?;
+        }
+        if let Some((shaper, _)) = &self.shaper {
+            for index in 0..shaper.channels[0].len() {
+                let [r, g, b] = [0, 1, 2].map(|channel| shaper.channels[channel][index]);
+                writeln!(f, "{:.6} {:.6} {:.6}", r, g, b)?;
+            }
+        }
+        if let Some((cube, _)) = &self.cube {
+            for [r, g, b] in &cube.table {
+                writeln!(f, "{:.6} {:.6} {:.6}", r, g, b)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for CubeLut {
+    type Error = EditorError;
+
+    fn try_from(text: String) -> Result<CubeLut, EditorError> {
+        CubeLut::parse(&text)
+    }
+}
+
+impl From<CubeLut> for String {
+    fn from(lut: CubeLut) -> String {
+        lut.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adjustments::SaturationModel;
+
+    #[test]
+    fn test_parse_resolve_cube_with_shaper() {
+        let text = "# Created by hand\n\
+            TITLE \"Invert\"\n\
+            LUT_1D_SIZE 2\n\
+            LUT_3D_SIZE 2\n\
+            LUT_1D_INPUT_RANGE 0.0 2.0\n\
+            LUT_IN_VIDEO_RANGE\n\
+            0 0 0\n1 1 1\n\
+            \n\
+            1 1 1\n0 1 1\n1 0 1\n0 0 1\n1 1 0\n0 1 0\n1 0 0\n0 0 0\n";
+        let lut = CubeLut::parse(text).unwrap();
+        assert_eq!(lut.title.as_deref(), Some("Invert"));
+        let (_, domain) = lut.shaper.as_ref().unwrap();
+        assert_eq!(domain.max, [2.0; 3]);
+
+        // The shaper halves the input, the cube inverts it
+        let [r, g, b] = lut.map([1.0, 0.4, 2.0], LutInterpolation::Tetrahedral);
+        assert!((r - 0.5).abs() < 1e-6 && (g - 0.8).abs() < 1e-6 && b.abs() < 1e-6, "{} {} {}", r, g, b);
+
+        // Writing and reading again keeps the tables
+        assert_eq!(CubeLut::parse(&lut.to_string()).unwrap(), lut);
+
+        let error = CubeLut::parse("LUT_3D_SIZE 2\n0 0 0\n1 x 0\n").unwrap_err();
+        assert_eq!(error.parameter(), Some("cube"));
+        assert!(error.to_string().contains("line 3"), "{}", error);
+        assert!(CubeLut::parse("LUT_3D_SIZE 2\n0 0 0\n").is_err());
+        assert!(CubeLut::parse("LUT_3D_SIZE 1\n0 0 0\n").is_err());
+    }
+
+    #[test]
+    fn test_bake_point_operations() {
+        let operations = [
+            Operation::Brightness { value: 12 },
+            Operation::Saturation { value: -40.0, model: SaturationModel::Hsl },
+            Operation::Sepia,
+        ];
+        let lut = CubeLut::bake(&operations, 17).unwrap();
+        let text = lut.to_string();
+        assert!(text.starts_with("LUT_3D_SIZE 17\n"));
+        assert_eq!(text.lines().count(), 1 + 17 * 17 * 17);
+
+        // Grid colors are exact up to the six written decimals
+        let mut sample = image::Rgba32FImage::from_pixel(1, 1, image::Rgba([0.25, 0.5, 0.75, 1.0]));
+        for operation in &operations {
+            operation.apply(&mut sample).unwrap();
+        }
+        let baked = CubeLut::parse(&text).unwrap().map([0.25, 0.5, 0.75], LutInterpolation::Trilinear);
+        assert!((0..3).all(|channel| (baked[channel] - sample.get_pixel(0, 0)[channel]).abs() < 1e-5), "{:?}", baked);
+
+        let error = CubeLut::bake(&[Operation::Sepia, Operation::FlipVertical], 33).unwrap_err();
+        assert_eq!(error.parameter(), Some("operations[1].op"));
+        assert_eq!(CubeLut::bake(&operations, 1).unwrap_err().parameter(), Some("size"));
+    }
+}
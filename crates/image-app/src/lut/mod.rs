@@ -0,0 +1,342 @@
+//! Lookup tables for point operations
+//!
+//! A point operation maps every color on its own, without looking at the
+//! neighbors or the rest of the image, so any run of them is fully described
+//! by what it does to each possible color. [`PointLut::compile`] samples a run
+//! once and the image is then edited in a single pass, however many sliders
+//! the run holds:
+//!
+//! * leading per-channel steps (brightness, contrast, exposure, levels, curves)
+//!   become three [`Lut1d`] tone curves of [`CURVE_SIZE`] entries;
+//! * everything from the first step that mixes channels (highlights, white
+//!   balance, saturation, sepia, ...) on is baked into a [`Lut3d`] color cube
+//!   of [`FUSED_CUBE_SIZE`]³ entries, read with tetrahedral interpolation,
+//!   which keeps grays exactly gray.
+//!
+//! Looks made elsewhere come in as `.cube` files (see [`cube`]), which can be
+//! applied on their own or as a step of a pipeline; a stack of point
+//! operations can be baked into one with [`CubeLut::bake`].
+//!
+//! The tables are sampled by running the operations themselves on float
+//! buffers, so fused and separate passes share all of their math. Tone curves
+//! stay within one 8-bit level of the separate passes. The cube is
+//! interpolated between its grid points: colors are off by a few hundredths of
+//! a level on average and fewer than one in a hundred by more than a level,
+//! but where a step clips colors at the gamut boundary its output bends
+//! sharply and colors right next to the bend can be off by up to
+//! [`FUSED_CUBE_TOLERANCE`] levels. Steps that bend too sharply for any grid,
+//! OKLCh saturation boosts and vibrance, are never fused (see
+//! [`Operation::fuses`]).
+
+pub mod cube;
+
+use std::str::FromStr;
+
+use image::{Rgba, Rgba32FImage};
+use serde::{Deserialize, Serialize};
+
+use crate::adjustments::curves::lookup;
+use crate::error::EditorError;
+use crate::pipeline::{Operation, PointKind};
+use crate::pixel::{map_pixels, EditorPixel, Image};
+
+pub use cube::{CubeLut, Domain};
+
+/// Entries of each fused tone curve
+pub const CURVE_SIZE: usize = 4096;
+/// Grid points along each axis of a baked `.cube` color cube
+pub const CUBE_SIZE: usize = 33;
+/// Grid points along each axis of a fused color cube
+pub const FUSED_CUBE_SIZE: usize = 65;
+/// Most 8-bit levels a fused color cube strays from the separate passes, next to gamut clipping
+pub const FUSED_CUBE_TOLERANCE: f32 = 10.0;
+
+/// How colors between the grid points of a [`Lut3d`] are blended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LutInterpolation {
+    /// All eight corners of the grid cell
+    Trilinear,
+    /// Four corners of the tetrahedron around the color; smoother hues, grays stay gray
+    #[default]
+    Tetrahedral,
+}
+
+impl FromStr for LutInterpolation {
+    type Err = EditorError;
+
+    fn from_str(name: &str) -> Result<LutInterpolation, EditorError> {
+        match name {
+            "trilinear" => Ok(LutInterpolation::Trilinear),
+            "tetrahedral" => Ok(LutInterpolation::Tetrahedral),
+            _ => Err(EditorError::invalid_parameter(
+                "interpolation",
+                format!("unknown interpolation `{}`, expected trilinear or tetrahedral", name),
+            )),
+        }
+    }
+}
+
+/// Per-channel tone curves sampled at evenly spaced levels from 0.0 to 1.0
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lut1d {
+    /// Output levels of the red, green and blue curves
+    pub channels: [Vec<f32>; 3],
+}
+
+impl Lut1d {
+    /// Curves that leave every level unchanged
+    pub fn identity(size: usize) -> Lut1d {
+        let ramp: Vec<f32> = (0..size).map(|index| index as f32 / (size - 1) as f32).collect();
+        Lut1d { channels: [ramp.clone(), ramp.clone(), ramp] }
+    }
+
+    /// Map a color through the curves, interpolating between entries
+    pub fn map(&self, rgb: [f32; 3]) -> [f32; 3] {
+        std::array::from_fn(|channel| lookup(&self.channels[channel], rgb[channel]))
+    }
+}
+
+/// Color cube sampled on a regular grid, red varying fastest
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lut3d {
+    /// Grid points along each axis (at least 2)
+    pub size: usize,
+    /// `size`³ output colors; entry `r + g * size + b * size²` belongs to the
+    /// input `(r, g, b) / (size - 1)`
+    pub table: Vec<[f32; 3]>,
+}
+
+impl Lut3d {
+    /// Cube that leaves every color unchanged
+    pub fn identity(size: usize) -> Lut3d {
+        let level = |index: usize| index as f32 / (size - 1) as f32;
+        let table = (0..size * size * size).map(|index| [level(index % size), level(index / size % size), level(index / (size * size))]).collect();
+        Lut3d { size, table }
+    }
+
+    /// Map a color through the cube, input clamped to 0.0-1.0
+    ///
+    /// Tetrahedral interpolation splits the cell around the color into six
+    /// tetrahedra along its gray diagonal and blends the four corners of the
+    /// one holding the color; trilinear blends all eight corners of the cell.
+    pub fn map(&self, rgb: [f32; 3], interpolation: LutInterpolation) -> [f32; 3] {
+        let last = (self.size - 1) as f32;
+        let position = rgb.map(|value| value.clamp(0.0, 1.0) * last);
+        let base = position.map(|value| (value as usize).min(self.size - 2));
+        let fraction: [f32; 3] = std::array::from_fn(|axis| position[axis] - base[axis] as f32);
+        let corner = |offset: [usize; 3]| {
+            let [r, g, b] = std::array::from_fn::<usize, 3, _>(|axis| base[axis] + offset[axis]);
+            self.table[r + (g + b * self.size) * self.size]
+        };
+
+        let mut result = [0.0; 3];
+        let mut add = |weight: f32, offset: [usize; 3]| {
+            for (total, value) in result.iter_mut().zip(corner(offset)) {
+                *total += weight * value;
+            }
+        };
+        match interpolation {
+            LutInterpolation::Trilinear => {
+                for index in 0..8 {
+                    let offset = [index & 1, index >> 1 & 1, index >> 2];
+                    let weight = (0..3).map(|axis| if offset[axis] == 1 { fraction[axis] } else { 1.0 - fraction[axis] }).product();
+                    add(weight, offset);
+                }
+            }
+            LutInterpolation::Tetrahedral => {
+                // Walk from the lower corner to the upper one, along the axes in order of their fraction
+                let mut axes = [0, 1, 2];
+                axes.sort_by(|&a, &b| fraction[b].total_cmp(&fraction[a]));
+                let weights = [1.0 - fraction[axes[0]], fraction[axes[0]] - fraction[axes[1]], fraction[axes[1]] - fraction[axes[2]], fraction[axes[2]]];
+                let mut offset = [0; 3];
+                for (step, weight) in weights.into_iter().enumerate() {
+                    if step > 0 {
+                        offset[axes[step - 1]] = 1;
+                    }
+                    add(weight, offset);
+                }
+            }
+        }
+        result
+    }
+}
+
+/// A run of point operations fused into one pass over the image
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointLut {
+    curves: Option<Lut1d>,
+    cube: Option<Lut3d>,
+}
+
+impl PointLut {
+    /// Sample a run of point operations into lookup tables
+    ///
+    /// # Arguments
+    /// * `operations` - Steps to fuse, in order; each must pass [`Operation::fuses`]
+    ///
+    /// # Returns
+    /// Tables that apply the whole run, or an error naming the first step that
+    /// is invalid or cannot be fused, e.g. `operations[3].op`
+    pub fn compile(operations: &[Operation]) -> Result<PointLut, EditorError> {
+        PointLut::compile_steps(operations, 0)
+    }
+
+    /// [`PointLut::compile`] for a run starting at pipeline step `first_step`
+    pub(crate) fn compile_steps(operations: &[Operation], first_step: usize) -> Result<PointLut, EditorError> {
+        check_point_operations(operations, first_step)?;
+        if let Some(step) = operations.iter().position(|operation| !operation.fuses()) {
+            let message = format!("`{}` bends colors too sharply to be fused", operations[step].name());
+            return Err(EditorError::invalid_parameter("op", message).at_pipeline_step(first_step + step));
+        }
+
+        let split = operations.iter().position(|operation| operation.point_kind() == Some(PointKind::Color)).unwrap_or(operations.len());
+        let (channel_steps, color_steps) = operations.split_at(split);
+
+        let curves = if channel_steps.is_empty() {
+            None
+        } else {
+            let identity = Lut1d::identity(CURVE_SIZE);
+            let mut ramp = Rgba32FImage::from_fn(CURVE_SIZE as u32, 1, |x, _| {
+                let level = identity.channels[0][x as usize];
+                Rgba([level, level, level, 1.0])
+            });
+            run(&mut ramp, channel_steps, first_step)?;
+            Some(Lut1d { channels: std::array::from_fn(|channel| ramp.pixels().map(|pixel| pixel[channel]).collect()) })
+        };
+
+        let cube = if color_steps.is_empty() {
+            None
+        } else {
+            Some(sample_cube(color_steps, FUSED_CUBE_SIZE, first_step + split)?)
+        };
+
+        Ok(PointLut { curves, cube })
+    }
+
+    /// Map one normalized color through the fused run
+    pub fn map(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let rgb = self.curves.as_ref().map_or(rgb, |curves| curves.map(rgb));
+        self.cube.as_ref().map_or(rgb, |cube| cube.map(rgb, LutInterpolation::Tetrahedral))
+    }
+
+    /// Apply the fused run to an image in one pass, keeping alpha
+    pub fn apply<P: EditorPixel>(&self, img: &mut Image<P>) {
+        match (&self.curves, &self.cube) {
+            (Some(curves), None) => P::map_tone(img, |channel, level| lookup(&curves.channels[channel], level)),
+            (None, None) => {}
+            _ => map_pixels(img, |[r, g, b, a]| {
+                let [r, g, b] = self.map([r, g, b]);
+                [r, g, b, a]
+            }),
+        }
+    }
+}
+
+/// Check that every step is a valid point operation, naming steps from `first_step` on
+fn check_point_operations(operations: &[Operation], first_step: usize) -> Result<(), EditorError> {
+    for (step, operation) in operations.iter().enumerate() {
+        if operation.point_kind().is_none() {
+            let message = format!("`{}` is not a point operation", operation.name());
+            return Err(EditorError::invalid_parameter("op", message).at_pipeline_step(first_step + step));
+        }
+        operation.validate().map_err(|e| e.at_pipeline_step(first_step + step))?;
+    }
+    Ok(())
+}
+
+/// Cube of `size`³ entries holding what `operations` do to each grid color
+fn sample_cube(operations: &[Operation], size: usize, first_step: usize) -> Result<Lut3d, EditorError> {
+    let identity = Lut3d::identity(size);
+    let mut grid = Rgba32FImage::from_fn(size as u32, (size * size) as u32, |x, y| {
+        let [r, g, b] = identity.table[x as usize + y as usize * size];
+        Rgba([r, g, b, 1.0])
+    });
+    run(&mut grid, operations, first_step)?;
+    Ok(Lut3d { size, table: grid.pixels().map(|pixel| [pixel[0], pixel[1], pixel[2]]).collect() })
+}
+
+/// Run `operations` on a sample buffer, naming failing steps from `first_step` on
+fn run(samples: &mut Rgba32FImage, operations: &[Operation], first_step: usize) -> Result<(), EditorError> {
+    for (step, operation) in operations.iter().enumerate() {
+        operation.apply(samples).map_err(|e| e.at_pipeline_step(first_step + step))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adjustments::SaturationModel;
+
+    #[test]
+    fn test_cube_interpolation() {
+        let identity = Lut3d::identity(5);
+        for rgb in [[0.0, 0.0, 0.0], [0.3, 0.71, 0.05], [1.0, 0.5, 0.25], [0.9, 0.9, 0.9]] {
+            for interpolation in [LutInterpolation::Trilinear, LutInterpolation::Tetrahedral] {
+                let mapped = identity.map(rgb, interpolation);
+                assert!(mapped.iter().zip(rgb).all(|(a, b)| (a - b).abs() < 1e-6), "{:?} {:?}", rgb, mapped);
+            }
+        }
+
+        // Between the grid points tetrahedra keep a gray axis exact, trilinear
+        // cells also blend in colors off the axis
+        let mut gray = Lut3d::identity(3);
+        for entry in &mut gray.table {
+            *entry = [entry[0].max(entry[1]).max(entry[2]); 3];
+        }
+        let [r, g, b] = gray.map([0.6, 0.6, 0.6], LutInterpolation::Tetrahedral);
+        assert!((r - 0.6).abs() < 1e-6 && r == g && g == b);
+        let [r, _, _] = gray.map([0.6, 0.6, 0.6], LutInterpolation::Trilinear);
+        assert!(r > 0.65, "{}", r);
+    }
+
+    #[test]
+    fn test_fused_run_matches_separate_passes() {
+        let source = Rgba32FImage::from_fn(40 * 40, 40, |x, y| Rgba([(x % 40) as f32 / 39.0, (x / 40) as f32 / 39.0, y as f32 / 39.0, 0.8]));
+        let differences = |operations: &[Operation]| {
+            let mut expected = source.clone();
+            run(&mut expected, operations, 0).unwrap();
+            let mut fused = source.clone();
+            PointLut::compile(operations).unwrap().apply(&mut fused);
+            assert!(fused.pixels().all(|pixel| pixel[3] == 0.8));
+            fused.pixels().zip(expected.pixels()).flat_map(|(a, b)| (0..3).map(move |c| (a[c] - b[c]).abs() * 255.0)).collect::<Vec<f32>>()
+        };
+
+        // Tone curves only
+        let tones = [
+            Operation::Exposure { stops: 0.4 },
+            Operation::Contrast { value: 15.0 },
+            Operation::Levels { black_point: 8, white_point: 240, gamma: 1.1 },
+            Operation::Curves { red_gamma: 1.1, green_gamma: 1.0, blue_gamma: 0.85 },
+            Operation::Brightness { value: -6 },
+        ];
+        let worst = differences(&tones).into_iter().fold(0.0, f32::max);
+        assert!(worst <= 1.0, "{}", worst);
+
+        // Tone curves and a color cube; white balance and saturation clip at the gamut boundary
+        let operations = [
+            Operation::Exposure { stops: 0.4 },
+            Operation::Contrast { value: 15.0 },
+            Operation::Highlights { amount: -20.0 },
+            Operation::ColorTemperature(crate::adjustments::WhiteBalance { temperature: 5000.0, tint: 3.0 }),
+            Operation::Saturation { value: 30.0, model: SaturationModel::Hsl },
+            Operation::Hue { shift: 4.0 },
+            Operation::Brightness { value: -6 },
+        ];
+        let differences = differences(&operations);
+        let mean = differences.iter().sum::<f32>() / differences.len() as f32;
+        let worst = differences.iter().fold(0.0, |worst: f32, &d| worst.max(d));
+        let over = differences.iter().filter(|&&d| d > 1.0).count() as f32 / differences.len() as f32;
+        // A few hundredths of a level on average, strays only next to clipped colors
+        assert!(mean < 0.05 && over < 0.01 && worst <= FUSED_CUBE_TOLERANCE, "{} {} {}", mean, worst, over);
+
+        let error = PointLut::compile(&[Operation::Sepia, Operation::GaussianBlur { sigma: 1.0 }]).unwrap_err();
+        assert_eq!(error.parameter(), Some("operations[1].op"));
+
+        // OKLCh saturation boosts are too steep next to the gamut boundary for any grid
+        let saturation = Operation::Saturation { value: 60.0, model: SaturationModel::Oklch };
+        let error = PointLut::compile(&[Operation::Exposure { stops: 0.4 }, saturation]).unwrap_err();
+        assert_eq!(error.parameter(), Some("operations[1].op"));
+    }
+}
@@ -0,0 +1,654 @@
+//! EXIF, ICC and XMP metadata
+//!
+//! [`Metadata`] holds the raw metadata blocks of a decoded file so they can be
+//! written back on export. EXIF and XMP are read from JPEG, PNG and WebP
+//! containers; the ICC profile from every format whose decoder exposes it.
+//!
+//! On export, [`MetadataOptions`] decides what is kept. PNG, JPEG and WebP can
+//! carry all three blocks; the other formats drop metadata.
+
+use exif::{In, Reader, Tag, Value};
+use serde::{Deserialize, Serialize};
+
+use crate::error::EditorError;
+
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const XMP_KEYWORD: &[u8] = b"XML:com.adobe.xmp";
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_GPS_IFD: u16 = 0x8825;
+
+/// Raw metadata blocks of an image
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    /// EXIF as a TIFF structure, without the `Exif\0\0` prefix
+    pub exif: Option<Vec<u8>>,
+    /// ICC color profile
+    pub icc_profile: Option<Vec<u8>>,
+    /// XMP packet (XML)
+    pub xmp: Option<Vec<u8>>,
+}
+
+/// Which metadata to keep on export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetadataOptions {
+    /// Keep the EXIF block
+    pub exif: bool,
+    /// Remove GPS position from kept EXIF and drop XMP that records one
+    pub strip_gps: bool,
+    /// Keep the XMP packet
+    pub xmp: bool,
+    /// Keep the ICC profile so colors display the same
+    pub icc_profile: bool,
+}
+
+impl Default for MetadataOptions {
+    fn default() -> MetadataOptions {
+        MetadataOptions::keep_all()
+    }
+}
+
+impl MetadataOptions {
+    /// Keep every block unchanged
+    pub fn keep_all() -> MetadataOptions {
+        MetadataOptions {
+            exif: true,
+            strip_gps: false,
+            xmp: true,
+            icc_profile: true,
+        }
+    }
+
+    /// Drop EXIF and XMP but keep the ICC profile
+    pub fn strip_all() -> MetadataOptions {
+        MetadataOptions {
+            exif: false,
+            strip_gps: false,
+            xmp: false,
+            icc_profile: true,
+        }
+    }
+}
+
+/// Commonly used EXIF fields in readable units
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ExifInfo {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub lens_make: Option<String>,
+    pub lens_model: Option<String>,
+    /// Exposure time in seconds
+    pub exposure_time: Option<f64>,
+    pub f_number: Option<f64>,
+    pub iso: Option<u32>,
+    /// Focal length in millimetres
+    pub focal_length: Option<f64>,
+    /// Capture time as `YYYY-MM-DDTHH:MM:SS`, with the UTC offset when recorded
+    pub date_time_original: Option<String>,
+    /// EXIF orientation (1-8)
+    pub orientation: Option<u16>,
+    pub gps: Option<GpsPosition>,
+}
+
+/// GPS position in decimal degrees
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct GpsPosition {
+    /// Positive north of the equator
+    pub latitude: f64,
+    /// Positive east of Greenwich
+    pub longitude: f64,
+    /// Metres above sea level
+    pub altitude: Option<f64>,
+}
+
+impl Metadata {
+    /// Whether no block is present
+    pub fn is_empty(&self) -> bool {
+        self.exif.is_none() && self.icc_profile.is_none() && self.xmp.is_none()
+    }
+
+    /// Extract EXIF and XMP from encoded JPEG, PNG or WebP bytes
+    ///
+    /// The ICC profile is filled in by [`crate::utils::load_image_with`], which
+    /// has the decoder at hand.
+    pub fn read(image_data: &[u8]) -> Metadata {
+        if image_data.starts_with(&[0xFF, 0xD8]) {
+            read_jpeg(image_data)
+        } else if image_data.starts_with(PNG_SIGNATURE) {
+            read_png(image_data)
+        } else if image_data.starts_with(b"RIFF") && image_data.get(8..12) == Some(b"WEBP") {
+            read_webp(image_data)
+        } else {
+            Metadata::default()
+        }
+    }
+
+    /// EXIF orientation (1-8), if recorded
+    pub fn orientation(&self) -> Option<u16> {
+        let tiff = Tiff::new(self.exif.as_deref()?)?;
+        let entry = tiff.find_entry(tiff.first_ifd()?, TAG_ORIENTATION)?;
+        // SHORT, single value
+        if tiff.u16_at(entry + 2)? != 3 || tiff.u32_at(entry + 4)? != 1 {
+            return None;
+        }
+        tiff.u16_at(entry + 8).filter(|value| (1..=8).contains(value))
+    }
+
+    /// Record that the pixels are upright, after the orientation was applied
+    pub(crate) fn reset_orientation(&mut self) {
+        let Some(exif) = self.exif.as_mut() else {
+            return;
+        };
+        let Some(entry) = Tiff::new(exif).and_then(|tiff| {
+            let entry = tiff.find_entry(tiff.first_ifd()?, TAG_ORIENTATION)?;
+            (tiff.u16_at(entry + 2)? == 3).then_some(entry)
+        }) else {
+            return;
+        };
+        let one = if exif.starts_with(b"II") { [1, 0] } else { [0, 1] };
+        exif[entry + 8..entry + 10].copy_from_slice(&one);
+    }
+
+    /// Parse the common EXIF fields
+    ///
+    /// # Returns
+    /// `None` without EXIF, or a `Decode` error if the block is malformed
+    pub fn exif_info(&self) -> Result<Option<ExifInfo>, EditorError> {
+        let Some(raw) = &self.exif else {
+            return Ok(None);
+        };
+        let exif = Reader::new()
+            .read_raw(raw.clone())
+            .map_err(|e| EditorError::Decode(format!("invalid EXIF: {}", e)))?;
+
+        let field = |tag: Tag| exif.get_field(tag, In::PRIMARY).map(|field| &field.value);
+        let text = |tag: Tag| match field(tag) {
+            Some(Value::Ascii(values)) => values
+                .first()
+                .map(|value| String::from_utf8_lossy(value).trim_end_matches(['\0', ' ']).to_string())
+                .filter(|value| !value.is_empty()),
+            _ => None,
+        };
+        let rational = |tag: Tag| match field(tag) {
+            Some(Value::Rational(values)) => values.first().map(|value| value.to_f64()).filter(|value| value.is_finite()),
+            _ => None,
+        };
+        let degrees = |tag: Tag, reference: Tag, negative: &str| match field(tag) {
+            Some(Value::Rational(values)) if values.len() == 3 => {
+                let value = values[0].to_f64() + values[1].to_f64() / 60.0 + values[2].to_f64() / 3600.0;
+                let sign = if text(reference).as_deref() == Some(negative) { -1.0 } else { 1.0 };
+                Some(sign * value).filter(|value| value.is_finite())
+            }
+            _ => None,
+        };
+
+        let gps = match (
+            degrees(Tag::GPSLatitude, Tag::GPSLatitudeRef, "S"),
+            degrees(Tag::GPSLongitude, Tag::GPSLongitudeRef, "W"),
+        ) {
+            (Some(latitude), Some(longitude)) => {
+                let below_sea_level = field(Tag::GPSAltitudeRef).and_then(|value| value.get_uint(0)) == Some(1);
+                let altitude = rational(Tag::GPSAltitude).map(|value| if below_sea_level { -value } else { value });
+                Some(GpsPosition { latitude, longitude, altitude })
+            }
+            _ => None,
+        };
+
+        let date_time_original = text(Tag::DateTimeOriginal).and_then(|value| {
+            let time = exif::DateTime::from_ascii(value.as_bytes()).ok()?;
+            let mut formatted = format!(
+                "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+                time.year, time.month, time.day, time.hour, time.minute, time.second
+            );
+            if let Some(offset) = text(Tag::OffsetTimeOriginal) {
+                formatted.push_str(&offset);
+            }
+            Some(formatted)
+        });
+
+        Ok(Some(ExifInfo {
+            make: text(Tag::Make),
+            model: text(Tag::Model),
+            lens_make: text(Tag::LensMake),
+            lens_model: text(Tag::LensModel),
+            exposure_time: rational(Tag::ExposureTime),
+            f_number: rational(Tag::FNumber),
+            iso: field(Tag::PhotographicSensitivity).and_then(|value| value.get_uint(0)),
+            focal_length: rational(Tag::FocalLength),
+            date_time_original,
+            orientation: self.orientation(),
+            gps,
+        }))
+    }
+
+    /// Copy of the metadata reduced to what `options` keeps
+    pub fn filtered(&self, options: &MetadataOptions) -> Metadata {
+        let mut exif = self.exif.clone().filter(|_| options.exif);
+        let mut xmp = self.xmp.clone().filter(|_| options.xmp);
+        if options.strip_gps {
+            // Unreadable EXIF is dropped rather than risk leaking the position
+            if let Some(block) = exif.as_mut() {
+                if strip_gps(block).is_none() {
+                    exif = None;
+                }
+            }
+            if xmp.as_deref().is_some_and(|packet| contains(packet, b"GPS")) {
+                xmp = None;
+            }
+        }
+        Metadata {
+            exif,
+            icc_profile: self.icc_profile.clone().filter(|_| options.icc_profile),
+            xmp,
+        }
+    }
+}
+
+/// Read-only view of a TIFF structure with bounds-checked accessors
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Option<Tiff<'a>> {
+        let little_endian = match data.get(..4)? {
+            [b'I', b'I', 42, 0] => true,
+            [b'M', b'M', 0, 42] => false,
+            _ => return None,
+        };
+        Some(Tiff { data, little_endian })
+    }
+
+    fn u16_at(&self, offset: usize) -> Option<u16> {
+        let bytes = self.data.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if self.little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        let bytes = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+    }
+
+    fn first_ifd(&self) -> Option<usize> {
+        self.u32_at(4).map(|offset| offset as usize)
+    }
+
+    /// Offsets of the 12-byte entries of the IFD at `ifd`
+    fn entries(&self, ifd: usize) -> Option<impl Iterator<Item = usize>> {
+        let count = self.u16_at(ifd)? as usize;
+        self.data.get(ifd + 2..ifd + 2 + count * 12)?;
+        Some((0..count).map(move |index| ifd + 2 + index * 12))
+    }
+
+    fn find_entry(&self, ifd: usize, tag: u16) -> Option<usize> {
+        self.entries(ifd)?.find(|&entry| self.u16_at(entry) == Some(tag))
+    }
+}
+
+/// Empty the GPS IFD, zeroing every value it points to
+fn strip_gps(exif: &mut [u8]) -> Option<()> {
+    let tiff = Tiff::new(exif)?;
+    let Some(pointer) = tiff.find_entry(tiff.first_ifd()?, TAG_GPS_IFD) else {
+        return Some(());
+    };
+    let gps_ifd = tiff.u32_at(pointer + 8)? as usize;
+
+    let mut cleared = Vec::new();
+    for entry in tiff.entries(gps_ifd)? {
+        let value_size = match tiff.u16_at(entry + 2)? {
+            1 | 2 | 6 | 7 => 1,
+            3 | 8 => 2,
+            4 | 9 | 11 => 4,
+            5 | 10 | 12 => 8,
+            _ => 0,
+        };
+        let size = value_size * tiff.u32_at(entry + 4)? as usize;
+        if size > 4 {
+            let offset = tiff.u32_at(entry + 8)? as usize;
+            cleared.push(offset..offset.checked_add(size)?.min(exif.len()));
+        }
+        cleared.push(entry..entry + 12);
+    }
+
+    for range in cleared {
+        exif.get_mut(range)?.fill(0);
+    }
+    exif[gps_ifd..gps_ifd + 2].fill(0);
+    Some(())
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+/// Walk the JPEG marker segments up to the image data
+fn read_jpeg(data: &[u8]) -> Metadata {
+    let mut metadata = Metadata::default();
+    let mut position = 2;
+    while position + 4 <= data.len() && data[position] == 0xFF {
+        let marker = data[position + 1];
+        if marker == 0xFF {
+            position += 1;
+            continue;
+        }
+        // Start of scan or end of image
+        if marker == 0xDA || marker == 0xD9 {
+            break;
+        }
+        let length = u16::from_be_bytes([data[position + 2], data[position + 3]]) as usize;
+        let Some(segment) = data.get(position + 4..position + 2 + length) else {
+            break;
+        };
+        if marker == 0xE1 {
+            if let Some(exif) = segment.strip_prefix(EXIF_HEADER) {
+                metadata.exif.get_or_insert_with(|| exif.to_vec());
+            } else if let Some(xmp) = segment.strip_prefix(XMP_HEADER) {
+                metadata.xmp.get_or_insert_with(|| xmp.to_vec());
+            }
+        }
+        position += 2 + length;
+    }
+    metadata
+}
+
+/// Collect `eXIf` and uncompressed XMP `iTXt` chunks
+fn read_png(data: &[u8]) -> Metadata {
+    let mut metadata = Metadata::default();
+    let mut position = PNG_SIGNATURE.len();
+    while let Some(header) = data.get(position..position + 8) {
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let Some(chunk) = data.get(position + 8..(position + 8).saturating_add(length)) else {
+            break;
+        };
+        match &header[4..] {
+            b"eXIf" => metadata.exif = Some(chunk.to_vec()),
+            b"iTXt" => {
+                if let Some(xmp) = read_xmp_itxt(chunk) {
+                    metadata.xmp = Some(xmp.to_vec());
+                }
+            }
+            b"IEND" => break,
+            _ => {}
+        }
+        position += 12 + length;
+    }
+    metadata
+}
+
+/// Text of an uncompressed `iTXt` chunk with the XMP keyword
+fn read_xmp_itxt(chunk: &[u8]) -> Option<&[u8]> {
+    let rest = chunk.strip_prefix(XMP_KEYWORD)?.strip_prefix(b"\0")?;
+    // Compression flag and method
+    let (&[0, _], rest) = rest.split_at_checked(2)? else {
+        return None;
+    };
+    // Language tag and translated keyword
+    let rest = &rest[rest.iter().position(|&byte| byte == 0)? + 1..];
+    let rest = &rest[rest.iter().position(|&byte| byte == 0)? + 1..];
+    Some(rest)
+}
+
+/// Collect the `EXIF` and `XMP ` chunks of a RIFF WebP file
+fn read_webp(data: &[u8]) -> Metadata {
+    let mut metadata = Metadata::default();
+    for (fourcc, chunk) in riff_chunks(data) {
+        match fourcc {
+            b"EXIF" => {
+                // Some writers keep the JPEG-style prefix
+                metadata.exif = Some(chunk.strip_prefix(EXIF_HEADER).unwrap_or(chunk).to_vec());
+            }
+            b"XMP " => metadata.xmp = Some(chunk.to_vec()),
+            _ => {}
+        }
+    }
+    metadata
+}
+
+/// Chunks of a RIFF WebP file as `(fourcc, payload)`
+fn riff_chunks(data: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut chunks = Vec::new();
+    let mut position = 12;
+    while let Some(header) = data.get(position..position + 8) {
+        let length = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let Some(chunk) = data.get(position + 8..(position + 8).saturating_add(length)) else {
+            break;
+        };
+        chunks.push((&header[..4], chunk));
+        position += 8 + length + (length & 1);
+    }
+    chunks
+}
+
+/// Insert `eXIf` and XMP `iTXt` chunks after the PNG header
+///
+/// The ICC profile is written by the PNG encoder itself.
+pub(crate) fn insert_png_chunks(png: Vec<u8>, metadata: &Metadata) -> Vec<u8> {
+    let mut chunks = Vec::new();
+    if let Some(exif) = &metadata.exif {
+        write_png_chunk(&mut chunks, b"eXIf", exif);
+    }
+    if let Some(xmp) = &metadata.xmp {
+        let mut text = XMP_KEYWORD.to_vec();
+        // No compression, empty language tag and translated keyword
+        text.extend_from_slice(&[0, 0, 0, 0, 0]);
+        text.extend_from_slice(xmp);
+        write_png_chunk(&mut chunks, b"iTXt", &text);
+    }
+    if chunks.is_empty() {
+        return png;
+    }
+
+    // Signature and the 13-byte IHDR chunk always come first
+    let header_end = PNG_SIGNATURE.len() + 12 + 13;
+    let mut output = Vec::with_capacity(png.len() + chunks.len());
+    output.extend_from_slice(&png[..header_end]);
+    output.extend_from_slice(&chunks);
+    output.extend_from_slice(&png[header_end..]);
+    output
+}
+
+fn write_png_chunk(output: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = output.len();
+    output.extend_from_slice(kind);
+    output.extend_from_slice(data);
+    let crc = crc32fast::hash(&output[start..]);
+    output.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// JPEG APP1 payloads for the EXIF and XMP blocks
+pub(crate) fn jpeg_app1_segments(metadata: &Metadata) -> Vec<Vec<u8>> {
+    let exif = metadata.exif.as_ref().map(|exif| [EXIF_HEADER, exif].concat());
+    let xmp = metadata.xmp.as_ref().map(|xmp| [XMP_HEADER, xmp].concat());
+    exif.into_iter().chain(xmp).collect()
+}
+
+/// Rewrite a WebP file in the extended format carrying the metadata chunks
+///
+/// # Arguments
+/// * `webp` - A simple or extended WebP file as produced by an encoder
+/// * `width`, `height` - Canvas size, 1 to 2^24 pixels as the VP8X header stores it
+///
+/// # Returns
+/// The rewritten file, or an error when the canvas size does not fit the header
+pub(crate) fn insert_webp_chunks(webp: Vec<u8>, width: u32, height: u32, metadata: &Metadata) -> Result<Vec<u8>, EditorError> {
+    if metadata.is_empty() {
+        return Ok(webp);
+    }
+    let canvas_field = |size: u32| match size.checked_sub(1) {
+        Some(field) if field < 1 << 24 => Ok(field.to_le_bytes()),
+        _ => Err(EditorError::Encode(format!("WebP canvas of {}x{} cannot carry metadata", width, height))),
+    };
+    let (width_field, height_field) = (canvas_field(width)?, canvas_field(height)?);
+
+    let chunks = riff_chunks(&webp);
+    let has_alpha = chunks.iter().any(|&(fourcc, chunk)| match fourcc {
+        b"VP8X" => chunk.first().is_some_and(|flags| flags & 0x10 != 0),
+        b"ALPH" => true,
+        // Alpha hint in the lossless bitstream header
+        b"VP8L" => chunk.get(4).is_some_and(|byte| byte & 0x10 != 0),
+        _ => false,
+    });
+
+    let mut flags = 0u8;
+    if metadata.icc_profile.is_some() {
+        flags |= 0x20;
+    }
+    if has_alpha {
+        flags |= 0x10;
+    }
+    if metadata.exif.is_some() {
+        flags |= 0x08;
+    }
+    if metadata.xmp.is_some() {
+        flags |= 0x04;
+    }
+    let mut header = vec![flags, 0, 0, 0];
+    header.extend_from_slice(&width_field[..3]);
+    header.extend_from_slice(&height_field[..3]);
+
+    let mut body = b"WEBP".to_vec();
+    write_riff_chunk(&mut body, b"VP8X", &header);
+    if let Some(icc) = &metadata.icc_profile {
+        write_riff_chunk(&mut body, b"ICCP", icc);
+    }
+    for (fourcc, chunk) in chunks {
+        if matches!(fourcc, b"ALPH" | b"VP8 " | b"VP8L") {
+            write_riff_chunk(&mut body, fourcc, chunk);
+        }
+    }
+    if let Some(exif) = &metadata.exif {
+        write_riff_chunk(&mut body, b"EXIF", exif);
+    }
+    if let Some(xmp) = &metadata.xmp {
+        write_riff_chunk(&mut body, b"XMP ", xmp);
+    }
+
+    let mut output = b"RIFF".to_vec();
+    output.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    output.extend_from_slice(&body);
+    Ok(output)
+}
+
+fn write_riff_chunk(output: &mut Vec<u8>, fourcc: &[u8], data: &[u8]) {
+    output.extend_from_slice(fourcc);
+    output.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    output.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        output.push(0);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use exif::experimental::Writer;
+    use exif::{Field, Rational};
+    use std::io::Cursor;
+
+    /// EXIF block of a phone photo taken in portrait orientation
+    pub(crate) fn sample_exif(orientation: u16) -> Vec<u8> {
+        let ascii = |tag, text: &str| Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![text.as_bytes().to_vec()]),
+        };
+        let rational = |tag, values: &[(u32, u32)]| Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Rational(values.iter().map(|&value| Rational::from(value)).collect()),
+        };
+        let fields = [
+            ascii(Tag::Make, "Pixel"),
+            ascii(Tag::Model, "Pixel 8"),
+            Field { tag: Tag::Orientation, ifd_num: In::PRIMARY, value: Value::Short(vec![orientation]) },
+            ascii(Tag::DateTimeOriginal, "2024:05:17 14:03:09"),
+            ascii(Tag::OffsetTimeOriginal, "+09:00"),
+            rational(Tag::ExposureTime, &[(1, 250)]),
+            rational(Tag::FNumber, &[(18, 10)]),
+            Field { tag: Tag::PhotographicSensitivity, ifd_num: In::PRIMARY, value: Value::Short(vec![200]) },
+            ascii(Tag::GPSLatitudeRef, "N"),
+            rational(Tag::GPSLatitude, &[(35, 1), (39, 1), (2952, 100)]),
+            ascii(Tag::GPSLongitudeRef, "E"),
+            rational(Tag::GPSLongitude, &[(139, 1), (42, 1), (1800, 100)]),
+        ];
+
+        let mut writer = Writer::new();
+        for field in &fields {
+            writer.push_field(field);
+        }
+        let mut buffer = Cursor::new(Vec::new());
+        writer.write(&mut buffer, false).unwrap();
+        buffer.into_inner()
+    }
+
+    #[test]
+    fn test_exif_info() {
+        let metadata = Metadata { exif: Some(sample_exif(6)), ..Metadata::default() };
+        let info = metadata.exif_info().unwrap().unwrap();
+
+        assert_eq!(info.make.as_deref(), Some("Pixel"));
+        assert_eq!(info.model.as_deref(), Some("Pixel 8"));
+        assert_eq!(info.exposure_time, Some(0.004));
+        assert_eq!(info.f_number, Some(1.8));
+        assert_eq!(info.iso, Some(200));
+        assert_eq!(info.orientation, Some(6));
+        assert_eq!(info.date_time_original.as_deref(), Some("2024-05-17T14:03:09+09:00"));
+        let gps = info.gps.unwrap();
+        assert!((gps.latitude - 35.6582).abs() < 1e-4);
+        assert!((gps.longitude - 139.705).abs() < 1e-4);
+
+        assert_eq!(Metadata::default().exif_info().unwrap(), None);
+        let garbage = Metadata { exif: Some(b"not exif".to_vec()), ..Metadata::default() };
+        assert_eq!(garbage.exif_info().unwrap_err().code(), "DECODE_ERROR");
+    }
+
+    #[test]
+    fn test_filtered_strips_gps() {
+        let metadata = Metadata {
+            exif: Some(sample_exif(1)),
+            icc_profile: Some(b"profile".to_vec()),
+            xmp: Some(b"<x:xmpmeta><exif:GPSLatitude>35,39N</exif:GPSLatitude></x:xmpmeta>".to_vec()),
+        };
+
+        let options = MetadataOptions { strip_gps: true, ..MetadataOptions::keep_all() };
+        let stripped = metadata.filtered(&options);
+        let info = stripped.exif_info().unwrap().unwrap();
+        assert_eq!(info.gps, None);
+        assert_eq!(info.model.as_deref(), Some("Pixel 8"));
+        assert_eq!(stripped.xmp, None);
+        assert_eq!(stripped.icc_profile, metadata.icc_profile);
+
+        let minimal = metadata.filtered(&MetadataOptions::strip_all());
+        assert_eq!(minimal, Metadata { icc_profile: metadata.icc_profile.clone(), ..Metadata::default() });
+    }
+
+    #[test]
+    fn test_reset_orientation() {
+        let mut metadata = Metadata { exif: Some(sample_exif(8)), ..Metadata::default() };
+        assert_eq!(metadata.orientation(), Some(8));
+        metadata.reset_orientation();
+        assert_eq!(metadata.orientation(), Some(1));
+        assert_eq!(metadata.exif_info().unwrap().unwrap().make.as_deref(), Some("Pixel"));
+    }
+
+    #[test]
+    fn test_webp_canvas_size_is_checked() {
+        let metadata = Metadata { exif: Some(sample_exif(1)), ..Metadata::default() };
+        let webp = b"RIFF\x04\0\0\0WEBP".to_vec();
+
+        let rewritten = insert_webp_chunks(webp.clone(), 3, 2, &metadata).unwrap();
+        assert_eq!(&rewritten[20..30], &[0x08, 0, 0, 0, 2, 0, 0, 1, 0, 0]);
+
+        for (width, height) in [(0, 2), (3, 0), ((1 << 24) + 1, 2)] {
+            let error = insert_webp_chunks(webp.clone(), width, height, &metadata).unwrap_err();
+            assert_eq!(error.code(), "ENCODE_ERROR");
+        }
+        // Nothing to insert, nothing to check
+        assert_eq!(insert_webp_chunks(webp.clone(), 0, 0, &Metadata::default()).unwrap(), webp);
+    }
+}
@@ -0,0 +1,288 @@
+//! Exposure overlays
+//!
+//! Views that show how an edited image is exposed rather than edits of their
+//! own. They run on the same buffer as the edits, so what they mark is exactly
+//! what the export would contain:
+//!
+//! - [`clipping_mask`]: one byte per pixel flagging which channels clip
+//! - [`clipping_overlay`]: the image with clipped pixels painted over, each in
+//!   the color of the channels that clip
+//! - [`false_color`]: an IRE false-color map or zebra stripes over bright areas
+//!
+//! IRE is the sRGB-encoded Rec.709 luminance on a 0-100 scale.
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::luma;
+use crate::error::{ensure_finite, EditorError};
+use crate::pixel::{EditorPixel, Image};
+
+/// [`clipping_mask`] bits of the red, green and blue channels clipping to white
+pub const CLIPPED_HIGHLIGHT: [u8; 3] = [0b0000_0001, 0b0000_0010, 0b0000_0100];
+/// [`clipping_mask`] bits of the red, green and blue channels clipping to black
+pub const CLIPPED_SHADOW: [u8; 3] = [0b0001_0000, 0b0010_0000, 0b0100_0000];
+
+/// Thresholds and colors of the clipping views
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClippingOptions {
+    /// 8-bit level at or below which a channel counts as clipped to black
+    pub shadow_level: u8,
+    /// 8-bit level at or above which a channel counts as clipped to white
+    pub highlight_level: u8,
+    /// Paint for pixels clipped to white in every channel
+    pub highlight_color: [u8; 3],
+    /// Paint for pixels clipped to black in every channel
+    pub shadow_color: [u8; 3],
+}
+
+impl Default for ClippingOptions {
+    fn default() -> ClippingOptions {
+        ClippingOptions { shadow_level: 0, highlight_level: 255, highlight_color: [255, 0, 0], shadow_color: [0, 0, 255] }
+    }
+}
+
+impl ClippingOptions {
+    /// Parse options from JSON
+    pub fn from_json(json: &str) -> Result<ClippingOptions, EditorError> {
+        let options: ClippingOptions =
+            serde_json::from_str(json).map_err(|e| EditorError::invalid_parameter("clipping", e.to_string()))?;
+        options.validate()?;
+        Ok(options)
+    }
+
+    /// Check that the shadow level lies below the highlight level
+    pub fn validate(&self) -> Result<(), EditorError> {
+        if self.shadow_level >= self.highlight_level {
+            return Err(EditorError::invalid_parameter("shadow_level", "must be lower than highlight_level"));
+        }
+        Ok(())
+    }
+
+    /// Mask bits of a normalized pixel
+    fn flags(&self, pixel: [f32; 4]) -> u8 {
+        let mut flags = 0;
+        for channel in 0..3 {
+            let level = pixel[channel] * 255.0;
+            if level < self.shadow_level as f32 + 0.5 {
+                flags |= CLIPPED_SHADOW[channel];
+            } else if level > self.highlight_level as f32 - 0.5 {
+                flags |= CLIPPED_HIGHLIGHT[channel];
+            }
+        }
+        flags
+    }
+}
+
+/// What [`false_color`] draws
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FalseColorMode {
+    /// Exposure bands in signal colors over a grayscale image
+    #[default]
+    Bands,
+    /// Diagonal stripes over areas at or above the zebra threshold
+    Zebra,
+}
+
+/// Settings of [`false_color`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FalseColorOptions {
+    pub mode: FalseColorMode,
+    /// IRE at or above which zebra stripes are drawn (0 to 100)
+    pub zebra_threshold: f32,
+    /// Width of each zebra stripe in pixels (1 to 256)
+    pub stripe_width: u32,
+}
+
+impl Default for FalseColorOptions {
+    fn default() -> FalseColorOptions {
+        FalseColorOptions { mode: FalseColorMode::Bands, zebra_threshold: 95.0, stripe_width: 8 }
+    }
+}
+
+impl FalseColorOptions {
+    /// Parse options from JSON
+    pub fn from_json(json: &str) -> Result<FalseColorOptions, EditorError> {
+        let options: FalseColorOptions =
+            serde_json::from_str(json).map_err(|e| EditorError::invalid_parameter("false_color", e.to_string()))?;
+        options.validate()?;
+        Ok(options)
+    }
+
+    /// Check the zebra settings
+    pub fn validate(&self) -> Result<(), EditorError> {
+        ensure_finite("zebra_threshold", self.zebra_threshold)?;
+        if !(0.0..=100.0).contains(&self.zebra_threshold) {
+            return Err(EditorError::invalid_parameter("zebra_threshold", "must be between 0 and 100"));
+        }
+        if !(1..=256).contains(&self.stripe_width) {
+            return Err(EditorError::invalid_parameter("stripe_width", "must be between 1 and 256"));
+        }
+        Ok(())
+    }
+}
+
+/// IRE bands of the false-color map, lower bound inclusive; levels between bands stay gray
+const FALSE_COLOR_BANDS: [(f32, f32, [u8; 3]); 7] = [
+    // Crushed blacks
+    (0.0, 2.5, [128, 0, 160]),
+    // Deep shadows
+    (2.5, 10.0, [0, 64, 255]),
+    // Middle gray (18% reflectance is about 46 IRE)
+    (42.0, 50.0, [0, 200, 0]),
+    // Skin tones
+    (52.0, 58.0, [255, 128, 160]),
+    // Bright but holding detail
+    (85.0, 95.0, [255, 230, 0]),
+    // About to clip
+    (95.0, 99.0, [255, 128, 0]),
+    // Clipped
+    (99.0, f32::INFINITY, [255, 0, 0]),
+];
+
+/// Flag clipped channels of every pixel
+///
+/// # Arguments
+/// * `img` - Image to check
+/// * `options` - Clipping levels (colors are ignored)
+///
+/// # Returns
+/// One byte per pixel in row order, combining [`CLIPPED_HIGHLIGHT`] and
+/// [`CLIPPED_SHADOW`] bits; 0 where nothing clips
+pub fn clipping_mask<P: EditorPixel>(img: &Image<P>, options: &ClippingOptions) -> Result<Vec<u8>, EditorError> {
+    options.validate()?;
+    Ok(img.pixels().map(|pixel| options.flags(pixel.to_unit())).collect())
+}
+
+/// The image with clipped pixels painted over
+///
+/// Pixels clipped in all three channels get `highlight_color` or
+/// `shadow_color`. Where only some channels clip to white, the pixel shows
+/// those channels (red, yellow, ...); where only some clip to black, it shows
+/// their complement, so a lost blue channel in the shadows reads as yellow.
+///
+/// # Arguments
+/// * `img` - Image to check
+/// * `options` - Clipping levels and colors
+///
+/// # Returns
+/// A new image of the same size, keeping alpha
+pub fn clipping_overlay<P: EditorPixel>(img: &Image<P>, options: &ClippingOptions) -> Result<Image<P>, EditorError> {
+    options.validate()?;
+    let mut overlay = img.clone();
+    for pixel in overlay.pixels_mut() {
+        let rgba = pixel.to_unit();
+        let flags = options.flags(rgba);
+        let highlight = CLIPPED_HIGHLIGHT.map(|bit| flags & bit != 0);
+        let shadow = CLIPPED_SHADOW.map(|bit| flags & bit != 0);
+
+        let paint = if highlight.iter().all(|&clipped| clipped) {
+            options.highlight_color
+        } else if shadow.iter().all(|&clipped| clipped) {
+            options.shadow_color
+        } else if highlight.iter().any(|&clipped| clipped) {
+            highlight.map(|clipped| if clipped { 255 } else { 0 })
+        } else if shadow.iter().any(|&clipped| clipped) {
+            shadow.map(|clipped| if clipped { 0 } else { 255 })
+        } else {
+            continue;
+        };
+        *pixel = P::from_unit(with_alpha(paint.map(|level| level as f32 / 255.0), rgba[3]));
+    }
+    Ok(overlay)
+}
+
+/// IRE false-color map or zebra stripes
+///
+/// # Arguments
+/// * `img` - Image to check
+/// * `options` - Mode and zebra settings
+///
+/// # Returns
+/// A new image of the same size, keeping alpha
+pub fn false_color<P: EditorPixel>(img: &Image<P>, options: &FalseColorOptions) -> Result<Image<P>, EditorError> {
+    options.validate()?;
+    let mut map = img.clone();
+    for (x, y, pixel) in map.enumerate_pixels_mut() {
+        let rgba = pixel.to_unit();
+        let clamped = rgba.map(|value| value.clamp(0.0, 1.0));
+        let ire = luma(clamped) * 100.0;
+
+        let color = match options.mode {
+            FalseColorMode::Bands => FALSE_COLOR_BANDS
+                .iter()
+                .find(|(lower, upper, _)| (*lower..*upper).contains(&ire))
+                .map(|(_, _, color)| color.map(|level| level as f32 / 255.0))
+                .unwrap_or([ire / 100.0; 3]),
+            FalseColorMode::Zebra => {
+                let on_stripe = ((x + y) / options.stripe_width).is_multiple_of(2);
+                if ire >= options.zebra_threshold && on_stripe {
+                    [0.0; 3]
+                } else {
+                    continue;
+                }
+            }
+        };
+        *pixel = P::from_unit(with_alpha(color, rgba[3]));
+    }
+    Ok(map)
+}
+
+fn with_alpha([r, g, b]: [f32; 3], alpha: f32) -> [f32; 4] {
+    [r, g, b, alpha]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_clipping_mask_and_overlay() {
+        let img = RgbaImage::from_fn(4, 1, |x, _| match x {
+            0 => Rgba([255, 255, 255, 255]),
+            1 => Rgba([255, 120, 40, 255]),
+            2 => Rgba([60, 40, 0, 200]),
+            _ => Rgba([128, 128, 128, 255]),
+        });
+
+        let mask = clipping_mask(&img, &ClippingOptions::default()).unwrap();
+        assert_eq!(mask, vec![0b0000_0111, CLIPPED_HIGHLIGHT[0], CLIPPED_SHADOW[2], 0]);
+
+        let overlay = clipping_overlay(&img, &ClippingOptions::default()).unwrap();
+        assert_eq!(overlay.get_pixel(0, 0), &Rgba([255, 0, 0, 255]));
+        assert_eq!(overlay.get_pixel(1, 0), &Rgba([255, 0, 0, 255]));
+        assert_eq!(overlay.get_pixel(2, 0), &Rgba([255, 255, 0, 200]));
+        assert_eq!(overlay.get_pixel(3, 0), img.get_pixel(3, 0));
+
+        // A looser threshold catches nearly clipped channels too
+        let loose = ClippingOptions { highlight_level: 250, ..ClippingOptions::default() };
+        let near = RgbaImage::from_pixel(1, 1, Rgba([252, 10, 10, 255]));
+        assert_eq!(clipping_mask(&near, &loose).unwrap(), vec![CLIPPED_HIGHLIGHT[0]]);
+        let error = ClippingOptions::from_json(r#"{ "shadow_level": 255 }"#).unwrap_err();
+        assert_eq!(error.parameter(), Some("shadow_level"));
+    }
+
+    #[test]
+    fn test_false_color_bands_and_zebra() {
+        let img = RgbaImage::from_fn(3, 1, |x, _| match x {
+            0 => Rgba([0, 0, 0, 255]),
+            1 => Rgba([119, 119, 119, 255]),
+            _ => Rgba([255, 255, 255, 255]),
+        });
+        let bands = false_color(&img, &FalseColorOptions::default()).unwrap();
+        assert_eq!(bands.get_pixel(0, 0), &Rgba([128, 0, 160, 255]));
+        assert_eq!(bands.get_pixel(1, 0), &Rgba([0, 200, 0, 255]));
+        assert_eq!(bands.get_pixel(2, 0), &Rgba([255, 0, 0, 255]));
+
+        let bright = RgbaImage::from_pixel(8, 8, Rgba([250, 250, 250, 255]));
+        let options = FalseColorOptions { mode: FalseColorMode::Zebra, stripe_width: 2, ..FalseColorOptions::default() };
+        let zebra = false_color(&bright, &options).unwrap();
+        assert_eq!(zebra.get_pixel(0, 0), &Rgba([0, 0, 0, 255]));
+        assert_eq!(zebra.get_pixel(2, 0), bright.get_pixel(2, 0));
+        let dark = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+        assert_eq!(false_color(&dark, &options).unwrap(), dark);
+    }
+}
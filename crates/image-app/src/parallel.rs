@@ -0,0 +1,20 @@
+//! Data parallelism for native and threaded WebAssembly builds
+//!
+//! Work is split into independent chunks that rayon spreads over all cores.
+//! WebAssembly builds without the `atomics` target feature have no threads, so
+//! there the same chunks run one after another and the results are identical.
+//! Threaded WebAssembly builds need the host to start rayon's thread pool
+//! (e.g. with `wasm-bindgen-rayon`) before the first edit.
+
+#[cfg(any(not(target_arch = "wasm32"), target_feature = "atomics"))]
+use rayon::prelude::*;
+
+/// Run `f` on every `chunk_len`-sized chunk of `data`, passing the chunk index
+///
+/// `chunk_len` must not be zero.
+pub(crate) fn for_each_chunk<T: Send>(data: &mut [T], chunk_len: usize, f: impl Fn(usize, &mut [T]) + Sync + Send) {
+    #[cfg(any(not(target_arch = "wasm32"), target_feature = "atomics"))]
+    data.par_chunks_mut(chunk_len).enumerate().for_each(|(index, chunk)| f(index, chunk));
+    #[cfg(all(target_arch = "wasm32", not(target_feature = "atomics")))]
+    data.chunks_mut(chunk_len).enumerate().for_each(|(index, chunk)| f(index, chunk));
+}
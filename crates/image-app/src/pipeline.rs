@@ -0,0 +1,767 @@
+//! Declarative edit pipelines
+//!
+//! A pipeline is an ordered list of operations serialized as JSON. It is decoded once,
+//! validated, and every step runs on the same in-memory image:
+//!
+//! ```json
+//! {
+//!   "version": 2,
+//!   "operations": [
+//!     { "op": "brightness", "value": 20 },
+//!     { "op": "levels", "black_point": 10, "white_point": 245, "gamma": 1.1 },
+//!     { "op": "gaussian_blur", "sigma": 1.5 }
+//!   ]
+//! }
+//! ```
+//!
+//! The same format is used for saved presets, so the `version` field is checked
+//! before anything is executed. Presets saved before the field existed omit it
+//! and are version 1. Version 1 `saturation` steps rotated hues; they are read
+//! as `hue_rotate` so old presets keep their look.
+//!
+//! [`Pipeline::compile`] fuses every run of two or more point operations into
+//! lookup tables (see [`crate::lut`]), so a long stack of sliders costs about
+//! one pass over the image; spatial filters, transforms, steps that measure
+//! the image and the few sliders a lookup table cannot follow closely (see
+//! [`Operation::fuses`]) still run on their own.
+
+use std::borrow::Cow;
+
+use image::Rgba;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ensure_finite, ensure_non_negative, EditorError};
+use crate::pixel::{EditorPixel, Image};
+use crate::transforms::{Interpolation, RotateOptions};
+use crate::adjustments::levels::check_clip_percent;
+use crate::adjustments::{ClaheOptions, Levels, SaturationModel, ToneCurves, WhiteBalance};
+use crate::filters::{BorderMode, ConvolveOptions, DenoiseOptions, UnsharpMaskOptions};
+use crate::lut::{CubeLut, LutInterpolation, PointLut};
+use crate::{adjustments, filters, transforms};
+
+/// Newest pipeline format understood by this build
+pub const PIPELINE_VERSION: u32 = 2;
+
+/// Presets saved before pipelines were versioned
+fn default_version() -> u32 {
+    1
+}
+
+fn default_clip_percent() -> f32 {
+    0.1
+}
+
+fn default_intensity() -> f32 {
+    100.0
+}
+
+/// Ordered list of operations applied to one decoded image
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pipeline {
+    #[serde(default = "default_version")]
+    pub version: u32,
+    pub operations: Vec<Operation>,
+}
+
+/// Pipeline ready to run, with runs of point operations fused (see [`Pipeline::compile`])
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledPipeline {
+    stages: Vec<Stage>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Stage {
+    /// A step that runs on its own, with its index for error messages
+    Single(usize, Operation),
+    Fused(PointLut),
+}
+
+/// How a point operation maps colors (see [`Operation::point_kind`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointKind {
+    /// Every output channel depends only on the same input channel
+    Channel,
+    /// Output channels mix the input channels
+    Color,
+}
+
+/// Single pipeline step, tagged by `op` in JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Operation {
+    Brightness { value: i32 },
+    Contrast { value: f32 },
+    Saturation {
+        value: f32,
+        #[serde(default)]
+        model: SaturationModel,
+    },
+    HueRotate { degrees: f32 },
+    WhiteBalance { value: f32 },
+    /// Kelvin white balance, e.g. `{ "op": "color_temperature", "temperature": 3200, "tint": 5 }`
+    ColorTemperature(WhiteBalance),
+    Hue { shift: f32 },
+    Exposure { stops: f32 },
+    Vibrance { amount: f32 },
+    Highlights { amount: f32 },
+    Shadows { amount: f32 },
+    Curves { red_gamma: f32, green_gamma: f32, blue_gamma: f32 },
+    /// Spline curves, e.g. `{ "op": "tone_curve", "master": [[0, 0], [128, 150], [255, 255]] }`
+    ToneCurve(ToneCurves),
+    Levels { black_point: u8, white_point: u8, gamma: f32 },
+    /// Per-channel levels, e.g. `{ "op": "channel_levels", "red": { "white_point": 240 } }`
+    ChannelLevels(Levels),
+    AutoLevels {
+        /// Share of pixels allowed to clip at each end, in percent
+        #[serde(default = "default_clip_percent")]
+        clip_percent: f32,
+    },
+    HistogramEqualization,
+    /// Color grade from the text of a `.cube` file, e.g. `{ "op": "lut", "cube": "LUT_3D_SIZE 33\n...", "intensity": 80 }`
+    Lut {
+        cube: CubeLut,
+        #[serde(default)]
+        interpolation: LutInterpolation,
+        /// Mix with the original colors, 0 to 100
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+    /// Local contrast, e.g. `{ "op": "clahe", "tiles_x": 8, "tiles_y": 8, "clip_limit": 2.0 }`
+    Clahe(ClaheOptions),
+    Grayscale,
+    Sepia,
+    GaussianBlur { sigma: f32 },
+    Sharpen {
+        amount: f32,
+        /// What the filter reads beyond the edges, e.g. `"mirror"` or `{ "constant": [0, 0, 0, 255] }`
+        #[serde(default)]
+        border: BorderMode,
+    },
+    /// e.g. `{ "op": "unsharp_mask", "radius": 1.5, "amount": 120, "threshold": 3, "luminance_only": true }`
+    UnsharpMask(UnsharpMaskOptions),
+    Vignette { strength: f32, radius: f32 },
+    ReduceNoise {
+        strength: f32,
+        #[serde(default)]
+        border: BorderMode,
+    },
+    /// Fast denoise, e.g. `{ "op": "denoise", "luminance": 30, "chroma": 60, "radius": 2 }`
+    Denoise(DenoiseOptions),
+    Emboss {
+        #[serde(default)]
+        border: BorderMode,
+    },
+    /// Custom or named kernel, e.g. `{ "op": "convolve", "kernel": { "name": "motion_blur", "length": 9, "angle": 0 } }`
+    Convolve(ConvolveOptions),
+    Rotate { angle: u32 },
+    RotateArbitrary {
+        angle: f32,
+        /// RGBA fill for the uncovered corners, transparent when omitted
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        background: Option<[u8; 4]>,
+        #[serde(default)]
+        interpolation: Interpolation,
+        /// Crop to the largest rectangle without uncovered corners
+        #[serde(default)]
+        auto_crop: bool,
+    },
+    FlipHorizontal,
+    FlipVertical,
+    Resize { width: u32, height: u32 },
+    Crop { x: u32, y: u32, width: u32, height: u32 },
+}
+
+impl Pipeline {
+    /// Parse and validate a pipeline from its JSON representation
+    ///
+    /// Older versions are brought to the current format.
+    pub fn from_json(json: &str) -> Result<Pipeline, EditorError> {
+        let pipeline: Pipeline =
+            serde_json::from_str(json).map_err(|e| EditorError::InvalidPipeline(e.to_string()))?;
+        let pipeline = pipeline.upgrade();
+        pipeline.validate()?;
+        Ok(pipeline)
+    }
+
+    /// Bring a pipeline saved by an older version to the current format
+    fn upgrade(mut self) -> Pipeline {
+        if self.version == 1 {
+            for operation in &mut self.operations {
+                if let Operation::Saturation { value, .. } = *operation {
+                    *operation = Operation::HueRotate { degrees: value };
+                }
+            }
+            self.version = 2;
+        }
+        self
+    }
+
+    /// Check the version and every step's parameters, upgrading a copy of older pipelines first
+    fn checked(&self) -> Result<Cow<'_, Pipeline>, EditorError> {
+        if self.version == 0 || self.version > PIPELINE_VERSION {
+            return Err(EditorError::UnsupportedPipelineVersion(self.version));
+        }
+        let pipeline = if self.version < PIPELINE_VERSION { Cow::Owned(self.clone().upgrade()) } else { Cow::Borrowed(self) };
+
+        for (step, operation) in pipeline.operations.iter().enumerate() {
+            operation.validate().map_err(|e| e.at_pipeline_step(step))?;
+        }
+
+        Ok(pipeline)
+    }
+
+    /// Serialize the pipeline, e.g. to store it as a preset
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("pipeline serialization cannot fail")
+    }
+
+    /// Check every step's parameters without touching any pixels
+    ///
+    /// Steps of older versions are checked as they will run. Bounds that
+    /// depend on the image size (crop rectangles) are checked when the step
+    /// runs.
+    pub fn validate(&self) -> Result<(), EditorError> {
+        self.checked().map(|_| ())
+    }
+
+    /// Run every step in order on a decoded image, one pass per step
+    ///
+    /// Parameter errors name the failing step, e.g. `operations[2].angle`. The
+    /// image may be partially processed when a step fails, so callers that need
+    /// the original should run the pipeline on a copy.
+    pub fn execute<P: EditorPixel>(&self, img: &mut Image<P>) -> Result<(), EditorError> {
+        let pipeline = self.checked()?;
+
+        for (step, operation) in pipeline.operations.iter().enumerate() {
+            operation.apply(img).map_err(|e| e.at_pipeline_step(step))?;
+        }
+
+        Ok(())
+    }
+
+    /// Validate the pipeline and fuse its runs of point operations
+    ///
+    /// Runs of tone adjustments stay within one 8-bit level of
+    /// [`Pipeline::execute`]. Runs that mix channels go through a color cube,
+    /// which is off by a few hundredths of a level on average and up to
+    /// [`crate::lut::FUSED_CUBE_TOLERANCE`] levels next to gamut clipping. The
+    /// compiled pipeline can be reused for any number of images.
+    pub fn compile(&self) -> Result<CompiledPipeline, EditorError> {
+        let pipeline = self.checked()?;
+        let operations = &pipeline.operations;
+
+        let mut stages = Vec::new();
+        let mut step = 0;
+        while step < operations.len() {
+            let run = operations[step..].iter().take_while(|operation| operation.fuses()).count();
+            if run >= 2 {
+                let lut = PointLut::compile_steps(&operations[step..step + run], step)?;
+                stages.push(Stage::Fused(lut));
+                step += run;
+            } else {
+                stages.push(Stage::Single(step, operations[step].clone()));
+                step += 1;
+            }
+        }
+
+        Ok(CompiledPipeline { stages })
+    }
+}
+
+impl CompiledPipeline {
+    /// Run the pipeline on a decoded image
+    ///
+    /// Errors and partial processing as in [`Pipeline::execute`].
+    pub fn execute<P: EditorPixel>(&self, img: &mut Image<P>) -> Result<(), EditorError> {
+        for stage in &self.stages {
+            match stage {
+                Stage::Single(step, operation) => operation.apply(img).map_err(|e| e.at_pipeline_step(*step))?,
+                Stage::Fused(lut) => lut.apply(img),
+            }
+        }
+        Ok(())
+    }
+
+    /// Passes over the image that one execution makes
+    pub fn passes(&self) -> usize {
+        self.stages.len()
+    }
+}
+
+impl Operation {
+    /// Name used in the `op` tag
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Brightness { .. } => "brightness",
+            Operation::Contrast { .. } => "contrast",
+            Operation::Saturation { .. } => "saturation",
+            Operation::HueRotate { .. } => "hue_rotate",
+            Operation::WhiteBalance { .. } => "white_balance",
+            Operation::ColorTemperature(_) => "color_temperature",
+            Operation::Hue { .. } => "hue",
+            Operation::Exposure { .. } => "exposure",
+            Operation::Vibrance { .. } => "vibrance",
+            Operation::Highlights { .. } => "highlights",
+            Operation::Shadows { .. } => "shadows",
+            Operation::Curves { .. } => "curves",
+            Operation::ToneCurve(_) => "tone_curve",
+            Operation::Levels { .. } => "levels",
+            Operation::ChannelLevels(_) => "channel_levels",
+            Operation::AutoLevels { .. } => "auto_levels",
+            Operation::HistogramEqualization => "histogram_equalization",
+            Operation::Lut { .. } => "lut",
+            Operation::Clahe(_) => "clahe",
+            Operation::Grayscale => "grayscale",
+            Operation::Sepia => "sepia",
+            Operation::GaussianBlur { .. } => "gaussian_blur",
+            Operation::Sharpen { .. } => "sharpen",
+            Operation::UnsharpMask(_) => "unsharp_mask",
+            Operation::Vignette { .. } => "vignette",
+            Operation::ReduceNoise { .. } => "reduce_noise",
+            Operation::Denoise(_) => "denoise",
+            Operation::Emboss { .. } => "emboss",
+            Operation::Convolve(_) => "convolve",
+            Operation::Rotate { .. } => "rotate",
+            Operation::RotateArbitrary { .. } => "rotate_arbitrary",
+            Operation::FlipHorizontal => "flip_horizontal",
+            Operation::FlipVertical => "flip_vertical",
+            Operation::Resize { .. } => "resize",
+            Operation::Crop { .. } => "crop",
+        }
+    }
+
+    /// Whether this step is a point operation, which maps every color on its own
+    ///
+    /// Steps that measure the image first, such as auto levels, are not.
+    pub fn point_kind(&self) -> Option<PointKind> {
+        match self {
+            Operation::Brightness { .. }
+            | Operation::Contrast { .. }
+            | Operation::Exposure { .. }
+            | Operation::Curves { .. }
+            | Operation::ToneCurve(_)
+            | Operation::Levels { .. }
+            | Operation::ChannelLevels(_) => Some(PointKind::Channel),
+            Operation::Saturation { .. }
+            | Operation::HueRotate { .. }
+            | Operation::WhiteBalance { .. }
+            | Operation::ColorTemperature(_)
+            | Operation::Hue { .. }
+            | Operation::Vibrance { .. }
+            | Operation::Highlights { .. }
+            | Operation::Shadows { .. }
+            | Operation::Grayscale
+            | Operation::Sepia
+            | Operation::Lut { .. } => Some(PointKind::Color),
+            Operation::AutoLevels { .. }
+            | Operation::HistogramEqualization
+            | Operation::Clahe(_)
+            | Operation::GaussianBlur { .. }
+            | Operation::Sharpen { .. }
+            | Operation::UnsharpMask(_)
+            | Operation::Vignette { .. }
+            | Operation::ReduceNoise { .. }
+            | Operation::Denoise(_)
+            | Operation::Emboss { .. }
+            | Operation::Convolve(_)
+            | Operation::Rotate { .. }
+            | Operation::RotateArbitrary { .. }
+            | Operation::FlipHorizontal
+            | Operation::FlipVertical
+            | Operation::Resize { .. }
+            | Operation::Crop { .. } => None,
+        }
+    }
+
+    /// Whether this step can join a fused run of point operations
+    ///
+    /// OKLCh saturation boosts and vibrance change steeply next to the gamut
+    /// boundary, too steeply for a color cube of any practical size to follow,
+    /// so they run on their own (see [`crate::lut`]).
+    pub fn fuses(&self) -> bool {
+        match *self {
+            Operation::Saturation { value, model: SaturationModel::Oklch } => value <= 0.0,
+            Operation::Vibrance { amount } => amount == 0.0,
+            _ => self.point_kind().is_some(),
+        }
+    }
+
+    /// Check the parameters that do not depend on the image
+    pub fn validate(&self) -> Result<(), EditorError> {
+        match *self {
+            Operation::Contrast { value } | Operation::WhiteBalance { value } => ensure_finite("value", value),
+            Operation::Saturation { value, .. } => adjustments::check_saturation(value),
+            Operation::HueRotate { degrees } => ensure_finite("degrees", degrees),
+            Operation::ColorTemperature(ref white_balance) => white_balance.validate(),
+            Operation::Hue { shift } => ensure_finite("shift", shift),
+            Operation::Exposure { stops } => ensure_finite("stops", stops),
+            Operation::Vibrance { amount }
+            | Operation::Highlights { amount }
+            | Operation::Shadows { amount } => ensure_finite("amount", amount),
+            Operation::Curves { red_gamma, green_gamma, blue_gamma } => {
+                adjustments::check_curves(red_gamma, green_gamma, blue_gamma)
+            }
+            Operation::ToneCurve(ref curves) => curves.validate(),
+            Operation::Levels { black_point, white_point, gamma } => {
+                adjustments::check_levels(black_point, white_point, gamma)
+            }
+            Operation::ChannelLevels(ref levels) => levels.validate(),
+            Operation::AutoLevels { clip_percent } => check_clip_percent(clip_percent),
+            Operation::Lut { intensity, .. } => adjustments::check_intensity(intensity),
+            Operation::Clahe(ref options) => options.validate(),
+            Operation::GaussianBlur { sigma } => ensure_non_negative("sigma", sigma),
+            Operation::Sharpen { amount, .. } => ensure_non_negative("amount", amount),
+            Operation::UnsharpMask(ref options) => options.validate(),
+            Operation::Vignette { strength, radius } => {
+                ensure_finite("strength", strength)?;
+                ensure_finite("radius", radius)
+            }
+            Operation::ReduceNoise { strength, .. } => ensure_non_negative("strength", strength),
+            Operation::Denoise(ref options) => options.validate(),
+            Operation::Convolve(ref options) => options.validate(),
+            Operation::Rotate { angle } => transforms::check_rotate(angle),
+            Operation::RotateArbitrary { angle, .. } => ensure_finite("angle", angle),
+            Operation::Resize { width, height } => transforms::check_resize(width, height),
+            Operation::Brightness { .. }
+            | Operation::Crop { .. }
+            | Operation::HistogramEqualization
+            | Operation::Grayscale
+            | Operation::Sepia
+            | Operation::Emboss { .. }
+            | Operation::FlipHorizontal
+            | Operation::FlipVertical => Ok(()),
+        }
+    }
+
+    /// Run this operation on a decoded image
+    ///
+    /// Geometry changes replace the buffer, everything else edits it in place.
+    pub fn apply<P: EditorPixel>(&self, img: &mut Image<P>) -> Result<(), EditorError> {
+        match *self {
+            Operation::Brightness { value } => adjustments::adjust_brightness(img, value),
+            Operation::Contrast { value } => adjustments::adjust_contrast(img, value),
+            Operation::Saturation { value, model } => adjustments::adjust_saturation_with(img, value, model),
+            Operation::HueRotate { degrees } => adjustments::rotate_hue(img, degrees),
+            Operation::WhiteBalance { value } => adjustments::adjust_white_balance(img, value),
+            Operation::ColorTemperature(ref white_balance) => adjustments::apply_white_balance(img, white_balance),
+            Operation::Hue { shift } => adjustments::adjust_hue(img, shift),
+            Operation::Exposure { stops } => adjustments::adjust_exposure(img, stops),
+            Operation::Vibrance { amount } => adjustments::adjust_vibrance(img, amount),
+            Operation::Highlights { amount } => adjustments::adjust_highlights(img, amount),
+            Operation::Shadows { amount } => adjustments::adjust_shadows(img, amount),
+            Operation::Curves { red_gamma, green_gamma, blue_gamma } => {
+                adjustments::adjust_curves(img, red_gamma, green_gamma, blue_gamma)
+            }
+            Operation::ToneCurve(ref curves) => adjustments::apply_tone_curves(img, curves),
+            Operation::Levels { black_point, white_point, gamma } => {
+                adjustments::adjust_levels(img, black_point, white_point, gamma)
+            }
+            Operation::ChannelLevels(ref levels) => adjustments::apply_levels(img, levels),
+            Operation::AutoLevels { clip_percent } => adjustments::auto_levels(img, clip_percent).map(|_| ()),
+            Operation::HistogramEqualization => adjustments::histogram_equalization(img),
+            Operation::Lut { ref cube, interpolation, intensity } => adjustments::apply_lut(img, cube, interpolation, intensity),
+            Operation::Clahe(ref options) => adjustments::clahe(img, options),
+            Operation::Grayscale => filters::to_grayscale(img),
+            Operation::Sepia => filters::apply_sepia(img),
+            Operation::GaussianBlur { sigma } => filters::gaussian_blur(img, sigma),
+            Operation::Sharpen { amount, border } => filters::sharpen_with(img, amount, border),
+            Operation::UnsharpMask(ref options) => filters::unsharp_mask(img, options),
+            Operation::Vignette { strength, radius } => filters::apply_vignette(img, strength, radius),
+            Operation::ReduceNoise { strength, border } => filters::reduce_noise_with(img, strength, border),
+            Operation::Denoise(ref options) => filters::denoise(img, options),
+            Operation::Emboss { border } => filters::apply_emboss_with(img, border),
+            Operation::Convolve(ref options) => filters::convolve(img, options),
+            Operation::Rotate { angle } => replace(img, transforms::rotate(img, angle)),
+            Operation::RotateArbitrary { angle, background, interpolation, auto_crop } => {
+                let options = RotateOptions {
+                    interpolation,
+                    background: background.map_or(transforms::TRANSPARENT, Rgba),
+                    auto_crop,
+                };
+                replace(img, transforms::rotate_arbitrary(img, angle, &options))
+            }
+            Operation::FlipHorizontal => replace(img, transforms::flip_horizontal(img)),
+            Operation::FlipVertical => replace(img, transforms::flip_vertical(img)),
+            Operation::Resize { width, height } => replace(img, transforms::resize(img, width, height)),
+            Operation::Crop { x, y, width, height } => replace(img, transforms::crop(img, x, y, width, height)),
+        }
+    }
+}
+
+fn replace<P: EditorPixel>(img: &mut Image<P>, result: Result<Image<P>, EditorError>) -> Result<(), EditorError> {
+    *img = result?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adjustments::ChannelLevels;
+    use crate::filters::{Channels, ConvolutionKernel};
+    use crate::lut::{Domain, Lut3d};
+    use image::{Rgba32FImage, RgbaImage};
+
+    fn create_test_image() -> RgbaImage {
+        RgbaImage::from_fn(40, 30, |x, y| {
+            Rgba([(x * 6) as u8, (y * 8) as u8, 100, 255])
+        })
+    }
+
+    fn invalid_parameter(result: Result<Pipeline, EditorError>) -> String {
+        match result {
+            Err(EditorError::InvalidParameter { parameter, .. }) => parameter,
+            other => panic!("expected InvalidParameter, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_and_execute() {
+        let pipeline = Pipeline::from_json(
+            r#"{
+                "version": 2,
+                "operations": [
+                    { "op": "brightness", "value": 10 },
+                    { "op": "levels", "black_point": 5, "white_point": 250, "gamma": 1.2 },
+                    { "op": "rotate", "angle": 90 },
+                    { "op": "crop", "x": 0, "y": 0, "width": 20, "height": 10 }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(pipeline.operations.len(), 4);
+
+        let mut img = create_test_image();
+        pipeline.execute(&mut img).unwrap();
+        assert_eq!(img.dimensions(), (20, 10));
+    }
+
+    #[test]
+    fn test_matches_sequential_ops() {
+        let pipeline = Pipeline {
+            version: PIPELINE_VERSION,
+            operations: vec![
+                Operation::Contrast { value: 1.3 },
+                Operation::Sepia,
+                Operation::FlipHorizontal,
+            ],
+        };
+
+        let mut expected = create_test_image();
+        adjustments::adjust_contrast(&mut expected, 1.3).unwrap();
+        filters::apply_sepia(&mut expected).unwrap();
+        let expected = transforms::flip_horizontal(&expected).unwrap();
+
+        let mut result = create_test_image();
+        pipeline.execute(&mut result).unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_compile_fuses_point_runs() {
+        let sliders = Pipeline::from_json(
+            r#"{
+                "version": 2,
+                "operations": [
+                    { "op": "exposure", "stops": 0.3 },
+                    { "op": "contrast", "value": 10 },
+                    { "op": "highlights", "amount": -20 },
+                    { "op": "shadows", "amount": 25 },
+                    { "op": "color_temperature", "temperature": 5800, "tint": 4 },
+                    { "op": "vibrance", "amount": 20 },
+                    { "op": "saturation", "value": 60, "model": "oklch" },
+                    { "op": "levels", "black_point": 4, "white_point": 250, "gamma": 1.05 },
+                    { "op": "brightness", "value": 5 },
+                    { "op": "curves", "red_gamma": 1.05, "green_gamma": 1.0, "blue_gamma": 0.95 },
+                    { "op": "hue", "shift": 4 },
+                    { "op": "tone_curve", "master": [[0, 0], [64, 56], [192, 200], [255, 255]] },
+                    { "op": "gaussian_blur", "sigma": 1.0 },
+                    { "op": "sepia" },
+                    { "op": "flip_horizontal" },
+                    { "op": "brightness", "value": 10 }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let compiled = sliders.compile().unwrap();
+        // exposure to color_temperature, vibrance, saturation, levels to
+        // tone_curve and the four steps after the blur
+        assert_eq!(compiled.passes(), 8);
+
+        let source = Rgba32FImage::from_fn(40, 30, |x, y| Rgba([x as f32 / 39.0, y as f32 / 29.0, ((x * y) % 40) as f32 / 39.0, 1.0]));
+        let mut expected = source.clone();
+        sliders.execute(&mut expected).unwrap();
+        let mut fused = source;
+        compiled.execute(&mut fused).unwrap();
+        let differences: Vec<f32> = fused.pixels().zip(expected.pixels()).flat_map(|(a, b)| (0..4).map(move |c| (a[c] - b[c]).abs() * 255.0)).collect();
+        let mean = differences.iter().sum::<f32>() / differences.len() as f32;
+        let worst = differences.iter().fold(0.0, |worst: f32, &d| worst.max(d));
+        let over = differences.iter().filter(|&&d| d > 1.0).count() as f32 / differences.len() as f32;
+        // Vibrance and the OKLCh boost run between the fused runs, which stay close to the separate passes
+        assert!(mean < 0.05 && over < 0.01 && worst <= crate::lut::FUSED_CUBE_TOLERANCE, "{} {} {}", mean, worst, over);
+
+        // Separate 8-bit passes round after every step, the fused curves only once
+        let tones = Pipeline::from_json(
+            r#"{
+                "operations": [
+                    { "op": "exposure", "stops": 0.3 },
+                    { "op": "contrast", "value": 10 },
+                    { "op": "levels", "black_point": 4, "white_point": 250, "gamma": 1.05 },
+                    { "op": "brightness", "value": 5 },
+                    { "op": "curves", "red_gamma": 1.05, "green_gamma": 1.0, "blue_gamma": 0.95 }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let compiled = tones.compile().unwrap();
+        assert_eq!(compiled.passes(), 1);
+        let mut expected = create_test_image();
+        tones.execute(&mut expected).unwrap();
+        let mut fused = create_test_image();
+        compiled.execute(&mut fused).unwrap();
+        assert!(fused.pixels().zip(expected.pixels()).all(|(a, b)| (0..4).all(|c| a[c].abs_diff(b[c]) <= 1)));
+
+        let invalid = Pipeline {
+            version: PIPELINE_VERSION,
+            operations: vec![Operation::Brightness { value: 5 }, Operation::Levels { black_point: 9, white_point: 9, gamma: 1.0 }],
+        };
+        assert_eq!(invalid.compile().unwrap_err().parameter(), Some("operations[1].black_point"));
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let pipeline = Pipeline {
+            version: PIPELINE_VERSION,
+            operations: vec![
+                Operation::Exposure { stops: 0.5 },
+                Operation::Curves { red_gamma: 1.1, green_gamma: 1.0, blue_gamma: 0.9 },
+                Operation::ToneCurve(ToneCurves { red: vec![[0.0, 10.0], [255.0, 250.0]], ..ToneCurves::default() }),
+                Operation::ChannelLevels(Levels { blue: ChannelLevels { output_black: 20, ..ChannelLevels::default() }, ..Levels::default() }),
+                Operation::AutoLevels { clip_percent: 0.5 },
+                Operation::ColorTemperature(WhiteBalance { temperature: 3200.0, tint: -4.0 }),
+                Operation::Clahe(ClaheOptions { tiles_x: 4, ..ClaheOptions::default() }),
+                Operation::UnsharpMask(UnsharpMaskOptions { radius: 2.5, luminance_only: true, ..UnsharpMaskOptions::default() }),
+                Operation::Sharpen { amount: 0.5, border: BorderMode::Constant([0, 0, 0, 255]) },
+                Operation::Emboss { border: BorderMode::Wrap },
+                Operation::Denoise(DenoiseOptions { chroma: 80.0, ..DenoiseOptions::default() }),
+                Operation::Convolve(ConvolveOptions {
+                    bias: 128.0,
+                    channels: Channels::RGBA,
+                    ..ConvolveOptions::new(ConvolutionKernel::MotionBlur { length: 9, angle: 30.0 })
+                }),
+                Operation::Grayscale,
+                Operation::Lut {
+                    cube: CubeLut { title: Some("Identity".to_string()), shaper: None, cube: Some((Lut3d::identity(2), Domain::default())) },
+                    interpolation: LutInterpolation::Trilinear,
+                    intensity: 60.0,
+                },
+            ],
+        };
+
+        assert_eq!(Pipeline::from_json(&pipeline.to_json()).unwrap(), pipeline);
+
+        // Border modes are optional
+        let emboss = Pipeline::from_json(r#"{ "operations": [{ "op": "emboss" }] }"#).unwrap();
+        assert_eq!(emboss.operations, [Operation::Emboss { border: BorderMode::Clamp }]);
+    }
+
+    #[test]
+    fn test_rejects_invalid_pipelines() {
+        assert!(matches!(
+            Pipeline::from_json(r#"{ "operations": [{ "op": "swirl" }] }"#),
+            Err(EditorError::InvalidPipeline(_))
+        ));
+        assert_eq!(
+            Pipeline::from_json(r#"{ "version": 99, "operations": [] }"#),
+            Err(EditorError::UnsupportedPipelineVersion(99))
+        );
+        assert_eq!(
+            invalid_parameter(Pipeline::from_json(
+                r#"{ "operations": [{ "op": "sepia" }, { "op": "rotate", "angle": 45 }] }"#
+            )),
+            "operations[1].angle"
+        );
+        assert_eq!(
+            invalid_parameter(Pipeline::from_json(
+                r#"{ "operations": [{ "op": "levels", "black_point": 200, "white_point": 100, "gamma": 1.0 }] }"#
+            )),
+            "operations[0].black_point"
+        );
+        assert_eq!(
+            invalid_parameter(Pipeline::from_json(
+                r#"{ "operations": [{ "op": "tone_curve", "green": [[10, 0], [10, 255]] }] }"#
+            )),
+            "operations[0].green"
+        );
+    }
+
+    #[test]
+    fn test_version_1_saturation_is_hue_rotation() {
+        let json = r#"{ "version": 1, "operations": [{ "op": "saturation", "value": 120 }] }"#;
+        let pipeline = Pipeline::from_json(json).unwrap();
+        assert_eq!(pipeline.version, PIPELINE_VERSION);
+        assert_eq!(pipeline.operations, vec![Operation::HueRotate { degrees: 120.0 }]);
+
+        let json = r#"{ "version": 2, "operations": [{ "op": "saturation", "value": 20, "model": "hsl" }] }"#;
+        let pipeline = Pipeline::from_json(json).unwrap();
+        assert_eq!(pipeline.operations, vec![Operation::Saturation { value: 20.0, model: SaturationModel::Hsl }]);
+
+        // Presets from before versioning are version 1
+        let pipeline = Pipeline::from_json(r#"{ "operations": [{ "op": "saturation", "value": -150 }] }"#).unwrap();
+        assert_eq!(pipeline.operations, vec![Operation::HueRotate { degrees: -150.0 }]);
+
+        // Pipelines built by hand are upgraded when they run
+        let old = Pipeline { version: 1, operations: vec![Operation::Saturation { value: 150.0, model: SaturationModel::Oklch }] };
+        assert!(old.validate().is_ok());
+        let mut expected = create_test_image();
+        adjustments::rotate_hue(&mut expected, 150.0).unwrap();
+        let mut img = create_test_image();
+        old.execute(&mut img).unwrap();
+        assert_eq!(img, expected);
+        let mut img = create_test_image();
+        old.compile().unwrap().execute(&mut img).unwrap();
+        assert_eq!(img, expected);
+    }
+
+    #[test]
+    fn test_worker_pipeline_versions() {
+        // What public/imageWorker.js sends for brightness 10, contrast 5 and saturation 120
+        let worker = |version: u32| {
+            format!(
+                r#"{{ "version": {}, "operations": [
+                    {{ "op": "brightness", "value": 10 }},
+                    {{ "op": "contrast", "value": 0.5 }},
+                    {{ "op": "saturation", "value": 12 }}
+                ] }}"#,
+                version
+            )
+        };
+        let mut base = create_test_image();
+        adjustments::adjust_brightness(&mut base, 10).unwrap();
+        adjustments::adjust_contrast(&mut base, 0.5).unwrap();
+
+        let mut img = create_test_image();
+        Pipeline::from_json(&worker(2)).unwrap().execute(&mut img).unwrap();
+        let mut expected = base.clone();
+        adjustments::adjust_saturation(&mut expected, 12.0).unwrap();
+        assert_eq!(img, expected);
+
+        let mut img = create_test_image();
+        Pipeline::from_json(&worker(1)).unwrap().execute(&mut img).unwrap();
+        let mut expected = base;
+        adjustments::rotate_hue(&mut expected, 12.0).unwrap();
+        assert_eq!(img, expected);
+    }
+
+    #[test]
+    fn test_crop_out_of_bounds_fails_at_runtime() {
+        let pipeline = Pipeline::from_json(
+            r#"{ "operations": [{ "op": "crop", "x": 30, "y": 0, "width": 20, "height": 10 }] }"#,
+        )
+        .unwrap();
+
+        let error = pipeline.execute(&mut create_test_image()).unwrap_err();
+        assert_eq!(error.code(), "INVALID_PARAMETER");
+        assert_eq!(error.parameter(), Some("operations[0].x"));
+    }
+}
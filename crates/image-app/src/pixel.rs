@@ -0,0 +1,308 @@
+//! Pixel formats accepted by the editing functions
+//!
+//! Adjustments, filters and transforms are generic over [`EditorPixel`], so the
+//! same code edits 8-bit [`RgbaImage`] buffers and high-precision
+//! [`Rgba32FImage`] working buffers. Both hold sRGB-encoded, straight-alpha
+//! colors; the float buffer stores them as 0.0-1.0 without rounding between
+//! steps, so a chain of edits does not band. [`crate::EditorImage`] and pipelines
+//! edit in float and only quantize when an 8-bit file is encoded, using ordered
+//! dithering so smooth gradients stay smooth.
+//!
+//! Per-pixel edits run row-parallel (see [`crate::parallel`]). With the `simd`
+//! feature, color matrices on float buffers (grayscale, sepia, hue rotation,
+//! white balance, color temperature) also use 8-lane SIMD. Per-channel tone
+//! edits (brightness, contrast, exposure, levels, curves) and edits in HSV or
+//! OKLCh (hue, vibrance, saturation) have no SIMD lanes. Results are the same
+//! with or without the feature, up to float rounding.
+
+use std::borrow::Cow;
+
+use image::{DynamicImage, ImageBuffer, Pixel, Rgba, Rgba32FImage, RgbaImage};
+
+use crate::color::{from_linear, linear_to_srgb_fast, matrix_apply, srgb_to_linear_fast, to_linear, Matrix3};
+use crate::parallel::for_each_chunk;
+
+/// Image buffer with the pixel type `P`
+pub type Image<P> = ImageBuffer<P, Vec<<P as Pixel>::Subpixel>>;
+
+/// 16-bit RGBA buffer handed to the 16-bit encoders
+pub type Rgba16Image = ImageBuffer<Rgba<u16>, Vec<u16>>;
+
+/// Affine color transform: each row holds the R, G and B weights and an offset
+/// of one output channel
+pub type ColorMatrix = [[f32; 4]; 3];
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for image::Rgba<u8> {}
+    impl Sealed for image::Rgba<f32> {}
+}
+
+/// RGBA pixel type the editing functions work on: `Rgba<u8>` or `Rgba<f32>`
+pub trait EditorPixel: Pixel<Subpixel: Send + Sync> + Send + Sync + sealed::Sealed + 'static {
+    /// Entries in the tone lookup tables built for this format: one per level
+    /// for 8-bit, interpolated for float
+    const TONE_LUT_SIZE: usize;
+
+    /// Normalized straight-alpha color (0.0-1.0)
+    fn to_unit(self) -> [f32; 4];
+
+    /// Pixel from a normalized color, clamped to 0.0-1.0
+    fn from_unit(rgba: [f32; 4]) -> Self;
+
+    /// Convert a decoded image into this format
+    fn from_dynamic(img: DynamicImage) -> Image<Self>;
+
+    /// Apply `tone(channel, value)` to the R, G and B channels
+    ///
+    /// 8-bit buffers evaluate it once per level through a lookup table.
+    fn map_tone(img: &mut Image<Self>, tone: impl Fn(usize, f32) -> f32 + Sync);
+
+    /// Apply an affine [`ColorMatrix`] to the encoded color channels
+    ///
+    /// Matrices that only scale and offset each channel go through
+    /// [`EditorPixel::map_tone`].
+    fn map_color_matrix(img: &mut Image<Self>, matrix: &ColorMatrix) {
+        scalar_color_matrix(img, matrix);
+    }
+
+    /// Apply a 3x3 matrix to the color channels in linear light
+    ///
+    /// Diagonal matrices, such as exposure, go through [`EditorPixel::map_tone`].
+    fn map_linear_matrix(img: &mut Image<Self>, matrix: &Matrix3) {
+        scalar_linear_matrix(img, matrix);
+    }
+
+    /// 8-bit copy for encoders, dithered when precision is lost
+    fn to_rgba8(img: &Image<Self>) -> Cow<'_, RgbaImage>;
+
+    /// 16-bit copy for encoders
+    fn to_rgba16(img: &Image<Self>) -> Rgba16Image;
+}
+
+impl EditorPixel for Rgba<u8> {
+    const TONE_LUT_SIZE: usize = 256;
+
+    fn to_unit(self) -> [f32; 4] {
+        self.0.map(|channel| channel as f32 / 255.0)
+    }
+
+    fn from_unit(rgba: [f32; 4]) -> Self {
+        Rgba(rgba.map(unit_to_u8))
+    }
+
+    fn from_dynamic(img: DynamicImage) -> RgbaImage {
+        img.into_rgba8()
+    }
+
+    fn map_tone(img: &mut RgbaImage, tone: impl Fn(usize, f32) -> f32 + Sync) {
+        let mut luts = [[0u8; 256]; 3];
+        for (channel, lut) in luts.iter_mut().enumerate() {
+            for (level, entry) in lut.iter_mut().enumerate() {
+                *entry = unit_to_u8(tone(channel, level as f32 / 255.0));
+            }
+        }
+
+        for_each_row(img, |row| {
+            for pixel in row.chunks_exact_mut(4) {
+                pixel[0] = luts[0][pixel[0] as usize];
+                pixel[1] = luts[1][pixel[1] as usize];
+                pixel[2] = luts[2][pixel[2] as usize];
+            }
+        });
+    }
+
+    fn to_rgba8(img: &RgbaImage) -> Cow<'_, RgbaImage> {
+        Cow::Borrowed(img)
+    }
+
+    fn to_rgba16(img: &RgbaImage) -> Rgba16Image {
+        Rgba16Image::from_fn(img.width(), img.height(), |x, y| {
+            Rgba(img.get_pixel(x, y).0.map(|channel| channel as u16 * 257))
+        })
+    }
+}
+
+impl EditorPixel for Rgba<f32> {
+    const TONE_LUT_SIZE: usize = 4096;
+
+    fn to_unit(self) -> [f32; 4] {
+        self.0
+    }
+
+    fn from_unit(rgba: [f32; 4]) -> Self {
+        Rgba(rgba.map(|channel| channel.clamp(0.0, 1.0)))
+    }
+
+    fn from_dynamic(img: DynamicImage) -> Rgba32FImage {
+        img.into_rgba32f()
+    }
+
+    fn map_tone(img: &mut Rgba32FImage, tone: impl Fn(usize, f32) -> f32 + Sync) {
+        for_each_row(img, |row| {
+            for pixel in row.chunks_exact_mut(4) {
+                for (channel, value) in pixel.iter_mut().take(3).enumerate() {
+                    *value = tone(channel, *value).clamp(0.0, 1.0);
+                }
+            }
+        });
+    }
+
+    #[cfg(feature = "simd")]
+    fn map_color_matrix(img: &mut Rgba32FImage, matrix: &ColorMatrix) {
+        // Per-channel scaling already vectorizes well in the tone loop
+        if is_diagonal(matrix) {
+            scalar_color_matrix(img, matrix);
+        } else {
+            for_each_row(img, |row| crate::simd::color_matrix(row, matrix));
+        }
+    }
+
+    #[cfg(feature = "simd")]
+    fn map_linear_matrix(img: &mut Rgba32FImage, matrix: &Matrix3) {
+        if is_diagonal(matrix) {
+            scalar_linear_matrix(img, matrix);
+        } else {
+            for_each_row(img, |row| crate::simd::linear_matrix(row, matrix));
+        }
+    }
+
+    fn to_rgba8(img: &Rgba32FImage) -> Cow<'_, RgbaImage> {
+        Cow::Owned(dither_to_rgba8(img))
+    }
+
+    fn to_rgba16(img: &Rgba32FImage) -> Rgba16Image {
+        Rgba16Image::from_fn(img.width(), img.height(), |x, y| {
+            Rgba(img.get_pixel(x, y).0.map(|channel| (channel.clamp(0.0, 1.0) * 65535.0).round() as u16))
+        })
+    }
+}
+
+fn unit_to_u8(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Default [`EditorPixel::map_color_matrix`], one pixel at a time
+pub(crate) fn scalar_color_matrix<P: EditorPixel>(img: &mut Image<P>, matrix: &ColorMatrix) {
+    if is_diagonal(matrix) {
+        P::map_tone(img, |channel, value| matrix[channel][channel] * value + matrix[channel][3]);
+    } else {
+        map_pixels(img, |[r, g, b, a]| {
+            let [r, g, b] = matrix.map(|[wr, wg, wb, offset]| wr * r + wg * g + wb * b + offset);
+            [r, g, b, a]
+        });
+    }
+}
+
+/// Default [`EditorPixel::map_linear_matrix`], one pixel at a time
+pub(crate) fn scalar_linear_matrix<P: EditorPixel>(img: &mut Image<P>, matrix: &Matrix3) {
+    if is_diagonal(matrix) {
+        P::map_tone(img, |channel, value| linear_to_srgb_fast(srgb_to_linear_fast(value) * matrix[channel][channel]));
+    } else {
+        map_pixels(img, |pixel| {
+            let [r, g, b, a] = to_linear(pixel);
+            let [r, g, b] = matrix_apply(matrix, [r, g, b]);
+            from_linear([r, g, b, a])
+        });
+    }
+}
+
+/// Replace every pixel with `f(rgba)`, working on normalized colors
+pub(crate) fn map_pixels<P: EditorPixel>(img: &mut Image<P>, f: impl Fn([f32; 4]) -> [f32; 4] + Sync) {
+    map_pixels_at(img, |_, _, rgba| f(rgba));
+}
+
+/// Replace every pixel with `f(x, y, rgba)`, working on normalized colors
+pub(crate) fn map_pixels_at<P: EditorPixel>(img: &mut Image<P>, f: impl Fn(u32, u32, [f32; 4]) -> [f32; 4] + Sync) {
+    let width = img.width() as usize;
+    if width == 0 {
+        return;
+    }
+    for_each_chunk(img, width * 4, |y, row| {
+        for (x, channels) in row.chunks_exact_mut(4).enumerate() {
+            let pixel = P::from_slice_mut(channels);
+            *pixel = P::from_unit(f(x as u32, y as u32, pixel.to_unit()));
+        }
+    });
+}
+
+/// Run `f` on the channels of every row, in parallel where threads are available
+fn for_each_row<S: Send>(img: &mut ImageBuffer<impl Pixel<Subpixel = S>, Vec<S>>, f: impl Fn(&mut [S]) + Sync) {
+    let row_len = img.width() as usize * 4;
+    if row_len > 0 {
+        for_each_chunk(img, row_len, |_, row| f(row));
+    }
+}
+
+/// Whether a matrix only scales each channel (offsets aside)
+fn is_diagonal<const N: usize>(matrix: &[[f32; N]; 3]) -> bool {
+    (0..3).all(|row| (0..3).all(|column| row == column || matrix[row][column] == 0.0))
+}
+
+/// Convert between pixel formats, rounding when narrowing to 8 bits
+pub fn convert<P: EditorPixel, Q: EditorPixel>(img: &Image<P>) -> Image<Q> {
+    Image::<Q>::from_fn(img.width(), img.height(), |x, y| Q::from_unit(img.get_pixel(x, y).to_unit()))
+}
+
+/// 8x8 Bayer matrix, thresholds 0-63
+const BAYER_8X8: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// Quantize a float image to 8 bits with ordered dithering
+///
+/// The dither offset stays below half a level, so colors that are already exact
+/// 8-bit values come out unchanged and only in-between values are spread over
+/// the two nearest levels. Alpha is rounded.
+pub fn dither_to_rgba8(img: &Rgba32FImage) -> RgbaImage {
+    RgbaImage::from_fn(img.width(), img.height(), |x, y| {
+        let threshold = BAYER_8X8[(y % 8) as usize][(x % 8) as usize];
+        let offset = (threshold as f32 + 0.5) / 64.0 - 0.5;
+        let [r, g, b, a] = img.get_pixel(x, y).0;
+        let dither = |value: f32| (value.clamp(0.0, 1.0) * 255.0 + offset).round().clamp(0.0, 255.0) as u8;
+        Rgba([dither(r), dither(g), dither(b), unit_to_u8(a)])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dither_keeps_exact_levels() {
+        let source = RgbaImage::from_fn(16, 16, |x, y| Rgba([(x * 16) as u8, (y * 16) as u8, 255, 128]));
+        assert_eq!(dither_to_rgba8(&convert(&source)), source);
+    }
+
+    #[test]
+    fn test_dither_preserves_average() {
+        // A level a quarter of the way between 100 and 101 is spread over both
+        let value = 100.25 / 255.0;
+        let flat = Rgba32FImage::from_pixel(64, 64, Rgba([value, value, value, 1.0]));
+        let dithered = dither_to_rgba8(&flat);
+
+        let mean = dithered.pixels().map(|pixel| pixel[0] as f32).sum::<f32>() / (64.0 * 64.0);
+        assert!((mean - 100.25).abs() < 0.01, "{}", mean);
+        assert!(dithered.pixels().all(|pixel| pixel[0] == 100 || pixel[0] == 101));
+    }
+
+    #[test]
+    fn test_map_tone_matches_between_formats() {
+        let source = RgbaImage::from_fn(16, 16, |x, y| Rgba([(x * 16) as u8, (y * 16) as u8, 64, 255]));
+        let tone = |channel: usize, value: f32| value.powf(1.0 / (1.0 + channel as f32));
+
+        let mut narrow = source.clone();
+        <Rgba<u8>>::map_tone(&mut narrow, tone);
+        let mut wide: Rgba32FImage = convert(&source);
+        <Rgba<f32>>::map_tone(&mut wide, tone);
+        assert_eq!(convert::<_, Rgba<u8>>(&wide), narrow);
+    }
+}
@@ -0,0 +1,121 @@
+//! 8-lane SIMD kernels for float buffers
+//!
+//! Rows of interleaved RGBA are processed eight pixels at a time: the channels
+//! are gathered into one [`f32x8`] each, transformed together and scattered
+//! back. Pixels left over at the end of a row take the scalar path with the
+//! same math, so results do not depend on the image width.
+//!
+//! The sRGB transfer curves read the same tables as
+//! [`crate::color::srgb_to_linear_fast`] and [`crate::color::linear_to_srgb_fast`]:
+//! positions and blends are computed in lanes, only the table reads are
+//! scalar. Results match the scalar path up to float rounding, whether or not
+//! the feature is enabled. The lanes pay off once they map to real vector
+//! registers (`-C target-cpu` with AVX2, or `+simd128` on WebAssembly).
+
+use wide::f32x8;
+
+use crate::color::{decode_table, encode_table, linear_to_srgb_fast, srgb_to_linear_fast, Matrix3};
+use crate::pixel::ColorMatrix;
+
+const LANES: usize = 8;
+
+/// Apply an affine color matrix to one row of RGBA channels
+pub(crate) fn color_matrix(row: &mut [f32], matrix: &ColorMatrix) {
+    let weights = matrix.map(|row| row.map(f32x8::splat));
+    let mut blocks = row.chunks_exact_mut(4 * LANES);
+    for block in &mut blocks {
+        let [r, g, b] = gather(block);
+        let [r, g, b] = weights.map(|[wr, wg, wb, offset]| wr * r + wg * g + wb * b + offset);
+        scatter(block, [r, g, b].map(clamp_unit));
+    }
+    for pixel in blocks.into_remainder().chunks_exact_mut(4) {
+        let [r, g, b] = [pixel[0], pixel[1], pixel[2]];
+        let rgb = matrix.map(|[wr, wg, wb, offset]| (wr * r + wg * g + wb * b + offset).clamp(0.0, 1.0));
+        pixel[..3].copy_from_slice(&rgb);
+    }
+}
+
+/// Apply a 3x3 matrix in linear light to one row of sRGB-encoded RGBA channels
+pub(crate) fn linear_matrix(row: &mut [f32], matrix: &Matrix3) {
+    let weights = matrix.map(|row| row.map(f32x8::splat));
+    let mut blocks = row.chunks_exact_mut(4 * LANES);
+    for block in &mut blocks {
+        let [r, g, b] = gather(block).map(decode);
+        let [r, g, b] = weights.map(|[wr, wg, wb]| wr * r + wg * g + wb * b);
+        scatter(block, [r, g, b].map(encode));
+    }
+    for pixel in blocks.into_remainder().chunks_exact_mut(4) {
+        let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(srgb_to_linear_fast);
+        let rgb = matrix.map(|[wr, wg, wb]| linear_to_srgb_fast(wr * r + wg * g + wb * b));
+        pixel[..3].copy_from_slice(&rgb);
+    }
+}
+
+/// R, G and B of eight interleaved pixels
+fn gather(block: &[f32]) -> [f32x8; 3] {
+    std::array::from_fn(|channel| f32x8::new(std::array::from_fn(|lane| block[lane * 4 + channel])))
+}
+
+/// Write R, G and B back, leaving alpha untouched
+fn scatter(block: &mut [f32], channels: [f32x8; 3]) {
+    for (channel, values) in channels.iter().enumerate() {
+        for (lane, value) in values.to_array().into_iter().enumerate() {
+            block[lane * 4 + channel] = value;
+        }
+    }
+}
+
+fn clamp_unit(value: f32x8) -> f32x8 {
+    value.max(f32x8::ZERO).min(f32x8::ONE)
+}
+
+/// [`srgb_to_linear_fast`] on eight values
+fn decode(value: f32x8) -> f32x8 {
+    interpolate(decode_table(), clamp_unit(value))
+}
+
+/// [`linear_to_srgb_fast`] on eight values
+fn encode(value: f32x8) -> f32x8 {
+    interpolate(encode_table(), clamp_unit(value).sqrt())
+}
+
+/// Linear interpolation into a table sampled evenly over 0.0-1.0
+fn interpolate(table: &[f32], value: f32x8) -> f32x8 {
+    let position = value * f32x8::splat((table.len() - 1) as f32);
+    let lower = position.to_array().map(|position| (position as usize).min(table.len() - 2));
+    let t = position - f32x8::new(lower.map(|index| index as f32));
+    let low = f32x8::new(lower.map(|index| table[index]));
+    let high = f32x8::new(lower.map(|index| table[index + 1]));
+    low + (high - low) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixel::{scalar_color_matrix, scalar_linear_matrix};
+    use image::{Rgba, Rgba32FImage};
+
+    #[test]
+    fn test_lanes_match_scalar_path() {
+        // 11 x 3 pixels: whole blocks and a scalar remainder, values outside 0.0-1.0 included
+        let source = Rgba32FImage::from_fn(11, 3, |x, y| {
+            let level = |seed: u32| (seed * 7 % 23) as f32 / 20.0 - 0.05;
+            Rgba([level(x + y * 11), level(x * 3 + y + 5), level(x + y * 2 + 9), 0.5])
+        });
+        let matrix = [[0.9, 0.2, -0.1], [0.05, 1.1, 0.0], [0.0, -0.2, 1.3]];
+        let affine = [[0.5, 0.2, 0.0, 0.1], [0.0, 1.0, 0.1, 0.0], [0.3, 0.3, 0.3, -0.05]];
+
+        // The scalar path is what builds without the `simd` feature run; both read the same tables
+        let mut expected = source.clone();
+        scalar_linear_matrix(&mut expected, &matrix);
+        let mut lanes = source.clone();
+        linear_matrix(&mut lanes, &matrix);
+        assert!(expected.iter().zip(lanes.iter()).all(|(a, b)| (a - b).abs() <= 1e-6), "{:?} {:?}", expected, lanes);
+
+        let mut expected = source.clone();
+        scalar_color_matrix(&mut expected, &affine);
+        let mut lanes = source;
+        color_matrix(&mut lanes, &affine);
+        assert!(expected.iter().zip(lanes.iter()).all(|(a, b)| (a - b).abs() <= 1e-6), "{:?} {:?}", expected, lanes);
+    }
+}
@@ -0,0 +1,267 @@
+//! Image statistics
+//!
+//! [`image_statistics`] measures R, G, B and luminance in one pass: a 256-bin
+//! luminance histogram, min/max/mean/median/standard deviation and chosen
+//! percentiles per channel, and how many pixels clip to black or white.
+//! Values are on the 8-bit scale (0.0-255.0) for every pixel format;
+//! luminance is the sRGB-encoded Rec.709 luminance of the linear color.
+//!
+//! The measurement can be limited to a region and, for speed on large images,
+//! to an evenly spaced subset of its pixels:
+//!
+//! ```json
+//! { "region": { "x": 100, "y": 50, "width": 400, "height": 300 }, "max_pixels": 250000, "percentiles": [1, 50, 99] }
+//! ```
+//!
+//! Fully transparent pixels are not counted.
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::luma;
+use crate::error::{ensure_finite, EditorError};
+use crate::pixel::{EditorPixel, Image};
+use crate::transforms::check_area;
+
+/// Bins behind the median and percentiles, fine enough for float images
+const BINS: usize = 4096;
+
+/// Rectangle of an image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// What [`image_statistics`] measures
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatisticsOptions {
+    /// Area to measure, the whole image when omitted
+    pub region: Option<Region>,
+    /// Measure at most about this many pixels, every pixel when omitted
+    pub max_pixels: Option<u64>,
+    /// Percentiles to report per channel (0 to 100)
+    pub percentiles: Vec<f32>,
+}
+
+impl Default for StatisticsOptions {
+    fn default() -> StatisticsOptions {
+        StatisticsOptions {
+            region: None,
+            max_pixels: None,
+            percentiles: vec![1.0, 5.0, 25.0, 50.0, 75.0, 95.0, 99.0],
+        }
+    }
+}
+
+impl StatisticsOptions {
+    /// Parse options from JSON
+    pub fn from_json(json: &str) -> Result<StatisticsOptions, EditorError> {
+        let options: StatisticsOptions =
+            serde_json::from_str(json).map_err(|e| EditorError::invalid_parameter("statistics", e.to_string()))?;
+        options.validate()?;
+        Ok(options)
+    }
+
+    /// Check the options that do not depend on the image
+    pub fn validate(&self) -> Result<(), EditorError> {
+        if self.max_pixels == Some(0) {
+            return Err(EditorError::invalid_parameter("max_pixels", "must be at least 1"));
+        }
+        for &percentile in &self.percentiles {
+            ensure_finite("percentiles", percentile)?;
+            if !(0.0..=100.0).contains(&percentile) {
+                return Err(EditorError::invalid_parameter("percentiles", "must be between 0 and 100"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Statistics of one channel, on the 8-bit scale
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct ChannelStatistics {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    pub median: f32,
+    pub std_dev: f32,
+    /// Values at the requested percentiles, in the order requested
+    pub percentiles: Vec<f32>,
+}
+
+/// Result of [`image_statistics`]
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct ImageStatistics {
+    /// Pixels measured
+    pub pixel_count: u64,
+    /// Distance between measured pixels in both directions, 1 when every pixel counts
+    pub sample_step: u32,
+    /// 256 bins of 8-bit luminance
+    pub luminance_histogram: Vec<u32>,
+    pub red: ChannelStatistics,
+    pub green: ChannelStatistics,
+    pub blue: ChannelStatistics,
+    pub luminance: ChannelStatistics,
+    /// Pixels with at least one channel at black (level 0)
+    pub clipped_shadows: u64,
+    /// Pixels with at least one channel at white (level 255)
+    pub clipped_highlights: u64,
+}
+
+/// Measure an image
+///
+/// # Arguments
+/// * `img` - Image to measure
+/// * `options` - Region, sampling and percentiles (see [`StatisticsOptions`])
+///
+/// # Returns
+/// Histogram, per-channel statistics and clipping counts
+pub fn image_statistics<P: EditorPixel>(img: &Image<P>, options: &StatisticsOptions) -> Result<ImageStatistics, EditorError> {
+    options.validate()?;
+    let region = options.region.unwrap_or(Region { x: 0, y: 0, width: img.width(), height: img.height() });
+    if options.region.is_some() {
+        check_area("region", img.dimensions(), region.x, region.y, region.width, region.height)
+            .map_err(|e| e.within("region"))?;
+    }
+
+    let step = sample_step(region.width as u64 * region.height as u64, options.max_pixels.unwrap_or(u64::MAX));
+    let mut channels: [Accumulator; 4] = std::array::from_fn(|_| Accumulator::new());
+    let mut statistics = ImageStatistics { sample_step: step, luminance_histogram: vec![0; 256], ..ImageStatistics::default() };
+
+    for y in (region.y..region.y + region.height).step_by(step as usize) {
+        for x in (region.x..region.x + region.width).step_by(step as usize) {
+            let pixel = img.get_pixel(x, y).to_unit();
+            if pixel[3] <= 0.0 {
+                continue;
+            }
+            let [r, g, b, _] = pixel.map(|value| value.clamp(0.0, 1.0));
+            let luma = luma([r, g, b, 1.0]);
+
+            statistics.pixel_count += 1;
+            statistics.luminance_histogram[(luma * 255.0).round() as usize] += 1;
+            if [r, g, b].iter().any(|&value| value * 255.0 < 0.5) {
+                statistics.clipped_shadows += 1;
+            }
+            if [r, g, b].iter().any(|&value| value * 255.0 > 254.5) {
+                statistics.clipped_highlights += 1;
+            }
+            for (channel, value) in channels.iter_mut().zip([r, g, b, luma]) {
+                channel.add(value);
+            }
+        }
+    }
+
+    if statistics.pixel_count > 0 {
+        let [red, green, blue, luminance] = channels.map(|channel| channel.finish(&options.percentiles));
+        statistics = ImageStatistics { red, green, blue, luminance, ..statistics };
+    }
+    Ok(statistics)
+}
+
+/// Step between sampled pixels in both directions so that about `max_pixels` of `pixels` are visited
+pub(crate) fn sample_step(pixels: u64, max_pixels: u64) -> u32 {
+    if pixels <= max_pixels {
+        return 1;
+    }
+    ((pixels as f64 / max_pixels as f64).sqrt().ceil() as u32).max(1)
+}
+
+/// Running sums and a fine histogram of one channel (0.0-1.0)
+struct Accumulator {
+    histogram: Vec<u32>,
+    count: u64,
+    sum: f64,
+    sum_squares: f64,
+    min: f32,
+    max: f32,
+}
+
+impl Accumulator {
+    fn new() -> Accumulator {
+        Accumulator { histogram: vec![0; BINS], count: 0, sum: 0.0, sum_squares: 0.0, min: f32::MAX, max: f32::MIN }
+    }
+
+    fn add(&mut self, value: f32) {
+        self.histogram[(value * (BINS - 1) as f32).round() as usize] += 1;
+        self.count += 1;
+        self.sum += value as f64;
+        self.sum_squares += value as f64 * value as f64;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    fn finish(&self, percentiles: &[f32]) -> ChannelStatistics {
+        let count = self.count as f64;
+        let mean = self.sum / count;
+        let variance = (self.sum_squares / count - mean * mean).max(0.0);
+        ChannelStatistics {
+            min: self.min * 255.0,
+            max: self.max * 255.0,
+            mean: (mean * 255.0) as f32,
+            median: self.percentile(50.0),
+            std_dev: (variance.sqrt() * 255.0) as f32,
+            percentiles: percentiles.iter().map(|&percentile| self.percentile(percentile)).collect(),
+        }
+    }
+
+    /// Smallest value with at least `percentile` percent of the values at or below it
+    fn percentile(&self, percentile: f32) -> f32 {
+        let rank = ((percentile as f64 / 100.0 * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0u64;
+        for (bin, &count) in self.histogram.iter().enumerate() {
+            seen += count as u64;
+            if seen >= rank {
+                // Bins are exact for the extremes, which are tracked separately
+                let value = bin as f32 / (BINS - 1) as f32;
+                return value.clamp(self.min, self.max) * 255.0;
+            }
+        }
+        self.max * 255.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_channel_statistics() {
+        // Red runs 0..=99 across, green is constant, blue is clipped on the right
+        let img = RgbaImage::from_fn(100, 10, |x, _| Rgba([x as u8, 128, if x >= 90 { 255 } else { 40 }, 255]));
+        let statistics = image_statistics(&img, &StatisticsOptions::default()).unwrap();
+
+        assert_eq!(statistics.pixel_count, 1000);
+        assert_eq!((statistics.red.min, statistics.red.max), (0.0, 99.0));
+        assert!((statistics.red.mean - 49.5).abs() < 1e-3);
+        assert!((statistics.red.median - 49.0).abs() < 0.1, "{}", statistics.red.median);
+        assert!((statistics.red.std_dev - 28.866).abs() < 0.01, "{}", statistics.red.std_dev);
+        assert!((statistics.red.percentiles[6] - 98.0).abs() < 0.1, "{:?}", statistics.red.percentiles);
+        assert_eq!((statistics.green.std_dev, statistics.green.median), (0.0, 128.0));
+
+        assert_eq!(statistics.clipped_shadows, 10);
+        assert_eq!(statistics.clipped_highlights, 100);
+        assert_eq!(statistics.luminance_histogram.iter().sum::<u32>(), 1000);
+    }
+
+    #[test]
+    fn test_region_and_sampling() {
+        let img = RgbaImage::from_fn(200, 200, |x, y| if x < 100 && y < 100 { Rgba([10, 10, 10, 255]) } else { Rgba([200, 200, 200, 255]) });
+
+        let options = StatisticsOptions { region: Some(Region { x: 0, y: 0, width: 100, height: 100 }), ..StatisticsOptions::default() };
+        let statistics = image_statistics(&img, &options).unwrap();
+        assert_eq!((statistics.pixel_count, statistics.red.max), (10_000, 10.0));
+
+        let sampled = image_statistics(&img, &StatisticsOptions { max_pixels: Some(2500), ..StatisticsOptions::default() }).unwrap();
+        assert_eq!((sampled.sample_step, sampled.pixel_count), (4, 2500));
+        assert!((sampled.red.mean - (0.25 * 10.0 + 0.75 * 200.0)).abs() < 1e-3);
+
+        let outside = StatisticsOptions { region: Some(Region { x: 150, y: 0, width: 100, height: 10 }), ..StatisticsOptions::default() };
+        assert_eq!(image_statistics(&img, &outside).unwrap_err().parameter(), Some("region.x"));
+        assert_eq!(StatisticsOptions::from_json(r#"{ "percentiles": [101] }"#).unwrap_err().parameter(), Some("percentiles"));
+    }
+}
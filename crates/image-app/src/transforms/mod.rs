@@ -1,196 +1,495 @@
-use wasm_bindgen::prelude::*;
-use image::GenericImageView;
-use crate::utils::{load_image, to_bytes, console_log};
+use std::str::FromStr;
+
+use image::{imageops, Rgba, Rgba32FImage};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ensure_finite, EditorError};
+use crate::pixel::{EditorPixel, Image};
+use crate::utils::{console_log, is_opaque, premultiply, unpremultiply};
+
+/// Fully transparent fill for areas a transform leaves uncovered
+pub const TRANSPARENT: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+pub(crate) fn check_rotate(angle: u32) -> Result<(), EditorError> {
+    match angle {
+        90 | 180 | 270 => Ok(()),
+        _ => Err(EditorError::invalid_parameter(
+            "angle",
+            format!("unsupported rotation angle {}, expected 90, 180 or 270", angle),
+        )),
+    }
+}
 
 /// Rotate image by fixed angles (90, 180, 270 degrees)
-/// 
+///
 /// # Arguments
-/// * `image_data` - Input image bytes
+/// * `img` - Input image
 /// * `angle` - Rotation angle in degrees (90, 180, or 270)
-/// 
+///
 /// # Returns
-/// Processed image bytes
-#[wasm_bindgen]
-pub fn rotate(image_data: &[u8], angle: u32) -> Vec<u8> {
-    let img = load_image(image_data);
-    let processed = match angle {
-        90 => img.rotate90(),
-        180 => img.rotate180(),
-        270 => img.rotate270(),
-        _ => {
-            console_log(&format!("Warning: Unsupported rotation angle {}, returning original image", angle));
-            img
-        },
-    };
-    
-    to_bytes(&processed)
+/// Rotated image
+pub fn rotate<P: EditorPixel>(img: &Image<P>, angle: u32) -> Result<Image<P>, EditorError> {
+    check_rotate(angle)?;
+
+    match angle {
+        90 => Ok(imageops::rotate90(img)),
+        180 => Ok(imageops::rotate180(img)),
+        _ => Ok(imageops::rotate270(img)),
+    }
 }
 
 /// Flip image horizontally
-/// 
+///
 /// # Arguments
-/// * `image_data` - Input image bytes
-/// 
+/// * `img` - Input image
+///
 /// # Returns
-/// Processed image bytes
-#[wasm_bindgen]
-pub fn flip_horizontal(image_data: &[u8]) -> Vec<u8> {
-    let img = load_image(image_data);
+/// Mirrored image
+pub fn flip_horizontal<P: EditorPixel>(img: &Image<P>) -> Result<Image<P>, EditorError> {
     console_log("Flip horizontal function called");
-    
-    let processed = img.fliph();
+
+    let processed = imageops::flip_horizontal(img);
     console_log("Horizontal flip successful");
-    
-    to_bytes(&processed)
+
+    Ok(processed)
 }
 
 /// Flip image vertically
-/// 
+///
 /// # Arguments
-/// * `image_data` - Input image bytes
-/// 
+/// * `img` - Input image
+///
 /// # Returns
-/// Processed image bytes
-#[wasm_bindgen]
-pub fn flip_vertical(image_data: &[u8]) -> Vec<u8> {
-    let img = load_image(image_data);
+/// Mirrored image
+pub fn flip_vertical<P: EditorPixel>(img: &Image<P>) -> Result<Image<P>, EditorError> {
     console_log("Flip vertical function called");
-    
-    let processed = img.flipv();
+
+    let processed = imageops::flip_vertical(img);
     console_log("Vertical flip successful");
-    
-    to_bytes(&processed)
+
+    Ok(processed)
 }
 
-/// Resize image to specified dimensions
-/// 
-/// # Arguments
-/// * `image_data` - Input image bytes
-/// * `width` - Target width in pixels
-/// * `height` - Target height in pixels
-/// 
-/// # Returns
-/// Processed image bytes
-#[wasm_bindgen]
-pub fn resize(image_data: &[u8], width: u32, height: u32) -> Vec<u8> {
-    let img = load_image(image_data);
-    console_log("Resize function called");
-    
-    if width == 0 || height == 0 {
-        console_log("Error: Width and height must be greater than 0");
-        return image_data.to_vec();
+/// Resampling filter used when rotating by an arbitrary angle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    /// Nearest source pixel, hard edges
+    Nearest,
+    /// 2x2 linear blend
+    #[default]
+    Bilinear,
+    /// 4x4 Catmull-Rom cubic
+    Bicubic,
+    /// 6x6 Lanczos (a = 3), sharpest
+    Lanczos,
+}
+
+impl FromStr for Interpolation {
+    type Err = EditorError;
+
+    fn from_str(name: &str) -> Result<Interpolation, EditorError> {
+        match name {
+            "nearest" => Ok(Interpolation::Nearest),
+            "bilinear" => Ok(Interpolation::Bilinear),
+            "bicubic" => Ok(Interpolation::Bicubic),
+            "lanczos" => Ok(Interpolation::Lanczos),
+            _ => Err(EditorError::invalid_parameter(
+                "interpolation",
+                format!("unknown interpolation `{}`, expected nearest, bilinear, bicubic or lanczos", name),
+            )),
+        }
     }
-    
-    let processed = img.resize(width, height, image::imageops::FilterType::Lanczos3);
-    console_log("Resize successful");
-    
-    to_bytes(&processed)
 }
 
-/// Crop image to specified rectangle
-/// 
-/// # Arguments
-/// * `image_data` - Input image bytes
-/// * `x` - X coordinate of crop area
-/// * `y` - Y coordinate of crop area
-/// * `width` - Width of crop area
-/// * `height` - Height of crop area
-/// 
-/// # Returns
-/// Processed image bytes
-#[wasm_bindgen]
-pub fn crop(image_data: &[u8], x: u32, y: u32, width: u32, height: u32) -> Vec<u8> {
-    let img = load_image(image_data);
-    console_log("Crop function called");
-    
-    if x + width > img.width() || y + height > img.height() {
-        console_log("Error: Crop area is out of bounds.");
-        return image_data.to_vec();
+/// Settings for [`rotate_arbitrary`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RotateOptions {
+    /// Resampling filter
+    pub interpolation: Interpolation,
+    /// Fill color for the uncovered corners
+    pub background: Rgba<u8>,
+    /// Return the largest axis-aligned rectangle containing only image pixels
+    /// ("straighten and crop") instead of the enlarged canvas
+    pub auto_crop: bool,
+}
+
+impl Default for RotateOptions {
+    fn default() -> RotateOptions {
+        RotateOptions {
+            interpolation: Interpolation::default(),
+            background: TRANSPARENT,
+            auto_crop: false,
+        }
     }
-    
-    let processed = img.crop_imm(x, y, width, height);
-    console_log("Crop successful");
-    
-    to_bytes(&processed)
 }
 
-/// Rotate image by arbitrary angle
-/// 
+/// Rotate image by an arbitrary angle
+///
+/// By default the canvas grows to fit the rotated image and uncovered areas are
+/// filled with the background color, with anti-aliased edges for every filter
+/// except [`Interpolation::Nearest`]. With `auto_crop` the result is the largest
+/// centered rectangle that holds only image pixels.
+///
 /// # Arguments
-/// * `image_data` - Input image bytes
-/// * `angle` - Rotation angle in degrees (any float value)
-/// 
+/// * `img` - Input image
+/// * `angle` - Rotation angle in degrees
+/// * `options` - Interpolation, background color and cropping
+///
 /// # Returns
-/// Processed image bytes
-#[wasm_bindgen]
-pub fn rotate_arbitrary(image_data: &[u8], angle: f32) -> Vec<u8> {
-    let img = load_image(image_data);
+/// Rotated image
+pub fn rotate_arbitrary<P: EditorPixel>(img: &Image<P>, angle: f32, options: &RotateOptions) -> Result<Image<P>, EditorError> {
     console_log("Arbitrary rotation function called");
-    
-    // Convert angle to radians
-    let angle_rad = angle * std::f32::consts::PI / 180.0;
-    
-    // For arbitrary rotation, we'll use a combination of existing rotations
-    // and handle common angles efficiently
-    let processed = if (angle % 360.0).abs() < 0.1 {
+    ensure_finite("angle", angle)?;
+
+    // Right angles move whole pixels and need no resampling
+    let turn = angle.rem_euclid(360.0);
+    let processed = if turn.min(360.0 - turn) < 0.1 {
         // 0 degrees - no rotation
-        img
-    } else if ((angle % 360.0) - 90.0).abs() < 0.1 {
+        img.clone()
+    } else if (turn - 90.0).abs() < 0.1 {
         // 90 degrees
-        img.rotate90()
-    } else if ((angle % 360.0) - 180.0).abs() < 0.1 {
-        // 180 degrees  
-        img.rotate180()
-    } else if ((angle % 360.0) - 270.0).abs() < 0.1 {
+        imageops::rotate90(img)
+    } else if (turn - 180.0).abs() < 0.1 {
+        // 180 degrees
+        imageops::rotate180(img)
+    } else if (turn - 270.0).abs() < 0.1 {
         // 270 degrees
-        img.rotate270()
+        imageops::rotate270(img)
+    } else {
+        resample_rotation(img, angle, options)
+    };
+
+    console_log("Arbitrary rotation successful");
+
+    Ok(processed)
+}
+
+fn resample_rotation<P: EditorPixel>(img: &Image<P>, angle: f32, options: &RotateOptions) -> Image<P> {
+    let (width, height) = img.dimensions();
+    let (sin, cos) = angle.to_radians().sin_cos();
+
+    let (new_width, new_height) = if options.auto_crop {
+        // Pixel centers span (width - 1) x (height - 1); staying inside that area
+        // keeps every sample fully covered
+        largest_inscribed_rect(width as f32 - 1.0, height as f32 - 1.0, sin.abs(), cos.abs())
     } else {
-        // For arbitrary angles, we need to implement manual rotation
-        // This is a simplified version - in a real implementation, 
-        // you would use proper interpolation and handle transparency
-        use image::{ImageBuffer, Rgb};
-        
-        let (width, height) = img.dimensions();
-        let center_x = width as f32 / 2.0;
-        let center_y = height as f32 / 2.0;
-        
         // Calculate new image dimensions after rotation
-        let cos_a = angle_rad.cos().abs();
-        let sin_a = angle_rad.sin().abs();
-        let new_width = ((width as f32) * cos_a + (height as f32) * sin_a).ceil() as u32;
-        let new_height = ((height as f32) * cos_a + (width as f32) * sin_a).ceil() as u32;
-        
-        let mut rotated = ImageBuffer::new(new_width, new_height);
-        let new_center_x = new_width as f32 / 2.0;
-        let new_center_y = new_height as f32 / 2.0;
-        
-        for (x, y, pixel) in rotated.enumerate_pixels_mut() {
-            // Translate to origin
-            let translated_x = x as f32 - new_center_x;
-            let translated_y = y as f32 - new_center_y;
-            
-            // Rotate coordinates (inverse rotation)
-            let rotated_x = translated_x * (-angle_rad).cos() - translated_y * (-angle_rad).sin();
-            let rotated_y = translated_x * (-angle_rad).sin() + translated_y * (-angle_rad).cos();
-            
-            // Translate back and map to original image
-            let orig_x = (rotated_x + center_x).round() as i32;
-            let orig_y = (rotated_y + center_y).round() as i32;
-            
-            // Check bounds and sample pixel
-            if orig_x >= 0 && orig_x < width as i32 && orig_y >= 0 && orig_y < height as i32 {
-                let source_pixel = img.get_pixel(orig_x as u32, orig_y as u32);
-                *pixel = Rgb([source_pixel[0], source_pixel[1], source_pixel[2]]);
-            } else {
-                // Background color (white)
-                *pixel = Rgb([255, 255, 255]);
+        (
+            ((width as f32) * cos.abs() + (height as f32) * sin.abs()).ceil() as u32,
+            ((height as f32) * cos.abs() + (width as f32) * sin.abs()).ceil() as u32,
+        )
+    };
+
+    // Interpolate premultiplied so transparent pixels don't bleed into their neighbors
+    let source = premultiply(img);
+    let [r, g, b, a] = options.background.0.map(|channel| channel as f32 / 255.0);
+    let background = [r * a, g * a, b * a, a];
+
+    let center_x = width as f32 / 2.0;
+    let center_y = height as f32 / 2.0;
+    let new_center_x = new_width as f32 / 2.0;
+    let new_center_y = new_height as f32 / 2.0;
+
+    let rotated = Rgba32FImage::from_fn(new_width, new_height, |x, y| {
+        // Inverse rotation maps the output pixel center back into the source,
+        // in source coordinates where pixel centers sit on whole numbers
+        let dx = x as f32 + 0.5 - new_center_x;
+        let dy = y as f32 + 0.5 - new_center_y;
+        let source_x = dx * cos + dy * sin + center_x - 0.5;
+        let source_y = -dx * sin + dy * cos + center_y - 0.5;
+
+        let (color, coverage) = match options.interpolation {
+            Interpolation::Nearest => {
+                let px = source_x.round();
+                let py = source_y.round();
+                if px >= 0.0 && px < width as f32 && py >= 0.0 && py < height as f32 {
+                    (source.get_pixel(px as u32, py as u32).0, 1.0)
+                } else {
+                    ([0.0; 4], 0.0)
+                }
             }
+            interpolation => (
+                sample(&source, source_x, source_y, interpolation),
+                edge_coverage(source_x, width) * edge_coverage(source_y, height),
+            ),
+        };
+
+        // Composite the (partially covered) sample over the background
+        let visible = color[3] * coverage;
+        let mut pixel = [0.0; 4];
+        for ((out, color), background) in pixel.iter_mut().zip(color).zip(background) {
+            *out = color * coverage + background * (1.0 - visible);
         }
-        
-        image::DynamicImage::ImageRgb8(rotated)
+        Rgba(pixel)
+    });
+
+    unpremultiply(&rotated)
+}
+
+/// Interpolated premultiplied color at a source position, edge pixels repeat outwards
+fn sample(source: &Rgba32FImage, x: f32, y: f32, interpolation: Interpolation) -> [f32; 4] {
+    let (support, kernel): (i32, fn(f32) -> f32) = match interpolation {
+        Interpolation::Nearest | Interpolation::Bilinear => (1, triangle),
+        Interpolation::Bicubic => (2, catmull_rom),
+        Interpolation::Lanczos => (3, lanczos3),
     };
-    
-    console_log("Arbitrary rotation successful");
-    
-    to_bytes(&processed)
-}
\ No newline at end of file
+
+    let (width, height) = source.dimensions();
+    let base_x = x.floor() as i32;
+    let base_y = y.floor() as i32;
+
+    let mut sum = [0.0f32; 4];
+    let mut weight_sum = 0.0f32;
+    for tap_y in base_y - support + 1..=base_y + support {
+        let weight_y = kernel(y - tap_y as f32);
+        let py = tap_y.clamp(0, height as i32 - 1) as u32;
+
+        for tap_x in base_x - support + 1..=base_x + support {
+            let weight = weight_y * kernel(x - tap_x as f32);
+            let px = tap_x.clamp(0, width as i32 - 1) as u32;
+
+            for (total, value) in sum.iter_mut().zip(source.get_pixel(px, py).0) {
+                *total += value * weight;
+            }
+            weight_sum += weight;
+        }
+    }
+
+    // Cubic and Lanczos lobes overshoot, keep the result a valid premultiplied color
+    let alpha = (sum[3] / weight_sum).clamp(0.0, 1.0);
+    [
+        (sum[0] / weight_sum).clamp(0.0, alpha),
+        (sum[1] / weight_sum).clamp(0.0, alpha),
+        (sum[2] / weight_sum).clamp(0.0, alpha),
+        alpha,
+    ]
+}
+
+/// How much of a pixel-sized footprint at `position` lies inside `0..size`
+///
+/// Falls off linearly over the outermost pixel, which anti-aliases the edges.
+fn edge_coverage(position: f32, size: u32) -> f32 {
+    (position + 1.0).min(size as f32 - position).clamp(0.0, 1.0)
+}
+
+fn triangle(x: f32) -> f32 {
+    (1.0 - x.abs()).max(0.0)
+}
+
+fn catmull_rom(x: f32) -> f32 {
+    const A: f32 = -0.5;
+    let x = x.abs();
+    if x < 1.0 {
+        (A + 2.0) * x * x * x - (A + 3.0) * x * x + 1.0
+    } else if x < 2.0 {
+        A * x * x * x - 5.0 * A * x * x + 8.0 * A * x - 4.0 * A
+    } else {
+        0.0
+    }
+}
+
+fn lanczos3(x: f32) -> f32 {
+    use std::f32::consts::PI;
+
+    if x == 0.0 {
+        1.0
+    } else if x.abs() < 3.0 {
+        3.0 * (PI * x).sin() * (PI * x / 3.0).sin() / (PI * PI * x * x)
+    } else {
+        0.0
+    }
+}
+
+/// Largest axis-aligned rectangle inside a `width` x `height` rectangle rotated
+/// by an angle with the given absolute sine and cosine
+fn largest_inscribed_rect(width: f32, height: f32, sin: f32, cos: f32) -> (u32, u32) {
+    let width_is_longer = width >= height;
+    let (long_side, short_side) = if width_is_longer { (width, height) } else { (height, width) };
+
+    let (crop_width, crop_height) = if short_side <= 2.0 * sin * cos * long_side || (sin - cos).abs() < 1e-6 {
+        // Two corners of the crop touch the longer side
+        let half = 0.5 * short_side;
+        if width_is_longer {
+            (half / sin, half / cos)
+        } else {
+            (half / cos, half / sin)
+        }
+    } else {
+        // All four corners of the crop touch the rotated edges
+        let cos_2a = cos * cos - sin * sin;
+        ((width * cos - height * sin) / cos_2a, (height * cos - width * sin) / cos_2a)
+    };
+
+    (
+        (crop_width.floor() as u32).max(1),
+        (crop_height.floor() as u32).max(1),
+    )
+}
+
+pub(crate) fn check_resize(width: u32, height: u32) -> Result<(), EditorError> {
+    if width == 0 {
+        return Err(EditorError::invalid_parameter("width", "must be greater than 0"));
+    }
+    if height == 0 {
+        return Err(EditorError::invalid_parameter("height", "must be greater than 0"));
+    }
+    Ok(())
+}
+
+/// Resize image to fit within the given bounds, preserving aspect ratio
+///
+/// # Arguments
+/// * `img` - Input image
+/// * `width` - Maximum width in pixels
+/// * `height` - Maximum height in pixels
+///
+/// # Returns
+/// Resized image
+pub fn resize<P: EditorPixel>(img: &Image<P>, width: u32, height: u32) -> Result<Image<P>, EditorError> {
+    console_log("Resize function called");
+    check_resize(width, height)?;
+
+    let (new_width, new_height) = fit_within(img.width(), img.height(), width, height);
+    let filter = imageops::FilterType::Lanczos3;
+    // Resample premultiplied so transparent pixels don't bleed into the edges
+    let processed = if is_opaque(img) {
+        imageops::resize(img, new_width, new_height, filter)
+    } else {
+        unpremultiply(&imageops::resize(&premultiply(img), new_width, new_height, filter))
+    };
+    console_log("Resize successful");
+
+    Ok(processed)
+}
+
+/// Largest size with the source aspect ratio that fits in `width` x `height`
+///
+/// Mirrors `DynamicImage::resize`, which the byte exports used before.
+fn fit_within(src_width: u32, src_height: u32, width: u32, height: u32) -> (u32, u32) {
+    let width_ratio = width as f64 / src_width as f64;
+    let height_ratio = height as f64 / src_height as f64;
+    let ratio = width_ratio.min(height_ratio);
+
+    let new_width = (src_width as f64 * ratio).round().max(1.0) as u32;
+    let new_height = (src_height as f64 * ratio).round().max(1.0) as u32;
+    (new_width, new_height)
+}
+
+/// Crop a rectangle out of the image
+///
+/// # Arguments
+/// * `img` - Input image
+/// * `x`, `y` - Top-left corner of the crop area
+/// * `width`, `height` - Size of the crop area
+///
+/// # Returns
+/// Cropped image
+pub fn crop<P: EditorPixel>(img: &Image<P>, x: u32, y: u32, width: u32, height: u32) -> Result<Image<P>, EditorError> {
+    console_log("Crop function called");
+
+    check_area("crop area", img.dimensions(), x, y, width, height)?;
+
+    let processed = imageops::crop_imm(img, x, y, width, height).to_image();
+    console_log("Crop successful");
+
+    Ok(processed)
+}
+
+/// Check that a non-empty rectangle lies inside an image of size `dimensions`
+///
+/// `what` names the rectangle in the message, e.g. "crop area".
+pub(crate) fn check_area(what: &str, dimensions: (u32, u32), x: u32, y: u32, width: u32, height: u32) -> Result<(), EditorError> {
+    if width == 0 || height == 0 {
+        return Err(EditorError::invalid_parameter("width", format!("{} must not be empty", what)));
+    }
+
+    let (image_width, image_height) = dimensions;
+    let fits_x = x.checked_add(width).is_some_and(|right| right <= image_width);
+    let fits_y = y.checked_add(height).is_some_and(|bottom| bottom <= image_height);
+    if !fits_x || !fits_y {
+        return Err(EditorError::invalid_parameter(
+            if fits_x { "y" } else { "x" },
+            format!(
+                "{} {}x{} at ({}, {}) is outside the {}x{} image",
+                what, width, height, x, y, image_width, image_height
+            ),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    fn create_test_image() -> RgbaImage {
+        RgbaImage::from_fn(40, 20, |x, y| Rgba([(x * 6) as u8, (y * 12) as u8, 90, 200]))
+    }
+
+    #[test]
+    fn test_rotate_arbitrary_background() {
+        let img = create_test_image();
+
+        let rotated = rotate_arbitrary(&img, 30.0, &RotateOptions::default()).unwrap();
+        assert_eq!(rotated.get_pixel(0, 0), &TRANSPARENT);
+        let (width, height) = rotated.dimensions();
+        assert_eq!(rotated.get_pixel(width / 2, height / 2)[3], 200);
+
+        let white = Rgba([255, 255, 255, 255]);
+        let options = RotateOptions { background: white, ..RotateOptions::default() };
+        let rotated = rotate_arbitrary(&img, 30.0, &options).unwrap();
+        assert_eq!(rotated.get_pixel(0, 0), &white);
+    }
+
+    #[test]
+    fn test_rotate_arbitrary_edges() {
+        let img = RgbaImage::from_pixel(60, 40, Rgba([200, 100, 50, 255]));
+
+        for interpolation in [Interpolation::Nearest, Interpolation::Bilinear, Interpolation::Bicubic, Interpolation::Lanczos] {
+            let options = RotateOptions { interpolation, ..RotateOptions::default() };
+            let rotated = rotate_arbitrary(&img, 10.0, &options).unwrap();
+            assert_eq!(rotated.dimensions(), (67, 50));
+
+            let partial = rotated.pixels().filter(|pixel| pixel[3] > 0 && pixel[3] < 255).count();
+            if interpolation == Interpolation::Nearest {
+                assert_eq!(partial, 0);
+            } else {
+                // Anti-aliased edges, and a flat color stays flat inside
+                assert!(partial > 0);
+                assert_eq!(rotated.get_pixel(33, 25), &Rgba([200, 100, 50, 255]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_rotate_arbitrary_auto_crop() {
+        let img = RgbaImage::from_pixel(80, 50, Rgba([10, 20, 30, 255]));
+
+        for interpolation in [Interpolation::Nearest, Interpolation::Bilinear, Interpolation::Bicubic, Interpolation::Lanczos] {
+            for angle in [3.0, -12.5, 45.0, 100.0] {
+                let options = RotateOptions { interpolation, auto_crop: true, ..RotateOptions::default() };
+                let rotated = rotate_arbitrary(&img, angle, &options).unwrap();
+                assert!(rotated.width() < 80 && rotated.height() < 80);
+                assert!(rotated.pixels().all(|pixel| pixel == &Rgba([10, 20, 30, 255])));
+            }
+        }
+    }
+
+    #[test]
+    fn test_interpolation_names() {
+        assert_eq!("lanczos".parse::<Interpolation>().unwrap(), Interpolation::Lanczos);
+        let error = "cubic".parse::<Interpolation>().unwrap_err();
+        assert_eq!(error.parameter(), Some("interpolation"));
+    }
+
+    #[test]
+    fn test_resize_keeps_alpha() {
+        let img = create_test_image();
+
+        let resized = resize(&img, 20, 20).unwrap();
+        assert_eq!(resized.dimensions(), (20, 10));
+        assert!(resized.pixels().all(|pixel| pixel[3] == 200));
+    }
+}
@@ -1,41 +1,285 @@
-use wasm_bindgen::prelude::*;
-use image::{DynamicImage, ImageFormat, GenericImageView};
 use std::io::Cursor;
 
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_namespace = console)]
-    fn log(s: &str);
+use image::error::ImageFormatHint;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageError, ImageReader, Rgba, Rgba32FImage, RgbaImage};
+
+use crate::error::EditorError;
+use crate::formats::{self, ExportFormat};
+use crate::metadata::Metadata;
+use crate::pixel::{EditorPixel, Image};
+
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+mod console {
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_namespace = console)]
+        pub fn log(s: &str);
+    }
 }
 
-/// Convert a DynamicImage to PNG bytes
-/// 
+/// Convert an RGBA image to PNG bytes
+///
+/// Fully opaque images are written as RGB to keep the output small. Use
+/// [`crate::formats::encode`] for other formats and encoder settings.
+///
 /// # Arguments
 /// * `img` - The image to convert
-/// 
+///
 /// # Returns
 /// Vector of PNG-encoded bytes
-pub fn to_bytes(img: &DynamicImage) -> Vec<u8> {
-    let mut buffer = Cursor::new(Vec::new());
-    img.write_to(&mut buffer, ImageFormat::Png).unwrap();
-    buffer.into_inner()
+pub fn to_bytes<P: EditorPixel>(img: &Image<P>) -> Result<Vec<u8>, EditorError> {
+    formats::encode(img, &ExportFormat::default())
+}
+
+/// Options for [`load_image_with`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadOptions {
+    /// Rotate and flip the pixels as the EXIF Orientation tag asks, so photos
+    /// taken in portrait come out upright
+    pub auto_orient: bool,
+}
+
+impl Default for LoadOptions {
+    fn default() -> LoadOptions {
+        LoadOptions { auto_orient: true }
+    }
 }
 
 /// Load an image from byte array
-/// 
+///
+/// PNG and JPEG are always supported; WebP, TIFF, GIF (first frame), BMP and QOI
+/// need their cargo features. The EXIF orientation is applied to the pixels.
+///
 /// # Arguments
-/// * `image_data` - Byte array containing image data
-/// 
+/// * `image_data` - Byte array containing the encoded image
+///
 /// # Returns
-/// DynamicImage instance
-pub fn load_image(image_data: &[u8]) -> DynamicImage {
-    image::load_from_memory(image_data).unwrap()
+/// Decoded image as an RGBA buffer
+pub fn load_image(image_data: &[u8]) -> Result<RgbaImage, EditorError> {
+    load_image_with(image_data, &LoadOptions::default()).map(|(img, _)| img)
+}
+
+/// Load an image together with its metadata
+///
+/// Decoding into an `Rgba32FImage` keeps the full precision of 16-bit files.
+/// When the orientation is applied, the EXIF Orientation tag in the returned
+/// metadata is reset to 1 so exporting it does not rotate the image twice.
+///
+/// # Arguments
+/// * `image_data` - Byte array containing the encoded image
+/// * `options` - Decoding options
+///
+/// # Returns
+/// Decoded image in the requested pixel format and the metadata of the file
+pub fn load_image_with<P: EditorPixel>(image_data: &[u8], options: &LoadOptions) -> Result<(Image<P>, Metadata), EditorError> {
+    let mut decoder = ImageReader::new(Cursor::new(image_data))
+        .with_guessed_format()
+        .map_err(|e| EditorError::Decode(e.to_string()))?
+        .into_decoder()
+        .map_err(decode_error)?;
+
+    let mut metadata = Metadata::read(image_data);
+    // A broken profile is not worth failing the whole load for
+    metadata.icc_profile = decoder.icc_profile().ok().flatten();
+    let orientation = match metadata.orientation() {
+        Some(value) => Orientation::from_exif(value as u8),
+        None => decoder.orientation().ok(),
+    }
+    .unwrap_or(Orientation::NoTransforms);
+
+    let mut img = DynamicImage::from_decoder(decoder).map_err(decode_error)?;
+    if options.auto_orient && orientation != Orientation::NoTransforms {
+        img.apply_orientation(orientation);
+        metadata.reset_orientation();
+    }
+    Ok((P::from_dynamic(img), metadata))
+}
+
+/// Bits per channel of an encoded image, read from its header
+///
+/// Sources with more than 8 bits (16-bit PNG and TIFF, float formats) report
+/// 16, the deepest [`ExportFormat::Png`] can write, so re-encoding an edited
+/// image does not truncate it.
+///
+/// # Arguments
+/// * `image_data` - Byte array containing the encoded image
+///
+/// # Returns
+/// 8 or 16
+pub fn source_bit_depth(image_data: &[u8]) -> Result<u8, EditorError> {
+    let decoder = ImageReader::new(Cursor::new(image_data))
+        .with_guessed_format()
+        .map_err(|e| EditorError::Decode(e.to_string()))?
+        .into_decoder()
+        .map_err(decode_error)?;
+    let color = decoder.color_type();
+    Ok(if color.bytes_per_pixel() > color.channel_count() { 16 } else { 8 })
+}
+
+fn decode_error(error: ImageError) -> EditorError {
+    match error {
+        // A recognised format whose decoder is not compiled in
+        ImageError::Unsupported(ref unsupported) if matches!(unsupported.format_hint(), ImageFormatHint::Exact(_)) => {
+            EditorError::UnsupportedFormat(error.to_string())
+        }
+        _ => EditorError::Decode(error.to_string()),
+    }
+}
+
+/// Whether every pixel of the image is fully opaque
+pub fn is_opaque<P: EditorPixel>(img: &Image<P>) -> bool {
+    img.pixels().all(|pixel| pixel.to_unit()[3] >= 1.0)
+}
+
+/// Convert to a premultiplied-alpha float image (0.0-1.0)
+///
+/// Resampling and convolution must run on premultiplied values, otherwise the
+/// hidden color of transparent pixels bleeds into visible edges.
+pub fn premultiply<P: EditorPixel>(img: &Image<P>) -> Rgba32FImage {
+    Rgba32FImage::from_fn(img.width(), img.height(), |x, y| {
+        let [r, g, b, alpha] = img.get_pixel(x, y).to_unit();
+        Rgba([r * alpha, g * alpha, b * alpha, alpha])
+    })
+}
+
+/// Convert a premultiplied-alpha float image back to straight RGBA
+pub fn unpremultiply<P: EditorPixel>(img: &Rgba32FImage) -> Image<P> {
+    Image::<P>::from_fn(img.width(), img.height(), |x, y| {
+        let [r, g, b, a] = img.get_pixel(x, y).0;
+        let alpha = a.clamp(0.0, 1.0);
+        if alpha <= 0.0 {
+            return P::from_unit([0.0; 4]);
+        }
+        P::from_unit([r / alpha, g / alpha, b / alpha, alpha])
+    })
 }
 
 /// Log a message to browser console
-/// 
+///
+/// This is a no-op outside the browser (native builds, tests, benches).
+///
 /// # Arguments
 /// * `message` - Message to log
 pub fn console_log(message: &str) {
-    log(message);
-}
\ No newline at end of file
+    #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+    console::log(message);
+
+    #[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+    let _ = message;
+}
+
+/// Convert RGB (0.0-1.0) to HSV (hue in degrees, saturation and value 0.0-1.0)
+#[inline]
+pub fn rgb_to_hsv(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let max = r.max(g.max(b));
+    let min = r.min(g.min(b));
+    let delta = max - min;
+
+    let s = if max == 0.0 { 0.0 } else { delta / max };
+    (hue_degrees(r, g, b, max, delta), s, max)
+}
+
+/// Convert HSV (hue in degrees, saturation and value 0.0-1.0) back to RGB (0.0-1.0)
+#[inline]
+pub fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (f32, f32, f32) {
+    let c = v * s;
+    from_hue_chroma(h, c, v - c)
+}
+
+/// Convert RGB (0.0-1.0) to HSL (hue in degrees, saturation and lightness 0.0-1.0)
+#[inline]
+pub fn rgb_to_hsl(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let max = r.max(g.max(b));
+    let min = r.min(g.min(b));
+    let delta = max - min;
+    let l = (max + min) / 2.0;
+
+    let s = if delta == 0.0 { 0.0 } else { delta / (1.0 - (2.0 * l - 1.0).abs()) };
+    (hue_degrees(r, g, b, max, delta), s.min(1.0), l)
+}
+
+/// Convert HSL (hue in degrees, saturation and lightness 0.0-1.0) back to RGB (0.0-1.0)
+#[inline]
+pub fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (f32, f32, f32) {
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    from_hue_chroma(h, c, l - c / 2.0)
+}
+
+/// Hue in degrees (0-360) shared by HSV and HSL
+#[inline]
+fn hue_degrees(r: f32, g: f32, b: f32, max: f32, delta: f32) -> f32 {
+    let h = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * (((g - b) / delta) % 6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+
+    if h < 0.0 {
+        h + 360.0
+    } else {
+        h
+    }
+}
+
+/// RGB from hue, chroma `c` and the amount `m` added to every channel
+#[inline]
+fn from_hue_chroma(h: f32, c: f32, m: f32) -> (f32, f32, f32) {
+    let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
+
+    let (r_prime, g_prime, b_prime) = if h < 60.0 {
+        (c, x, 0.0)
+    } else if h < 120.0 {
+        (x, c, 0.0)
+    } else if h < 180.0 {
+        (0.0, c, x)
+    } else if h < 240.0 {
+        (0.0, x, c)
+    } else if h < 300.0 {
+        (x, 0.0, c)
+    } else {
+        (c, 0.0, x)
+    };
+
+    (r_prime + m, g_prime + m, b_prime + m)
+}
+
+/// Optimized memory management and processing utilities
+pub mod optimization {
+    /// Optimized pixel processing with chunked operations
+    ///
+    /// Chunks are a multiple of 4 bytes, so RGBA pixels never straddle two chunks.
+    pub fn process_pixels_chunked<F>(data: &mut [u8], mut processor: F)
+    where
+        F: FnMut(&mut [u8])
+    {
+        // Process in 64-byte chunks for optimal cache line usage
+        const CHUNK_SIZE: usize = 64;
+        let mut chunks_iter = data.chunks_exact_mut(CHUNK_SIZE);
+
+        for chunk in chunks_iter.by_ref() {
+            processor(chunk);
+        }
+
+        let remainder = chunks_iter.into_remainder();
+
+        if !remainder.is_empty() {
+            processor(remainder);
+        }
+    }
+
+    /// Optimized RGB to grayscale conversion with fixed-point arithmetic
+    #[inline(always)]
+    pub fn rgb_to_gray_fast(r: u8, g: u8, b: u8) -> u8 {
+        // Using fixed-point arithmetic: 0.299*R + 0.587*G + 0.114*B
+        // Approximated as: (77*R + 150*G + 29*B) >> 8
+        let gray = (77 * r as u32 + 150 * g as u32 + 29 * b as u32) >> 8;
+        gray as u8
+    }
+}
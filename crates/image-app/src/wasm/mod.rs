@@ -0,0 +1,1093 @@
+//! JavaScript bindings
+//!
+//! Thin `wasm-bindgen` layer over the pure-Rust core: every export decodes its
+//! input, runs the matching [`Operation`] and encodes the result. Errors are thrown
+//! as a JavaScript `Error` with `code`, `message` and `parameter`.
+//!
+//! Only compiled with the `wasm` feature (on by default).
+
+use image::{Rgba32FImage, RgbaImage};
+use wasm_bindgen::prelude::*;
+
+use crate::adjustments::{ClaheOptions, Levels, ToneCurves, WhiteBalance};
+use crate::filters::{BorderMode, Channels, ConvolutionKernel, ConvolveOptions, DenoiseOptions, Kernel, UnsharpMaskOptions};
+use crate::lut::{CubeLut, CUBE_SIZE};
+use crate::metadata::Metadata;
+use crate::overlays::{self, ClippingOptions, FalseColorOptions};
+use crate::utils::{load_image, load_image_with, source_bit_depth, to_bytes, LoadOptions};
+use crate::{adjustments, formats, EditorError, EditorImage, ExportFormat, ExportOptions, Operation, Pipeline, StatisticsOptions};
+
+impl From<EditorError> for JsValue {
+    fn from(error: EditorError) -> JsValue {
+        let js_error = js_sys::Error::new(&error.to_string());
+        js_error.set_name("EditorError");
+
+        let object: &JsValue = js_error.as_ref();
+        let parameter = error.parameter().map_or(JsValue::NULL, JsValue::from_str);
+        // Reflect::set only fails on frozen objects; a fresh Error is never frozen
+        let _ = js_sys::Reflect::set(object, &JsValue::from_str("code"), &JsValue::from_str(error.code()));
+        let _ = js_sys::Reflect::set(object, &JsValue::from_str("parameter"), &parameter);
+
+        js_error.into()
+    }
+}
+
+/// Decode, run one operation and encode, converting errors for JavaScript
+///
+/// The EXIF, XMP and ICC profile of the input are written back into the PNG.
+fn process_bytes(image_data: &[u8], operation: Operation) -> Result<Vec<u8>, JsValue> {
+    let (mut img, metadata): (RgbaImage, _) = load_image_with(image_data, &LoadOptions::default())?;
+    operation.apply(&mut img)?;
+    Ok(formats::encode_with_metadata(&img, &ExportFormat::default(), &metadata)?)
+}
+
+/// Read an optional `[r, g, b]` / `[r, g, b, a]` color passed from JavaScript
+fn parse_color(parameter: &str, color: Option<Vec<u8>>) -> Result<Option<[u8; 4]>, EditorError> {
+    match color.as_deref() {
+        None => Ok(None),
+        Some(&[r, g, b]) => Ok(Some([r, g, b, u8::MAX])),
+        Some(&[r, g, b, a]) => Ok(Some([r, g, b, a])),
+        Some(_) => Err(EditorError::invalid_parameter(parameter, "expected [r, g, b] or [r, g, b, a]")),
+    }
+}
+
+/// Build a `rotate_arbitrary` step from the optional JavaScript arguments
+fn rotate_operation(
+    angle: f32,
+    background: Option<Vec<u8>>,
+    interpolation: Option<String>,
+    auto_crop: Option<bool>,
+) -> Result<Operation, EditorError> {
+    Ok(Operation::RotateArbitrary {
+        angle,
+        background: parse_color("background", background)?,
+        interpolation: interpolation.as_deref().map(str::parse).transpose()?.unwrap_or_default(),
+        auto_crop: auto_crop.unwrap_or(false),
+    })
+}
+
+/// Read an optional border mode name (`"clamp"` when omitted)
+fn parse_border(border: Option<String>) -> Result<BorderMode, EditorError> {
+    Ok(border.as_deref().map(str::parse).transpose()?.unwrap_or_default())
+}
+
+// --- JavaScriptに公開する関数 ---
+
+#[wasm_bindgen]
+pub fn adjust_brightness(image_data: &[u8], value: i32) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Brightness { value })
+}
+
+#[wasm_bindgen]
+pub fn adjust_contrast(image_data: &[u8], value: f32) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Contrast { value })
+}
+
+/// Build a `saturation` step from the optional JavaScript model name
+fn saturation_operation(value: f32, model: Option<String>) -> Result<Operation, EditorError> {
+    Ok(Operation::Saturation {
+        value,
+        model: model.as_deref().map(str::parse).transpose()?.unwrap_or_default(),
+    })
+}
+
+/// Adjust color saturation without touching hue
+///
+/// # Arguments
+/// * `image_data` - Input image bytes
+/// * `value` - Saturation change (-100 = grayscale, +100 = double)
+/// * `model` - `"oklch"` (default), `"hsl"` or `"hsv"`
+///
+/// # Returns
+/// Processed image bytes
+#[wasm_bindgen]
+pub fn adjust_saturation(image_data: &[u8], value: f32, model: Option<String>) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, saturation_operation(value, model)?)
+}
+
+/// Rotate all hues around the gray axis by `degrees`
+#[wasm_bindgen]
+pub fn hue_rotate(image_data: &[u8], degrees: f32) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::HueRotate { degrees })
+}
+
+#[wasm_bindgen]
+pub fn rotate(image_data: &[u8], angle: u32) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Rotate { angle })
+}
+
+#[wasm_bindgen]
+pub fn crop(image_data: &[u8], x: u32, y: u32, width: u32, height: u32) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Crop { x, y, width, height })
+}
+
+#[wasm_bindgen]
+pub fn adjust_white_balance(image_data: &[u8], value: f32) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::WhiteBalance { value })
+}
+
+/// White balance from the color temperature (Kelvin) and tint of the light
+///
+/// # Arguments
+/// * `image_data` - Input image bytes
+/// * `temperature` - Light to neutralize in Kelvin (1667 to 25000, 6504 = no change)
+/// * `tint` - Green/magenta shift of the light (-100 to +100, positive = magenta)
+///
+/// # Returns
+/// Processed image bytes
+#[wasm_bindgen]
+pub fn apply_white_balance(image_data: &[u8], temperature: f32, tint: f32) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::ColorTemperature(WhiteBalance { temperature, tint }))
+}
+
+/// Measure the white balance that makes an area neutral
+///
+/// # Arguments
+/// * `image_data` - Input image bytes
+/// * `x`, `y` - Top-left corner of the sample area
+/// * `width`, `height` - Size of the sample area, 1×1 for a single pixel
+///
+/// # Returns
+/// `{ temperature, tint }` for `apply_white_balance`
+#[wasm_bindgen]
+pub fn pick_neutral(image_data: &[u8], x: u32, y: u32, width: u32, height: u32) -> Result<JsValue, JsValue> {
+    let img = load_image(image_data)?;
+    to_js(&adjustments::pick_neutral(&img, x, y, width, height)?)
+}
+
+#[wasm_bindgen]
+pub fn flip_horizontal(image_data: &[u8]) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::FlipHorizontal)
+}
+
+#[wasm_bindgen]
+pub fn flip_vertical(image_data: &[u8]) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::FlipVertical)
+}
+
+/// Rotate by any angle
+///
+/// # Arguments
+/// * `image_data` - Input image bytes
+/// * `angle` - Rotation angle in degrees
+/// * `background` - `[r, g, b]` or `[r, g, b, a]` fill for the corners, transparent when omitted
+/// * `interpolation` - `"nearest"`, `"bilinear"` (default), `"bicubic"` or `"lanczos"`
+/// * `auto_crop` - Crop to the largest rectangle without uncovered corners
+#[wasm_bindgen]
+pub fn rotate_arbitrary(
+    image_data: &[u8],
+    angle: f32,
+    background: Option<Vec<u8>>,
+    interpolation: Option<String>,
+    auto_crop: Option<bool>,
+) -> Result<Vec<u8>, JsValue> {
+    let operation = rotate_operation(angle, background, interpolation, auto_crop)?;
+    process_bytes(image_data, operation)
+}
+
+#[wasm_bindgen]
+pub fn resize(image_data: &[u8], width: u32, height: u32) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Resize { width, height })
+}
+
+#[wasm_bindgen]
+pub fn to_grayscale(image_data: &[u8]) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Grayscale)
+}
+
+#[wasm_bindgen]
+pub fn apply_sepia(image_data: &[u8]) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Sepia)
+}
+
+#[wasm_bindgen]
+pub fn gaussian_blur(image_data: &[u8], sigma: f32) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::GaussianBlur { sigma })
+}
+
+/// Sharpen with a 1-pixel unsharp mask
+///
+/// # Arguments
+/// * `image_data` - Input image bytes
+/// * `amount` - Sharpening intensity (0.0 to 1.0)
+/// * `border` - `"clamp"` (default), `"mirror"`, `"wrap"` or `"constant"` (transparent)
+///
+/// # Returns
+/// Processed image bytes
+#[wasm_bindgen]
+pub fn sharpen(image_data: &[u8], amount: f32, border: Option<String>) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Sharpen { amount, border: parse_border(border)? })
+}
+
+/// Sharpen with an unsharp mask
+///
+/// # Arguments
+/// * `image_data` - Input image bytes
+/// * `radius` - Blur radius in pixels (0.1 to 100.0)
+/// * `amount` - Strength in percent (0 to 500)
+/// * `threshold` - Smallest difference in levels that gets sharpened (0 to 255)
+/// * `luminance_only` - Sharpen luma only, avoiding color fringes
+/// * `border` - `"clamp"` (default), `"mirror"`, `"wrap"` or `"constant"` (transparent)
+///
+/// # Returns
+/// Processed image bytes
+#[wasm_bindgen]
+pub fn unsharp_mask(
+    image_data: &[u8],
+    radius: f32,
+    amount: f32,
+    threshold: f32,
+    luminance_only: bool,
+    border: Option<String>,
+) -> Result<Vec<u8>, JsValue> {
+    let border = parse_border(border)?;
+    process_bytes(image_data, Operation::UnsharpMask(UnsharpMaskOptions { radius, amount, threshold, luminance_only, border }))
+}
+
+#[wasm_bindgen]
+pub fn adjust_hue(image_data: &[u8], shift: f32) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Hue { shift })
+}
+
+#[wasm_bindgen]
+pub fn adjust_exposure(image_data: &[u8], stops: f32) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Exposure { stops })
+}
+
+#[wasm_bindgen]
+pub fn adjust_vibrance(image_data: &[u8], amount: f32) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Vibrance { amount })
+}
+
+#[wasm_bindgen]
+pub fn apply_vignette(image_data: &[u8], strength: f32, radius: f32) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Vignette { strength, radius })
+}
+
+#[wasm_bindgen]
+pub fn reduce_noise(image_data: &[u8], strength: f32, border: Option<String>) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::ReduceNoise { strength, border: parse_border(border)? })
+}
+
+/// Fast edge-preserving noise reduction
+///
+/// # Arguments
+/// * `image_data` - Input image bytes
+/// * `luminance` - Luminance noise reduction (0 to 100)
+/// * `chroma` - Color noise reduction (0 to 100)
+/// * `radius` - Window radius in pixels (1 to 32, usually 2)
+///
+/// # Returns
+/// Processed image bytes
+#[wasm_bindgen]
+pub fn denoise(image_data: &[u8], luminance: f32, chroma: f32, radius: u32) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Denoise(DenoiseOptions { luminance, chroma, radius }))
+}
+
+#[wasm_bindgen]
+pub fn apply_emboss(image_data: &[u8], border: Option<String>) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Emboss { border: parse_border(border)? })
+}
+
+/// Convolve with a custom kernel
+///
+/// # Arguments
+/// * `image_data` - Input image bytes
+/// * `kernel` - `width * height` weights, row by row
+/// * `width`, `height` - Odd kernel size (1 to 127)
+/// * `divisor` - Divides the weighted sums, the sum of the weights when omitted
+/// * `bias` - Offset in 8-bit levels added after dividing
+/// * `channels` - Letters of `"rgba"` to filter, `"rgb"` when omitted; alpha needs weights that do not sum to zero
+///
+/// # Returns
+/// Processed image bytes
+#[wasm_bindgen]
+pub fn apply_kernel(
+    image_data: &[u8],
+    kernel: Vec<f32>,
+    width: u32,
+    height: u32,
+    divisor: Option<f32>,
+    bias: f32,
+    channels: Option<String>,
+) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Convolve(kernel_options(kernel, width, height, divisor, bias, channels)?))
+}
+
+/// Convolve with a custom or named kernel
+///
+/// # Arguments
+/// * `image_data` - Input image bytes
+/// * `options_json` - Kernel, divisor, bias, channels and border, e.g.
+///   `{"kernel":{"name":"emboss","angle":135},"bias":128,"border":"mirror"}`
+///
+/// # Returns
+/// Processed image bytes
+#[wasm_bindgen]
+pub fn convolve(image_data: &[u8], options_json: &str) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Convolve(ConvolveOptions::from_json(options_json)?))
+}
+
+/// Apply a color grade from a `.cube` LUT
+///
+/// # Arguments
+/// * `image_data` - Input image bytes
+/// * `cube` - Text of the `.cube` file (1D, 3D or both)
+/// * `intensity` - Mix with the original colors (0 to 100, 100 = full grade)
+/// * `interpolation` - `"tetrahedral"` (default) or `"trilinear"`
+///
+/// # Returns
+/// Processed image bytes
+#[wasm_bindgen]
+pub fn apply_lut(image_data: &[u8], cube: &str, intensity: f32, interpolation: Option<String>) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, lut_operation(cube, intensity, interpolation)?)
+}
+
+/// Build a `lut` operation from the `apply_lut` arguments
+fn lut_operation(cube: &str, intensity: f32, interpolation: Option<String>) -> Result<Operation, EditorError> {
+    Ok(Operation::Lut {
+        cube: CubeLut::parse(cube)?,
+        interpolation: interpolation.as_deref().map(str::parse).transpose()?.unwrap_or_default(),
+        intensity,
+    })
+}
+
+/// Build `convolve` options from the `apply_kernel` arguments
+fn kernel_options(
+    kernel: Vec<f32>,
+    width: u32,
+    height: u32,
+    divisor: Option<f32>,
+    bias: f32,
+    channels: Option<String>,
+) -> Result<ConvolveOptions, EditorError> {
+    let kernel = ConvolutionKernel::Custom(Kernel::new(kernel, width, height)?);
+    let channels = channels.as_deref().map(str::parse).transpose()?.unwrap_or(Channels::RGB);
+    Ok(ConvolveOptions { divisor, bias, channels, ..ConvolveOptions::new(kernel) })
+}
+
+#[wasm_bindgen]
+pub fn histogram_equalization(image_data: &[u8]) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::HistogramEqualization)
+}
+
+/// Contrast-limited adaptive histogram equalization on lightness
+///
+/// # Arguments
+/// * `image_data` - Input image bytes
+/// * `tiles_x`, `tiles_y` - Tile grid (1 to 64 each, usually 8)
+/// * `clip_limit` - Contrast limit (1.0 to 100.0, usually 2.0; 1.0 = no change)
+///
+/// # Returns
+/// Processed image bytes
+#[wasm_bindgen]
+pub fn clahe(image_data: &[u8], tiles_x: u32, tiles_y: u32, clip_limit: f32) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Clahe(ClaheOptions { tiles_x, tiles_y, clip_limit }))
+}
+
+#[wasm_bindgen]
+pub fn adjust_highlights(image_data: &[u8], amount: f32) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Highlights { amount })
+}
+
+#[wasm_bindgen]
+pub fn adjust_shadows(image_data: &[u8], amount: f32) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Shadows { amount })
+}
+
+#[wasm_bindgen]
+pub fn adjust_curves(image_data: &[u8], red_gamma: f32, green_gamma: f32, blue_gamma: f32) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Curves { red_gamma, green_gamma, blue_gamma })
+}
+
+/// Apply spline tone curves
+///
+/// # Arguments
+/// * `image_data` - Input image bytes
+/// * `curves_json` - Control points per curve on the 0-255 scale, e.g.
+///   `{"master":[[0,0],[64,48],[192,208],[255,255]],"blue":[[0,20],[255,255]]}`
+///
+/// # Returns
+/// Processed image bytes
+#[wasm_bindgen]
+pub fn apply_tone_curves(image_data: &[u8], curves_json: &str) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::ToneCurve(ToneCurves::from_json(curves_json)?))
+}
+
+/// Sample tone curves for drawing them in a curve editor
+///
+/// # Arguments
+/// * `curves_json` - Same format as `apply_tone_curves`
+/// * `size` - Samples per curve, 256 for 8-bit or 4096 for float precision
+///
+/// # Returns
+/// An object with `master`, `red`, `green` and `blue` arrays of output levels
+/// (0-255) for inputs evenly spaced from 0 to 255
+#[wasm_bindgen]
+pub fn sample_tone_curves(curves_json: &str, size: usize) -> Result<JsValue, JsValue> {
+    to_js(&ToneCurves::from_json(curves_json)?.sample(size)?)
+}
+
+#[wasm_bindgen]
+pub fn adjust_levels(image_data: &[u8], black_point: u8, white_point: u8, gamma: f32) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::Levels { black_point, white_point, gamma })
+}
+
+/// Apply per-channel levels with output black and white points
+///
+/// # Arguments
+/// * `image_data` - Input image bytes
+/// * `levels_json` - Levels per channel, e.g.
+///   `{"master":{"output_black":16},"blue":{"black_point":12,"gamma":0.9}}`
+///
+/// # Returns
+/// Processed image bytes
+#[wasm_bindgen]
+pub fn apply_levels(image_data: &[u8], levels_json: &str) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::ChannelLevels(Levels::from_json(levels_json)?))
+}
+
+/// Stretch each channel to the full range, clipping `clip_percent` of the
+/// pixels at each end (e.g. 0.1)
+#[wasm_bindgen]
+pub fn auto_levels(image_data: &[u8], clip_percent: f32) -> Result<Vec<u8>, JsValue> {
+    process_bytes(image_data, Operation::AutoLevels { clip_percent })
+}
+
+/// Recommend values for the exposure, contrast, highlights, shadows, white
+/// balance and vibrance sliders without changing the image
+///
+/// # Returns
+/// `{ exposure, contrast, highlights, shadows, white_balance: { temperature, tint }, vibrance }`
+#[wasm_bindgen]
+pub fn analyze_auto_adjustments(image_data: &[u8]) -> Result<JsValue, JsValue> {
+    let img = load_image(image_data)?;
+    to_js(&adjustments::analyze_auto_adjustments(&img))
+}
+
+#[wasm_bindgen]
+pub fn calculate_histogram(image_data: &[u8]) -> Result<Vec<u32>, JsValue> {
+    let img = load_image(image_data)?;
+    Ok(adjustments::calculate_histogram(&img))
+}
+
+/// Luminance histogram, per-channel min/max/mean/median/stddev and percentiles,
+/// and clipped pixel counts
+///
+/// # Arguments
+/// * `image_data` - Input image bytes
+/// * `options_json` - Optional region, pixel budget and percentiles, e.g.
+///   `{"region":{"x":0,"y":0,"width":100,"height":100},"max_pixels":250000,"percentiles":[1,50,99]}`
+///
+/// # Returns
+/// The statistics as an object (see [`crate::statistics`])
+#[wasm_bindgen]
+pub fn image_statistics(image_data: &[u8], options_json: Option<String>) -> Result<JsValue, JsValue> {
+    let options = parse_options(options_json, StatisticsOptions::from_json)?;
+    let img = load_image(image_data)?;
+    to_js(&crate::statistics::image_statistics(&img, &options)?)
+}
+
+/// Flag clipped channels of every pixel
+///
+/// # Arguments
+/// * `image_data` - Input image bytes
+/// * `options_json` - Optional levels, e.g. `{"shadow_level":2,"highlight_level":253}`
+///
+/// # Returns
+/// One byte per pixel: bits 0-2 for red, green and blue at white, bits 4-6 at black
+#[wasm_bindgen]
+pub fn clipping_mask(image_data: &[u8], options_json: Option<String>) -> Result<Vec<u8>, JsValue> {
+    let options = parse_options(options_json, ClippingOptions::from_json)?;
+    let img = load_image(image_data)?;
+    Ok(overlays::clipping_mask(&img, &options)?)
+}
+
+/// The image with clipped pixels painted over, as PNG bytes
+///
+/// # Arguments
+/// * `image_data` - Input image bytes
+/// * `options_json` - Optional levels and colors, e.g. `{"highlight_color":[255,0,255]}`
+#[wasm_bindgen]
+pub fn clipping_overlay(image_data: &[u8], options_json: Option<String>) -> Result<Vec<u8>, JsValue> {
+    let options = parse_options(options_json, ClippingOptions::from_json)?;
+    let img = load_image(image_data)?;
+    Ok(to_bytes(&overlays::clipping_overlay(&img, &options)?)?)
+}
+
+/// IRE false-color map or zebra stripes, as PNG bytes
+///
+/// # Arguments
+/// * `image_data` - Input image bytes
+/// * `options_json` - Optional mode and zebra settings, e.g. `{"mode":"zebra","zebra_threshold":90}`
+#[wasm_bindgen]
+pub fn false_color(image_data: &[u8], options_json: Option<String>) -> Result<Vec<u8>, JsValue> {
+    let options = parse_options(options_json, FalseColorOptions::from_json)?;
+    let img = load_image(image_data)?;
+    Ok(to_bytes(&overlays::false_color(&img, &options)?)?)
+}
+
+/// Parse optional JSON options, falling back to the defaults
+fn parse_options<T: Default>(
+    options_json: Option<String>,
+    parse: fn(&str) -> Result<T, EditorError>,
+) -> Result<T, EditorError> {
+    options_json.map_or_else(|| Ok(T::default()), |json| parse(&json))
+}
+
+/// Run a JSON edit pipeline on the image in a single call
+///
+/// The image is decoded once, every operation is applied in order to the same
+/// float buffer, and the result is encoded once. Runs of point operations such
+/// as brightness, levels and white balance share one pass. The result is a PNG
+/// with the bit depth and metadata of the input, so 16-bit sources stay 16-bit.
+/// See [`crate::pipeline`] for the format.
+///
+/// # Arguments
+/// * `image_data` - Input image bytes
+/// * `pipeline_json` - Pipeline description (`{"version":2,"operations":[...]}`)
+///
+/// # Returns
+/// Processed image bytes, or an `EditorError` naming the invalid step
+#[wasm_bindgen]
+pub fn process_pipeline(image_data: &[u8], pipeline_json: &str) -> Result<Vec<u8>, JsValue> {
+    let pipeline = Pipeline::from_json(pipeline_json)?.compile()?;
+    let format = ExportFormat::png(source_bit_depth(image_data)?);
+    let (mut img, metadata): (Rgba32FImage, _) = load_image_with(image_data, &LoadOptions::default())?;
+    pipeline.execute(&mut img)?;
+    Ok(formats::encode_with_metadata(&img, &format, &metadata)?)
+}
+
+/// Bake the point operations of a pipeline into a `.cube` LUT
+///
+/// The look of the sliders can then be applied in other tools.
+///
+/// # Arguments
+/// * `pipeline_json` - Pipeline of point operations only (see [`crate::pipeline`])
+/// * `size` - Grid points along each axis (2 to 256, 33 when omitted)
+/// * `title` - Optional `TITLE` written into the file
+///
+/// # Returns
+/// Text of the `.cube` file, or an `EditorError` naming a step that is not a
+/// point operation
+#[wasm_bindgen]
+pub fn export_cube(pipeline_json: &str, size: Option<u32>, title: Option<String>) -> Result<String, JsValue> {
+    let pipeline = Pipeline::from_json(pipeline_json)?;
+    let mut lut = CubeLut::bake(&pipeline.operations, size.map_or(CUBE_SIZE, |size| size as usize))?;
+    lut.title = title;
+    Ok(lut.to_string())
+}
+
+/// Re-encode image bytes in another format
+///
+/// The pixels are decoded at full precision, so a 16-bit source exported with
+/// `"bit_depth":16` keeps every level.
+///
+/// # Arguments
+/// * `image_data` - Input image bytes
+/// * `options_json` - Export settings, e.g. `{"format":"jpeg","quality":85}`, and
+///   the metadata to keep, e.g. `"metadata":{"strip_gps":true}` (see [`crate::formats`])
+///
+/// # Returns
+/// Encoded image bytes
+#[wasm_bindgen]
+pub fn export_image(image_data: &[u8], options_json: &str) -> Result<Vec<u8>, JsValue> {
+    let options = ExportOptions::from_json(options_json)?;
+    let (img, metadata): (Rgba32FImage, _) = load_image_with(image_data, &LoadOptions::default())?;
+    Ok(formats::encode_with_metadata(&img, &options.format, &metadata.filtered(&options.metadata))?)
+}
+
+/// Read the common EXIF fields without decoding the pixels
+///
+/// # Arguments
+/// * `image_data` - Input image bytes (JPEG, PNG or WebP)
+///
+/// # Returns
+/// An object with `make`, `model`, `lens_model`, `exposure_time`, `f_number`,
+/// `iso`, `focal_length`, `date_time_original`, `orientation` and `gps`, or
+/// `null` when the file has no EXIF
+#[wasm_bindgen]
+pub fn read_exif(image_data: &[u8]) -> Result<JsValue, JsValue> {
+    to_js(&Metadata::read(image_data).exif_info()?)
+}
+
+/// Convert a serializable value to a plain JavaScript object (`None` becomes `null`)
+fn to_js<T: serde::Serialize>(value: &T) -> Result<JsValue, JsValue> {
+    let json = serde_json::to_string(value).map_err(|e| EditorError::Encode(e.to_string()))?;
+    js_sys::JSON::parse(&json)
+}
+
+/// Formats this build can decode, e.g. `["png", "jpeg", "webp"]`
+#[wasm_bindgen]
+pub fn supported_import_formats() -> Vec<String> {
+    formats::import_formats().into_iter().map(String::from).collect()
+}
+
+/// Formats this build can encode with `export_image`
+#[wasm_bindgen]
+pub fn supported_export_formats() -> Vec<String> {
+    formats::export_formats().into_iter().map(String::from).collect()
+}
+
+/// JavaScript handle around [`EditorImage`]
+///
+/// Exported as `EditorImage`: decode once, chain edits in memory and only
+/// encode on `export()`.
+#[wasm_bindgen(js_name = EditorImage)]
+pub struct JsEditorImage {
+    inner: EditorImage,
+}
+
+impl JsEditorImage {
+    /// Borrow the wrapped editor
+    pub fn editor(&self) -> &EditorImage {
+        &self.inner
+    }
+
+    fn apply(&mut self, operation: Operation) -> Result<(), JsValue> {
+        Ok(self.inner.apply_operation(&operation)?)
+    }
+}
+
+#[wasm_bindgen(js_class = EditorImage)]
+impl JsEditorImage {
+    /// Decode image bytes into a new editing handle
+    ///
+    /// # Arguments
+    /// * `image_data` - Input image bytes
+    /// * `auto_orient` - Apply the EXIF orientation to the pixels (default `true`)
+    #[wasm_bindgen(constructor)]
+    pub fn new(image_data: &[u8], auto_orient: Option<bool>) -> Result<JsEditorImage, JsValue> {
+        let options = LoadOptions { auto_orient: auto_orient.unwrap_or(true) };
+        Ok(JsEditorImage { inner: EditorImage::decode_with(image_data, &options)? })
+    }
+
+    /// Common EXIF fields of the source file, `null` without EXIF (see `read_exif`)
+    pub fn exif(&self) -> Result<JsValue, JsValue> {
+        to_js(&self.inner.exif_info()?)
+    }
+
+    /// Current width in pixels
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> u32 {
+        self.inner.width()
+    }
+
+    /// Current height in pixels
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> u32 {
+        self.inner.height()
+    }
+
+    /// Discard all edits and restore the decoded source image
+    pub fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    /// Make the current state the new source image used by `reset`
+    pub fn commit(&mut self) {
+        self.inner.commit();
+    }
+
+    /// Encode the current image as PNG bytes, keeping the source metadata
+    pub fn export(&self) -> Result<Vec<u8>, JsValue> {
+        Ok(self.inner.encode()?)
+    }
+
+    /// Encode the current image with explicit export settings
+    ///
+    /// # Arguments
+    /// * `options_json` - Export settings, e.g. `{"format":"webp","metadata":{"exif":false}}`
+    pub fn export_as(&self, options_json: &str) -> Result<Vec<u8>, JsValue> {
+        let options = ExportOptions::from_json(options_json)?;
+        Ok(self.inner.encode_as(&options)?)
+    }
+
+    pub fn adjust_brightness(&mut self, value: i32) -> Result<(), JsValue> {
+        self.apply(Operation::Brightness { value })
+    }
+
+    pub fn adjust_contrast(&mut self, value: f32) -> Result<(), JsValue> {
+        self.apply(Operation::Contrast { value })
+    }
+
+    pub fn adjust_saturation(&mut self, value: f32, model: Option<String>) -> Result<(), JsValue> {
+        self.apply(saturation_operation(value, model)?)
+    }
+
+    pub fn hue_rotate(&mut self, degrees: f32) -> Result<(), JsValue> {
+        self.apply(Operation::HueRotate { degrees })
+    }
+
+    pub fn adjust_white_balance(&mut self, value: f32) -> Result<(), JsValue> {
+        self.apply(Operation::WhiteBalance { value })
+    }
+
+    /// White balance from temperature and tint (see `apply_white_balance`)
+    pub fn apply_white_balance(&mut self, temperature: f32, tint: f32) -> Result<(), JsValue> {
+        self.apply(Operation::ColorTemperature(WhiteBalance { temperature, tint }))
+    }
+
+    /// Measure `{ temperature, tint }` that neutralizes an area of the current image
+    pub fn pick_neutral(&self, x: u32, y: u32, width: u32, height: u32) -> Result<JsValue, JsValue> {
+        to_js(&self.inner.pick_neutral(x, y, width, height)?)
+    }
+
+    pub fn adjust_hue(&mut self, shift: f32) -> Result<(), JsValue> {
+        self.apply(Operation::Hue { shift })
+    }
+
+    pub fn adjust_exposure(&mut self, stops: f32) -> Result<(), JsValue> {
+        self.apply(Operation::Exposure { stops })
+    }
+
+    pub fn adjust_vibrance(&mut self, amount: f32) -> Result<(), JsValue> {
+        self.apply(Operation::Vibrance { amount })
+    }
+
+    pub fn adjust_highlights(&mut self, amount: f32) -> Result<(), JsValue> {
+        self.apply(Operation::Highlights { amount })
+    }
+
+    pub fn adjust_shadows(&mut self, amount: f32) -> Result<(), JsValue> {
+        self.apply(Operation::Shadows { amount })
+    }
+
+    pub fn adjust_curves(&mut self, red_gamma: f32, green_gamma: f32, blue_gamma: f32) -> Result<(), JsValue> {
+        self.apply(Operation::Curves { red_gamma, green_gamma, blue_gamma })
+    }
+
+    /// Apply spline tone curves (same JSON as `apply_tone_curves`)
+    pub fn apply_tone_curves(&mut self, curves_json: &str) -> Result<(), JsValue> {
+        self.apply(Operation::ToneCurve(ToneCurves::from_json(curves_json)?))
+    }
+
+    pub fn adjust_levels(&mut self, black_point: u8, white_point: u8, gamma: f32) -> Result<(), JsValue> {
+        self.apply(Operation::Levels { black_point, white_point, gamma })
+    }
+
+    /// Apply per-channel levels (same JSON as `apply_levels`)
+    pub fn apply_levels(&mut self, levels_json: &str) -> Result<(), JsValue> {
+        self.apply(Operation::ChannelLevels(Levels::from_json(levels_json)?))
+    }
+
+    /// Apply auto levels and return the levels used, in the `apply_levels` format
+    pub fn auto_levels(&mut self, clip_percent: f32) -> Result<JsValue, JsValue> {
+        to_js(&self.inner.auto_levels(clip_percent)?)
+    }
+
+    /// Recommend slider values for the source image (see `analyze_auto_adjustments`)
+    pub fn analyze_auto_adjustments(&self) -> Result<JsValue, JsValue> {
+        to_js(&self.inner.analyze_auto_adjustments())
+    }
+
+    pub fn histogram_equalization(&mut self) -> Result<(), JsValue> {
+        self.apply(Operation::HistogramEqualization)
+    }
+
+    pub fn clahe(&mut self, tiles_x: u32, tiles_y: u32, clip_limit: f32) -> Result<(), JsValue> {
+        self.apply(Operation::Clahe(ClaheOptions { tiles_x, tiles_y, clip_limit }))
+    }
+
+    pub fn to_grayscale(&mut self) -> Result<(), JsValue> {
+        self.apply(Operation::Grayscale)
+    }
+
+    pub fn apply_sepia(&mut self) -> Result<(), JsValue> {
+        self.apply(Operation::Sepia)
+    }
+
+    pub fn gaussian_blur(&mut self, sigma: f32) -> Result<(), JsValue> {
+        self.apply(Operation::GaussianBlur { sigma })
+    }
+
+    pub fn sharpen(&mut self, amount: f32, border: Option<String>) -> Result<(), JsValue> {
+        self.apply(Operation::Sharpen { amount, border: parse_border(border)? })
+    }
+
+    pub fn unsharp_mask(
+        &mut self,
+        radius: f32,
+        amount: f32,
+        threshold: f32,
+        luminance_only: bool,
+        border: Option<String>,
+    ) -> Result<(), JsValue> {
+        let border = parse_border(border)?;
+        self.apply(Operation::UnsharpMask(UnsharpMaskOptions { radius, amount, threshold, luminance_only, border }))
+    }
+
+    pub fn apply_vignette(&mut self, strength: f32, radius: f32) -> Result<(), JsValue> {
+        self.apply(Operation::Vignette { strength, radius })
+    }
+
+    pub fn reduce_noise(&mut self, strength: f32, border: Option<String>) -> Result<(), JsValue> {
+        self.apply(Operation::ReduceNoise { strength, border: parse_border(border)? })
+    }
+
+    pub fn denoise(&mut self, luminance: f32, chroma: f32, radius: u32) -> Result<(), JsValue> {
+        self.apply(Operation::Denoise(DenoiseOptions { luminance, chroma, radius }))
+    }
+
+    pub fn apply_emboss(&mut self, border: Option<String>) -> Result<(), JsValue> {
+        self.apply(Operation::Emboss { border: parse_border(border)? })
+    }
+
+    pub fn apply_kernel(
+        &mut self,
+        kernel: Vec<f32>,
+        width: u32,
+        height: u32,
+        divisor: Option<f32>,
+        bias: f32,
+        channels: Option<String>,
+    ) -> Result<(), JsValue> {
+        self.apply(Operation::Convolve(kernel_options(kernel, width, height, divisor, bias, channels)?))
+    }
+
+    /// Convolve with a custom or named kernel (same JSON as `convolve`)
+    pub fn convolve(&mut self, options_json: &str) -> Result<(), JsValue> {
+        self.apply(Operation::Convolve(ConvolveOptions::from_json(options_json)?))
+    }
+
+    /// Apply a color grade from the text of a `.cube` file (same arguments as `apply_lut`)
+    pub fn apply_lut(&mut self, cube: &str, intensity: f32, interpolation: Option<String>) -> Result<(), JsValue> {
+        self.apply(lut_operation(cube, intensity, interpolation)?)
+    }
+
+    pub fn rotate(&mut self, angle: u32) -> Result<(), JsValue> {
+        self.apply(Operation::Rotate { angle })
+    }
+
+    pub fn rotate_arbitrary(
+        &mut self,
+        angle: f32,
+        background: Option<Vec<u8>>,
+        interpolation: Option<String>,
+        auto_crop: Option<bool>,
+    ) -> Result<(), JsValue> {
+        let operation = rotate_operation(angle, background, interpolation, auto_crop)?;
+        self.apply(operation)
+    }
+
+    pub fn flip_horizontal(&mut self) -> Result<(), JsValue> {
+        self.apply(Operation::FlipHorizontal)
+    }
+
+    pub fn flip_vertical(&mut self) -> Result<(), JsValue> {
+        self.apply(Operation::FlipVertical)
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), JsValue> {
+        self.apply(Operation::Resize { width, height })
+    }
+
+    pub fn crop(&mut self, x: u32, y: u32, width: u32, height: u32) -> Result<(), JsValue> {
+        self.apply(Operation::Crop { x, y, width, height })
+    }
+
+    /// Apply a JSON edit pipeline (see [`crate::pipeline`]) to the current image
+    pub fn apply_pipeline(&mut self, pipeline_json: &str) -> Result<(), JsValue> {
+        let pipeline = Pipeline::from_json(pipeline_json)?;
+        Ok(self.inner.run_pipeline(&pipeline)?)
+    }
+
+    /// Histogram of the current image (same layout as `calculate_histogram`)
+    pub fn calculate_histogram(&self) -> Vec<u32> {
+        self.inner.histogram()
+    }
+
+    /// Statistics of the current image (same options and result as `image_statistics`)
+    pub fn image_statistics(&self, options_json: Option<String>) -> Result<JsValue, JsValue> {
+        to_js(&self.inner.statistics(&parse_options(options_json, StatisticsOptions::from_json)?)?)
+    }
+
+    /// Clipping flags of the current image (same options and layout as `clipping_mask`)
+    pub fn clipping_mask(&self, options_json: Option<String>) -> Result<Vec<u8>, JsValue> {
+        let options = parse_options(options_json, ClippingOptions::from_json)?;
+        Ok(overlays::clipping_mask(self.inner.image(), &options)?)
+    }
+
+    /// Clipping overlay of the current image as PNG bytes (see `clipping_overlay`)
+    pub fn clipping_overlay(&self, options_json: Option<String>) -> Result<Vec<u8>, JsValue> {
+        let options = parse_options(options_json, ClippingOptions::from_json)?;
+        Ok(to_bytes(&overlays::clipping_overlay(self.inner.image(), &options)?)?)
+    }
+
+    /// False-color view of the current image as PNG bytes (see `false_color`)
+    pub fn false_color(&self, options_json: Option<String>) -> Result<Vec<u8>, JsValue> {
+        let options = parse_options(options_json, FalseColorOptions::from_json)?;
+        Ok(to_bytes(&overlays::false_color(self.inner.image(), &options)?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageBuffer, ImageFormat, Rgb};
+    use std::io::Cursor;
+
+    fn create_test_image() -> DynamicImage {
+        let img = ImageBuffer::from_fn(100, 100, |x, y| {
+            Rgb([(x % 256) as u8, (y % 256) as u8, 128])
+        });
+        DynamicImage::ImageRgb8(img)
+    }
+
+    fn image_to_bytes(img: &DynamicImage) -> Vec<u8> {
+        let mut buffer = Cursor::new(Vec::new());
+        img.write_to(&mut buffer, ImageFormat::Png).unwrap();
+        buffer.into_inner()
+    }
+
+    #[test]
+    fn test_adjust_brightness() {
+        let test_img = create_test_image();
+        let input_bytes = image_to_bytes(&test_img);
+
+        let result = adjust_brightness(&input_bytes, 50).unwrap();
+        assert!(!result.is_empty());
+
+        // Should be able to load the result
+        let processed = image::load_from_memory(&result).unwrap();
+        assert_eq!(processed.width(), 100);
+        assert_eq!(processed.height(), 100);
+    }
+
+    #[test]
+    fn test_adjust_contrast() {
+        let test_img = create_test_image();
+        let input_bytes = image_to_bytes(&test_img);
+
+        let result = adjust_contrast(&input_bytes, 1.5).unwrap();
+        assert!(!result.is_empty());
+
+        let processed = image::load_from_memory(&result).unwrap();
+        assert_eq!(processed.width(), 100);
+        assert_eq!(processed.height(), 100);
+    }
+
+    #[test]
+    fn test_to_grayscale() {
+        let test_img = create_test_image();
+        let input_bytes = image_to_bytes(&test_img);
+
+        let result = to_grayscale(&input_bytes).unwrap();
+        assert!(!result.is_empty());
+
+        let processed = image::load_from_memory(&result).unwrap();
+        assert_eq!(processed.width(), 100);
+        assert_eq!(processed.height(), 100);
+    }
+
+    #[test]
+    fn test_gaussian_blur() {
+        let test_img = create_test_image();
+        let input_bytes = image_to_bytes(&test_img);
+
+        let result = gaussian_blur(&input_bytes, 2.0).unwrap();
+        assert!(!result.is_empty());
+
+        let processed = image::load_from_memory(&result).unwrap();
+        assert_eq!(processed.width(), 100);
+        assert_eq!(processed.height(), 100);
+    }
+
+    #[test]
+    fn test_rotate() {
+        let test_img = create_test_image();
+        let input_bytes = image_to_bytes(&test_img);
+
+        let result = rotate(&input_bytes, 90).unwrap();
+        assert!(!result.is_empty());
+
+        let processed = image::load_from_memory(&result).unwrap();
+        // After 90 degree rotation, dimensions should be swapped
+        assert_eq!(processed.width(), 100);
+        assert_eq!(processed.height(), 100);
+    }
+
+    #[test]
+    fn test_flip_horizontal() {
+        let test_img = create_test_image();
+        let input_bytes = image_to_bytes(&test_img);
+
+        let result = flip_horizontal(&input_bytes).unwrap();
+        assert!(!result.is_empty());
+
+        let processed = image::load_from_memory(&result).unwrap();
+        assert_eq!(processed.width(), 100);
+        assert_eq!(processed.height(), 100);
+    }
+
+    #[test]
+    fn test_resize() {
+        let test_img = create_test_image();
+        let input_bytes = image_to_bytes(&test_img);
+
+        let result = resize(&input_bytes, 50, 50).unwrap();
+        assert!(!result.is_empty());
+
+        let processed = image::load_from_memory(&result).unwrap();
+        assert_eq!(processed.width(), 50);
+        assert_eq!(processed.height(), 50);
+    }
+
+    #[test]
+    fn test_apply_sepia() {
+        let test_img = create_test_image();
+        let input_bytes = image_to_bytes(&test_img);
+
+        let result = apply_sepia(&input_bytes).unwrap();
+        assert!(!result.is_empty());
+
+        let processed = image::load_from_memory(&result).unwrap();
+        assert_eq!(processed.width(), 100);
+        assert_eq!(processed.height(), 100);
+    }
+
+    #[test]
+    fn test_chained_operations_match_byte_exports() {
+        let bytes = image_to_bytes(&create_test_image());
+
+        let mut editor = JsEditorImage::new(&bytes, None).unwrap();
+        editor.adjust_brightness(20).unwrap();
+        editor.adjust_contrast(1.5).unwrap();
+        editor.apply_sepia().unwrap();
+
+        let step = adjust_brightness(&bytes, 20).unwrap();
+        let step = adjust_contrast(&step, 1.5).unwrap();
+        let chained = apply_sepia(&step).unwrap();
+        let from_exports = load_image(&chained).unwrap();
+
+        // The byte exports round to 8 bits after every step, the editor only at the end
+        let from_editor = editor.editor().to_rgba8();
+        for (a, b) in from_editor.pixels().zip(from_exports.pixels()) {
+            assert!(a.0.iter().zip(b.0.iter()).all(|(a, b)| a.abs_diff(*b) <= 2), "{:?} {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_byte_exports_keep_metadata() {
+        let metadata = Metadata { exif: Some(crate::metadata::tests::sample_exif(1)), ..Metadata::default() };
+        let bytes = formats::encode_with_metadata(&create_test_image().to_rgba8(), &ExportFormat::default(), &metadata).unwrap();
+
+        let brightened = adjust_brightness(&bytes, 20).unwrap();
+        assert_eq!(Metadata::read(&brightened).exif, metadata.exif);
+        let piped = process_pipeline(&bytes, r#"{ "version": 2, "operations": [{ "op": "gaussian_blur", "sigma": 1.0 }] }"#).unwrap();
+        assert_eq!(Metadata::read(&piped).exif, metadata.exif);
+    }
+
+    #[test]
+    fn test_pipeline_keeps_16_bit_sources() {
+        let deep = DynamicImage::ImageRgb16(create_test_image().to_rgb16());
+        let operations = r#"{ "version": 2, "operations": [{ "op": "brightness", "value": 0 }] }"#;
+
+        let result = image::load_from_memory(&process_pipeline(&image_to_bytes(&deep), operations).unwrap()).unwrap();
+        assert_eq!(result.color(), image::ColorType::Rgb16);
+        assert_eq!(result.to_rgb16(), deep.to_rgb16());
+
+        let result = image::load_from_memory(&process_pipeline(&image_to_bytes(&create_test_image()), operations).unwrap()).unwrap();
+        assert_eq!(result.color(), image::ColorType::Rgb8);
+    }
+}
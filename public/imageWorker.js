@@ -41,157 +41,31 @@ async function initializeWasm() {
 
 /**
  * Process image with given parameters
+ *
+ * The adjustments object is translated into a declarative pipeline and
+ * executed in a single WASM call on one decoded buffer.
  */
 async function processImage(imageData, adjustments, taskId) {
   if (!isWasmInitialized) {
     throw new Error('WASM module not initialized');
   }
   
-  let processedData = new Uint8Array(imageData);
-  const totalSteps = calculateTotalSteps(adjustments);
-  let currentStep = 0;
+  let editor = null;
   
   try {
-    // Step 1: Basic adjustments (fast operations)
-    if (needsBasicAdjustments(adjustments)) {
-      self.postMessage({
-        type: 'PROCESSING_PROGRESS',
-        taskId,
-        progress: Math.round((currentStep / totalSteps) * 100),
-        message: '基本調整を適用中...'
-      });
-      
-      if (adjustments.brightness !== 0) {
-        processedData = wasmModule.adjust_brightness(processedData, adjustments.brightness);
-        currentStep++;
-        updateProgress(taskId, currentStep, totalSteps, '明度調整完了');
-      }
-      
-      if (adjustments.contrast !== 0) {
-        processedData = wasmModule.adjust_contrast(processedData, adjustments.contrast * 0.1);
-        currentStep++;
-        updateProgress(taskId, currentStep, totalSteps, 'コントラスト調整完了');
-      }
-      
-      if (adjustments.saturation !== 0) {
-        processedData = wasmModule.adjust_saturation(processedData, adjustments.saturation * 0.1);
-        currentStep++;
-        updateProgress(taskId, currentStep, totalSteps, '彩度調整完了');
-      }
-      
-      if (adjustments.temperature !== 0) {
-        processedData = wasmModule.adjust_white_balance(processedData, adjustments.temperature);
-        currentStep++;
-        updateProgress(taskId, currentStep, totalSteps, '色温度調整完了');
-      }
-    }
+    const pipeline = buildPipeline(adjustments);
     
-    // Yield control to allow other tasks
-    await yieldToMain();
-    
-    // Step 2: Color adjustments
-    if (adjustments.hue !== 0) {
-      updateProgress(taskId, currentStep, totalSteps, '色相調整を適用中...');
-      processedData = wasmModule.adjust_hue(processedData, adjustments.hue);
-      currentStep++;
-      updateProgress(taskId, currentStep, totalSteps, '色相調整完了');
-      await yieldToMain();
-    }
-    
-    if (adjustments.exposure !== 0) {
-      updateProgress(taskId, currentStep, totalSteps, '露出調整を適用中...');
-      processedData = wasmModule.adjust_exposure(processedData, adjustments.exposure);
-      currentStep++;
-      updateProgress(taskId, currentStep, totalSteps, '露出調整完了');
-      await yieldToMain();
-    }
-    
-    if (adjustments.vibrance !== 0) {
-      updateProgress(taskId, currentStep, totalSteps, 'バイブランス調整を適用中...');
-      processedData = wasmModule.adjust_vibrance(processedData, adjustments.vibrance);
-      currentStep++;
-      updateProgress(taskId, currentStep, totalSteps, 'バイブランス調整完了');
-      await yieldToMain();
-    }
-    
-    // Step 3: Advanced adjustments
-    if (adjustments.highlights !== 0) {
-      updateProgress(taskId, currentStep, totalSteps, 'ハイライト調整を適用中...');
-      processedData = wasmModule.adjust_highlights(processedData, adjustments.highlights);
-      currentStep++;
-      updateProgress(taskId, currentStep, totalSteps, 'ハイライト調整完了');
-      await yieldToMain();
-    }
-    
-    if (adjustments.shadows !== 0) {
-      updateProgress(taskId, currentStep, totalSteps, 'シャドウ調整を適用中...');
-      processedData = wasmModule.adjust_shadows(processedData, adjustments.shadows);
-      currentStep++;
-      updateProgress(taskId, currentStep, totalSteps, 'シャドウ調整完了');
-      await yieldToMain();
-    }
-    
-    if (needsCurveAdjustments(adjustments)) {
-      updateProgress(taskId, currentStep, totalSteps, 'カラーカーブを適用中...');
-      processedData = wasmModule.adjust_curves(
-        processedData, 
-        adjustments.redGamma || 1.0, 
-        adjustments.greenGamma || 1.0, 
-        adjustments.blueGamma || 1.0
-      );
-      currentStep++;
-      updateProgress(taskId, currentStep, totalSteps, 'カラーカーブ調整完了');
-      await yieldToMain();
-    }
-    
-    if (needsLevelsAdjustments(adjustments)) {
-      updateProgress(taskId, currentStep, totalSteps, 'レベル補正を適用中...');
-      processedData = wasmModule.adjust_levels(
-        processedData, 
-        adjustments.blackPoint || 0, 
-        adjustments.whitePoint || 255, 
-        adjustments.gamma || 1.0
-      );
-      currentStep++;
-      updateProgress(taskId, currentStep, totalSteps, 'レベル補正完了');
-      await yieldToMain();
-    }
-    
-    // Step 4: Filters (heavy operations)
-    if (adjustments.blur && adjustments.blur > 0) {
-      updateProgress(taskId, currentStep, totalSteps, 'ブラー効果を適用中...');
-      processedData = wasmModule.gaussian_blur(processedData, adjustments.blur);
-      currentStep++;
-      updateProgress(taskId, currentStep, totalSteps, 'ブラー効果完了');
-      await yieldToMain();
-    }
-    
-    if (adjustments.sharpen && adjustments.sharpen > 0) {
-      updateProgress(taskId, currentStep, totalSteps, 'シャープ効果を適用中...');
-      processedData = wasmModule.sharpen(processedData, adjustments.sharpen);
-      currentStep++;
-      updateProgress(taskId, currentStep, totalSteps, 'シャープ効果完了');
-      await yieldToMain();
-    }
-    
-    if (adjustments.vignetteStrength && adjustments.vignetteStrength > 0) {
-      updateProgress(taskId, currentStep, totalSteps, 'ビネット効果を適用中...');
-      processedData = wasmModule.apply_vignette(
-        processedData, 
-        adjustments.vignetteStrength, 
-        adjustments.vignetteRadius || 0.8
-      );
-      currentStep++;
-      updateProgress(taskId, currentStep, totalSteps, 'ビネット効果完了');
-      await yieldToMain();
-    }
+    self.postMessage({
+      type: 'PROCESSING_PROGRESS',
+      taskId,
+      progress: 0,
+      message: `${pipeline.operations.length}件の調整を適用中...`
+    });
     
-    if (adjustments.noiseReduction && adjustments.noiseReduction > 0) {
-      updateProgress(taskId, currentStep, totalSteps, 'ノイズ除去を適用中...');
-      processedData = wasmModule.reduce_noise(processedData, adjustments.noiseReduction);
-      currentStep++;
-      updateProgress(taskId, currentStep, totalSteps, 'ノイズ除去完了');
-    }
+    // Decode once, run every operation in Rust, encode once
+    editor = new wasmModule.EditorImage(new Uint8Array(imageData));
+    editor.apply_pipeline(JSON.stringify(pipeline));
+    const processedData = editor.export();
     
     // Complete processing
     self.postMessage({
@@ -203,44 +77,102 @@ async function processImage(imageData, adjustments, taskId) {
     });
     
   } catch (error) {
+    // EditorError from WASM carries a machine-readable code and the offending parameter
     self.postMessage({
       type: 'PROCESSING_ERROR',
       taskId,
-      error: error.message
+      error: error.message,
+      code: error.code || null,
+      parameter: error.parameter || null
     });
+  } finally {
+    if (editor) {
+      editor.free();
+    }
   }
 }
 
 /**
  * Helper functions
  */
-function calculateTotalSteps(adjustments) {
-  let steps = 0;
+const PIPELINE_VERSION = 2;
+
+/**
+ * Translate the UI adjustments object into a pipeline description
+ * (see `crates/image-app/src/pipeline.rs` for the format)
+ */
+function buildPipeline(adjustments) {
+  const operations = [];
+  
+  // Basic adjustments
+  if (adjustments.brightness !== 0) {
+    operations.push({ op: 'brightness', value: Math.round(adjustments.brightness) });
+  }
+  if (adjustments.contrast !== 0) {
+    operations.push({ op: 'contrast', value: adjustments.contrast * 0.1 });
+  }
+  if (adjustments.saturation !== 0) {
+    // Same scale as the adjust_saturation preview: -100 (grayscale) to +100 (double)
+    operations.push({ op: 'saturation', value: adjustments.saturation * 0.1 });
+  }
+  if (adjustments.temperature !== 0) {
+    operations.push({ op: 'white_balance', value: adjustments.temperature });
+  }
   
-  if (adjustments.brightness !== 0) steps++;
-  if (adjustments.contrast !== 0) steps++;
-  if (adjustments.saturation !== 0) steps++;
-  if (adjustments.temperature !== 0) steps++;
-  if (adjustments.hue !== 0) steps++;
-  if (adjustments.exposure !== 0) steps++;
-  if (adjustments.vibrance !== 0) steps++;
-  if (adjustments.highlights !== 0) steps++;
-  if (adjustments.shadows !== 0) steps++;
-  if (needsCurveAdjustments(adjustments)) steps++;
-  if (needsLevelsAdjustments(adjustments)) steps++;
-  if (adjustments.blur && adjustments.blur > 0) steps++;
-  if (adjustments.sharpen && adjustments.sharpen > 0) steps++;
-  if (adjustments.vignetteStrength && adjustments.vignetteStrength > 0) steps++;
-  if (adjustments.noiseReduction && adjustments.noiseReduction > 0) steps++;
+  // Color adjustments
+  if (adjustments.hue !== 0) {
+    operations.push({ op: 'hue', shift: adjustments.hue });
+  }
+  if (adjustments.exposure !== 0) {
+    operations.push({ op: 'exposure', stops: adjustments.exposure });
+  }
+  if (adjustments.vibrance !== 0) {
+    operations.push({ op: 'vibrance', amount: adjustments.vibrance });
+  }
   
-  return Math.max(steps, 1);
-}
-
-function needsBasicAdjustments(adjustments) {
-  return adjustments.brightness !== 0 || 
-         adjustments.contrast !== 0 || 
-         adjustments.saturation !== 0 || 
-         adjustments.temperature !== 0;
+  // Advanced adjustments
+  if (adjustments.highlights !== 0) {
+    operations.push({ op: 'highlights', amount: adjustments.highlights });
+  }
+  if (adjustments.shadows !== 0) {
+    operations.push({ op: 'shadows', amount: adjustments.shadows });
+  }
+  if (needsCurveAdjustments(adjustments)) {
+    operations.push({
+      op: 'curves',
+      red_gamma: adjustments.redGamma || 1.0,
+      green_gamma: adjustments.greenGamma || 1.0,
+      blue_gamma: adjustments.blueGamma || 1.0
+    });
+  }
+  if (needsLevelsAdjustments(adjustments)) {
+    operations.push({
+      op: 'levels',
+      black_point: adjustments.blackPoint || 0,
+      white_point: adjustments.whitePoint || 255,
+      gamma: adjustments.gamma || 1.0
+    });
+  }
+  
+  // Filters (heavy operations)
+  if (adjustments.blur && adjustments.blur > 0) {
+    operations.push({ op: 'gaussian_blur', sigma: adjustments.blur });
+  }
+  if (adjustments.sharpen && adjustments.sharpen > 0) {
+    operations.push({ op: 'sharpen', amount: adjustments.sharpen });
+  }
+  if (adjustments.vignetteStrength && adjustments.vignetteStrength > 0) {
+    operations.push({
+      op: 'vignette',
+      strength: adjustments.vignetteStrength,
+      radius: adjustments.vignetteRadius || 0.8
+    });
+  }
+  if (adjustments.noiseReduction && adjustments.noiseReduction > 0) {
+    operations.push({ op: 'reduce_noise', strength: adjustments.noiseReduction });
+  }
+  
+  return { version: PIPELINE_VERSION, operations };
 }
 
 function needsCurveAdjustments(adjustments) {
@@ -255,20 +187,6 @@ function needsLevelsAdjustments(adjustments) {
          (adjustments.gamma && adjustments.gamma !== 1.0);
 }
 
-function updateProgress(taskId, currentStep, totalSteps, message) {
-  const progress = Math.round((currentStep / totalSteps) * 100);
-  self.postMessage({
-    type: 'PROCESSING_PROGRESS',
-    taskId,
-    progress,
-    message
-  });
-}
-
-async function yieldToMain() {
-  return new Promise(resolve => setTimeout(resolve, 0));
-}
-
 /**
  * Process task queue
  */